@@ -5,9 +5,11 @@ use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use cw721::{
     AllNftInfoResponse, ApprovedForAllResponse, ContractInfoResponse, NftInfoResponse, OwnerOfResponse,
-    NumTokensResponse, TokensResponse,
+    NumTokensResponse, RankSupplyResponse, TokensResponse,
+};
+use cw721_base::msg::{
+    HandleMsg, InitMsg, LockInfoResponse, MinterResponse, MintersResponse, QueryMsg, RanksResponse,
 };
-use cw721_base::msg::{HandleMsg, InitMsg, MinterResponse, QueryMsg};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -21,9 +23,13 @@ fn main() {
     export_schema(&schema_for!(AllNftInfoResponse), &out_dir);
     export_schema(&schema_for!(ApprovedForAllResponse), &out_dir);
     export_schema(&schema_for!(ContractInfoResponse), &out_dir);
+    export_schema(&schema_for!(LockInfoResponse), &out_dir);
     export_schema(&schema_for!(MinterResponse), &out_dir);
+    export_schema(&schema_for!(MintersResponse), &out_dir);
     export_schema(&schema_for!(NftInfoResponse), &out_dir);
     export_schema(&schema_for!(OwnerOfResponse), &out_dir);
     export_schema(&schema_for!(NumTokensResponse), &out_dir);
     export_schema(&schema_for!(TokensResponse), &out_dir);
+    export_schema(&schema_for!(RanksResponse), &out_dir);
+    export_schema(&schema_for!(RankSupplyResponse), &out_dir);
 }
@@ -1,28 +1,38 @@
 use cosmwasm_std::{
-    from_binary, log, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr,
-    InitResponse, Order, Querier, StdError, StdResult, Storage, WasmMsg, Uint128,
+    log, to_binary, Api, BlockInfo, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr,
+    InitResponse, Order, Querier, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
 };
 
 use cw0::{calc_range_start_human, calc_range_start_string};
 use cw2::set_contract_version;
 use cw721::{
-    AllNftInfoResponse, ApprovedForAllResponse, Expiration, NftInfoResponse, OwnerOfResponse,
-    NumTokensResponse, TokensResponse,
+    AllNftInfoResponse, ApprovedForAllResponse, Cw721ReceiveMsg, Expiration, NftInfoResponse, OwnerOfResponse,
+    NumTokensResponse, RankSupplyResponse, TokenStats, TokensResponse, Trait,
 };
 
-use crate::msg::{HandleMsg, InitMsg, MinterResponse, QueryMsg};
+use crate::msg::{
+    CapUpdate, HandleMsg, InitMsg, LockInfoResponse, MintMsg, MinterItem, MinterResponse, MintersResponse,
+    QueryMsg, RanksResponse,
+};
 use crate::state::{
     contract_info, contract_info_read, mint, mint_read, operators,
     operators_read, tokens, tokens_read, Approval, TokenInfo, ContractInfo,
-    base_num_tokens, increment_base_tokens,
-    silver_num_tokens, increment_silver_tokens,
-    gold_num_tokens, increment_gold_tokens,
+    frozen, is_frozen, finalized, is_finalized,
+    ranks, ranks_read, load_rank, rank_tokens, rank_tokens_read, owner_tokens, owner_tokens_read,
+    owner_rank_tokens, owner_rank_tokens_read,
+    rank_num_tokens, increment_rank_tokens, decrement_rank_tokens,
+    burned_num_tokens, increment_burned_tokens, Metadata,
+    minters, minters_read, MinterInfo, locker, locker_read, locks, locks_read,
+    game, game_read, level_thresholds, load_level_thresholds, Stats,
 };
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw721-base";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// attributes every token gets from the contract, which cannot be set at mint
+const RESERVED_TRAITS: [&str; 2] = ["Rank", "Serial"];
+
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     _env: Env,
@@ -33,11 +43,36 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     let info = ContractInfo {
         name: msg.name,
         symbol: msg.symbol,
-        base_cap: msg.base_cap,
-        silver_cap: msg.silver_cap,
-        gold_cap: msg.gold_cap,
+        base_uri: msg.base_uri,
     };
     contract_info(&mut deps.storage).save(&info)?;
+
+    if msg.ranks.is_empty() {
+        return Err(StdError::generic_err("At least one rank is required"));
+    }
+    if msg.level_thresholds.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(StdError::generic_err("Level thresholds must be increasing"));
+    }
+    level_thresholds(&mut deps.storage).save(&msg.level_thresholds)?;
+
+    for rank in msg.ranks.iter() {
+        // rank ids are followed by a number in token ids, so they cannot end with digits
+        if rank.id.is_empty() || !rank.id.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(StdError::generic_err(format!("Invalid rank id {}", rank.id)));
+        }
+        if ranks_read(&deps.storage).may_load(rank.id.as_bytes())?.is_some() {
+            return Err(StdError::generic_err(format!("Rank {} is defined twice", rank.id)));
+        }
+        if let Some(recipe) = &rank.upgrade {
+            if recipe.count == 0 {
+                return Err(StdError::generic_err(format!("Upgrading rank {} takes no tokens", rank.id)));
+            }
+            if recipe.to == rank.id || !msg.ranks.iter().any(|r| r.id == recipe.to) {
+                return Err(StdError::generic_err(format!("Rank {} upgrades to an unknown rank", rank.id)));
+            }
+        }
+        ranks(&mut deps.storage).save(rank.id.as_bytes(), rank)?;
+    }
     let minter = deps.api.canonical_address(&msg.minter)?;
     mint(&mut deps.storage).save(&minter)?;
     Ok(InitResponse::default())
@@ -51,20 +86,21 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     match msg {
         HandleMsg::Mint {
             owner,
-            rank
-        } => handle_mint(deps, env, owner, rank),
-        HandleMsg::BaseToSilver {
-            base_1,
-            base_2,
-            base_3,
-        } => handle_base_to_silver(deps, env, base_1, base_2, base_3),
-        HandleMsg::SilverToGold {
-            silver_1,
-            silver_2,
-            silver_3,
-            silver_4,
-            silver_5,
-        } => handle_silver_to_gold(deps, env, silver_1, silver_2, silver_3, silver_4, silver_5),
+            rank,
+            metadata,
+        } => handle_mint(deps, env, owner, rank, metadata.unwrap_or_default()),
+        HandleMsg::BatchMint { mints } => handle_batch_mint(deps, env, mints),
+        HandleMsg::BatchTransferNft {
+            recipient,
+            token_ids,
+        } => handle_batch_transfer_nft(deps, env, recipient, token_ids),
+        HandleMsg::BatchSendNft {
+            contract,
+            token_ids,
+            msg,
+        } => handle_batch_send_nft(deps, env, contract, token_ids, msg),
+        HandleMsg::UpdateBaseUri { base_uri } => handle_update_base_uri(deps, env, base_uri),
+        HandleMsg::Upgrade { token_ids } => handle_upgrade(deps, env, token_ids),
         HandleMsg::Approve {
             spender,
             token_id,
@@ -87,6 +123,23 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
         HandleMsg::UpdateMinter {
             minter,
         } => handle_update_minter(deps, env, minter),
+        HandleMsg::AddMinter {
+            minter,
+            ranks,
+            quota,
+        } => handle_add_minter(deps, env, minter, ranks, quota),
+        HandleMsg::RemoveMinter { minter } => handle_remove_minter(deps, env, minter),
+        HandleMsg::UpdateLocker { locker } => handle_update_locker(deps, env, locker),
+        HandleMsg::Lock { token_id, until } => handle_lock(deps, env, token_id, until),
+        HandleMsg::Unlock { token_id } => handle_unlock(deps, env, token_id),
+        HandleMsg::UpdateGame { game } => handle_update_game(deps, env, game),
+        HandleMsg::RecordPerformance { token_id, xp, score } => {
+            handle_record_performance(deps, env, token_id, xp, score)
+        }
+        HandleMsg::UpdateCaps { caps } => handle_update_caps(deps, env, caps),
+        HandleMsg::FinalizeSupply {} => handle_finalize_supply(deps, env),
+        HandleMsg::Freeze {} => handle_freeze(deps, env),
+        HandleMsg::Burn { token_id } => handle_burn(deps, env, token_id),
     }
 }
 
@@ -95,56 +148,134 @@ pub fn handle_mint<S: Storage, A: Api, Q: Querier>(
     env: Env,
     owner: HumanAddr,
     rank: String,
+    metadata: Metadata,
 ) -> StdResult<HandleResponse> {
-    let rank_copy_1 = rank.clone();
-    let rank_copy_2 = rank.clone();
-    let rank_copy_3 = rank.clone();
-    let minter = mint(&mut deps.storage).load()?;
+    authorize_mint(deps, &env, &rank)?;
+
+    let token_id = _mint(deps, &owner, rank.clone(), metadata)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "mint"),
+            log("minter", env.message.sender),
+            log("token_id", token_id),
+            log("rank", rank),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_batch_mint<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    mints: Vec<MintMsg>,
+) -> StdResult<HandleResponse> {
+    if mints.is_empty() {
+        return Err(StdError::generic_err("Nothing to mint"));
+    }
+
+    let mut token_ids = vec![];
+    for msg in mints {
+        authorize_mint(deps, &env, &msg.rank)?;
+        token_ids.push(_mint(deps, &msg.owner, msg.rank, msg.metadata.unwrap_or_default())?);
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "batch_mint"),
+            log("minter", env.message.sender),
+            log("token_ids", token_ids.join(",")),
+        ],
+        data: None,
+    })
+}
+
+/// The minter and the contract itself can mint any rank. Minters added with AddMinter
+/// can mint their ranks until their quota is used up, which this counts against
+fn authorize_mint<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    rank: &str,
+) -> StdResult<()> {
+    let minter = mint_read(&deps.storage).load()?;
     let sender_raw = deps.api.canonical_address(&env.message.sender)?;
 
-    if sender_raw != minter {
-        if sender_raw !=  deps.api.canonical_address(&env.contract.address)? {
-            return Err(StdError::unauthorized());
-        }
-    } 
+    if sender_raw == minter || env.message.sender == env.contract.address {
+        return Ok(());
+    }
 
-    // create the token
-    let token = TokenInfo {
-        owner: deps.api.canonical_address(&owner)?,
-        approvals: vec![],
-        rank,
+    let mut info = match minters_read(&deps.storage).may_load(sender_raw.as_slice())? {
+        Some(info) => info,
+        None => return Err(StdError::unauthorized()),
     };
+    if let Some(ranks) = &info.ranks {
+        if !ranks.iter().any(|r| r == rank) {
+            return Err(StdError::generic_err(format!("Not allowed to mint rank {}", rank)));
+        }
+    }
+    if let Some(quota) = info.quota {
+        if info.minted >= quota {
+            return Err(StdError::generic_err("Minting quota is used up"));
+        }
+    }
+    info.minted += 1;
+    minters(&mut deps.storage).save(sender_raw.as_slice(), &info)
+}
+
+/// Creates a new token of the given rank, checking the caps. Returns the new token_id
+pub fn _mint<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    owner: &HumanAddr,
+    rank: String,
+    metadata: Metadata,
+) -> StdResult<String> {
+    if is_frozen(&deps.storage)? {
+        return Err(StdError::generic_err("Contract is frozen"))
+    }
 
-    if !query_mintable(deps, rank_copy_1).unwrap() {
+    if !query_mintable(deps, rank.clone())? {
         return Err(StdError::generic_err("Minting cannot exceed the cap"))
     }
 
+    if let Some(attribute) = metadata
+        .attributes
+        .iter()
+        .find(|a| RESERVED_TRAITS.contains(&a.trait_type.as_str()))
+    {
+        return Err(StdError::generic_err(format!(
+            "Attribute {} is set by the contract",
+            attribute.trait_type
+        )));
+    }
+
     // generate a token id based on rank
-    let token_id = generate_token_id(deps, rank_copy_2).unwrap();
+    // burned tokens are counted too, so serials and ids are never reused
+    let serial = rank_num_tokens(&deps.storage, &rank)? + burned_num_tokens(&deps.storage, &rank)? + 1;
+    let token_id = generate_token_id(deps, &rank, serial)?;
+
+    // create the token
+    let owner_raw = deps.api.canonical_address(owner)?;
+    let token = TokenInfo {
+        owner: owner_raw.clone(),
+        approvals: vec![],
+        rank: rank.clone(),
+        serial,
+        metadata,
+        stats: Stats::default(),
+    };
 
     tokens(&mut deps.storage).update(token_id.as_bytes(), |old| match old {
         Some(_) => Err(StdError::generic_err("token_id already claimed")),
         None => Ok(token),
     })?;
+    owner_tokens(&mut deps.storage, &owner_raw).save(token_id.as_bytes(), &rank)?;
+    owner_rank_tokens(&mut deps.storage, &owner_raw, &rank).save(token_id.as_bytes(), &true)?;
+    rank_tokens(&mut deps.storage, &rank).save(token_id.as_bytes(), &true)?;
+    increment_rank_tokens(&mut deps.storage, &rank)?;
 
-    if rank_copy_3.eq("S"){
-        increment_silver_tokens(&mut deps.storage)?;
-    } else if rank_copy_3.eq("G"){
-        increment_gold_tokens(&mut deps.storage)?;
-    } else  {
-        increment_base_tokens(&mut deps.storage)?;
-    }
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![
-            log("action", "mint"),
-            log("minter", env.message.sender),
-            log("token_id", token_id),
-            log("rank", rank_copy_3),
-        ],
-        data: None,
-    })
+    Ok(token_id)
 }
 
 pub fn handle_update_minter<S: Storage, A: Api, Q: Querier>(
@@ -172,1181 +303,2460 @@ pub fn handle_update_minter<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-pub fn handle_base_to_silver<S: Storage, A: Api, Q: Querier>(
+pub fn handle_add_minter<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    base_1: String,
-    base_2: String,
-    base_3: String,
+    minter: HumanAddr,
+    ranks: Option<Vec<String>>,
+    quota: Option<u64>,
 ) -> StdResult<HandleResponse> {
+    let main_minter = mint_read(&deps.storage).load()?;
     let sender_raw = deps.api.canonical_address(&env.message.sender)?;
-    let sender = deps.api.human_address(&sender_raw)?;
 
-    // the contract address is used as the burn address
-    _transfer_nft(deps, &env, &env.contract.address, &base_1)?;
-    _transfer_nft(deps, &env, &env.contract.address, &base_2)?;
-    _transfer_nft(deps, &env, &env.contract.address, &base_3)?;
-
-    // create msg for minting
-    let mint_msg = HandleMsg::Mint {
-        owner: sender.clone(), 
-        rank: "S".into()
-    };
+    if sender_raw != main_minter {
+        return Err(StdError::unauthorized());
+    }
+    if let Some(ranks) = &ranks {
+        for rank in ranks.iter() {
+            load_rank(&deps.storage, rank)?;
+        }
+    }
 
-    // Have the contract execute the mint function since the sender is not authorized to mint
-    let _mint_response = StdResult::<CosmosMsg>::from(Ok(WasmMsg::Execute {
-        contract_addr: env.contract.address,
-        msg: to_binary(&mint_msg).unwrap(),
-        send: vec![],
-    }.into()))?;
+    // updating an existing minter keeps what it minted so far
+    let minter_raw = deps.api.canonical_address(&minter)?;
+    minters(&mut deps.storage).update(minter_raw.as_slice(), |old| {
+        Ok(MinterInfo {
+            ranks,
+            quota,
+            minted: old.map(|info| info.minted).unwrap_or_default(),
+        })
+    })?;
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "base_to_gold"),
-            log("sender", &sender),
-            log("base_1", base_1),
-            log("base_2", base_2),
-            log("base_3", base_3),
+            log("action", "add_minter"),
+            log("sender", env.message.sender),
+            log("minter", minter),
         ],
         data: None,
     })
 }
 
-pub fn handle_silver_to_gold<S: Storage, A: Api, Q: Querier>(
+pub fn handle_remove_minter<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    silver_1: String,
-    silver_2: String,
-    silver_3: String,
-    silver_4: String,
-    silver_5: String,
+    minter: HumanAddr,
 ) -> StdResult<HandleResponse> {
+    let main_minter = mint_read(&deps.storage).load()?;
     let sender_raw = deps.api.canonical_address(&env.message.sender)?;
-    let sender = deps.api.human_address(&sender_raw)?;
-
-    // the contract address is used as the burn address
-    _transfer_nft(deps, &env, &env.contract.address, &silver_1)?;
-    _transfer_nft(deps, &env, &env.contract.address, &silver_2)?;
-    _transfer_nft(deps, &env, &env.contract.address, &silver_3)?;
-    _transfer_nft(deps, &env, &env.contract.address, &silver_4)?;
-    _transfer_nft(deps, &env, &env.contract.address, &silver_5)?;
-
-    // create msg for minting
-    let mint_msg = HandleMsg::Mint {
-        owner: sender.clone(), 
-        rank: "G".into()
-    };
 
-    // Have the contract execute the mint function since the sender is not authorized to mint
-    let _mint_response = StdResult::<CosmosMsg>::from(Ok(WasmMsg::Execute {
-        contract_addr: env.contract.address,
-        msg: to_binary(&mint_msg).unwrap(),
-        send: vec![],
-    }.into()))?;
+    if sender_raw != main_minter {
+        return Err(StdError::unauthorized());
+    }
+
+    let minter_raw = deps.api.canonical_address(&minter)?;
+    minters_read(&deps.storage).load(minter_raw.as_slice())?;
+    minters(&mut deps.storage).remove(minter_raw.as_slice());
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "base_to_gold"),
-            log("sender", &sender),
-            log("silver_1", silver_1),
-            log("silver_2", silver_2),
-            log("silver_3", silver_3),
-            log("silver_4", silver_4),
-            log("silver_5", silver_5),
+            log("action", "remove_minter"),
+            log("sender", env.message.sender),
+            log("minter", minter),
         ],
         data: None,
     })
 }
 
-pub fn handle_transfer_nft<S: Storage, A: Api, Q: Querier>(
+pub fn handle_update_locker<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    recipient: HumanAddr,
-    token_id: String,
+    new_locker: Option<HumanAddr>,
 ) -> StdResult<HandleResponse> {
-    _transfer_nft(deps, &env, &recipient, &token_id)?;
+    let minter = mint_read(&deps.storage).load()?;
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+
+    if sender_raw != minter {
+        return Err(StdError::unauthorized());
+    }
+
+    // existing locks stay until they expire or the new locker lifts them
+    match &new_locker {
+        Some(addr) => {
+            let locker_raw = deps.api.canonical_address(addr)?;
+            locker(&mut deps.storage).save(&locker_raw)?;
+        }
+        None => locker(&mut deps.storage).remove(),
+    }
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "transfer_nft"),
+            log("action", "update_locker"),
             log("sender", env.message.sender),
-            log("recipient", recipient),
-            log("token_id", token_id),
+            log(
+                "locker",
+                new_locker.map(|addr| addr.to_string()).unwrap_or_default(),
+            ),
         ],
         data: None,
     })
 }
 
-pub fn handle_send_nft<S: Storage, A: Api, Q: Querier>(
+pub fn handle_lock<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    contract: HumanAddr,
     token_id: String,
-    msg: Option<Binary>,
+    until: Expiration,
 ) -> StdResult<HandleResponse> {
-    // Unwrap message first
-    let msgs: Vec<CosmosMsg> = match &msg {
-        None => vec![],
-        Some(msg) => vec![from_binary(msg)?],
-    };
+    check_locker(deps, &env)?;
+    // the token must exist
+    tokens_read(&deps.storage).load(token_id.as_bytes())?;
 
-    // Transfer token
-    _transfer_nft(deps, &env, &contract, &token_id)?;
+    // reject expired data as invalid
+    if until.is_expired(&env.block) {
+        return Err(StdError::generic_err("Cannot set lock that is already expired"));
+    }
+    // locking a locked token replaces its expiration
+    locks(&mut deps.storage).save(token_id.as_bytes(), &until)?;
 
-    // Send message
     Ok(HandleResponse {
-        messages: msgs,
+        messages: vec![],
         log: vec![
-            log("action", "send_nft"),
+            log("action", "lock"),
             log("sender", env.message.sender),
-            log("recipient", contract),
             log("token_id", token_id),
+            log("until", until),
         ],
         data: None,
     })
 }
 
-pub fn _transfer_nft<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: &Env,
-    recipient: &HumanAddr,
-    token_id: &str,
-) -> StdResult<TokenInfo> {
-    let mut token = tokens(&mut deps.storage).load(token_id.as_bytes())?;
-    // ensure we have permissions
-    check_ownership(&deps, env, &token)?;
-    // set owner and remove existing approvals
-    token.owner = deps.api.canonical_address(recipient)?;
-    token.approvals = vec![];
-    tokens(&mut deps.storage).save(token_id.as_bytes(), &token)?;
-    Ok(token)
-}
-
-pub fn handle_approve<S: Storage, A: Api, Q: Querier>(
+pub fn handle_unlock<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    spender: HumanAddr,
     token_id: String,
-    expires: Option<Expiration>,
 ) -> StdResult<HandleResponse> {
-    _update_approvals(deps, &env, &spender, &token_id, true, expires)?;
+    check_locker(deps, &env)?;
+    if locks_read(&deps.storage).may_load(token_id.as_bytes())?.is_none() {
+        return Err(StdError::generic_err(format!("Token {} is not locked", token_id)));
+    }
+    locks(&mut deps.storage).remove(token_id.as_bytes());
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "approve"),
+            log("action", "unlock"),
             log("sender", env.message.sender),
-            log("spender", spender),
             log("token_id", token_id),
         ],
         data: None,
     })
 }
 
-pub fn handle_revoke<S: Storage, A: Api, Q: Querier>(
+pub fn handle_update_game<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    spender: HumanAddr,
-    token_id: String,
+    new_game: Option<HumanAddr>,
 ) -> StdResult<HandleResponse> {
-    _update_approvals(deps, &env, &spender, &token_id, false, None)?;
+    let minter = mint_read(&deps.storage).load()?;
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+
+    if sender_raw != minter {
+        return Err(StdError::unauthorized());
+    }
+
+    match &new_game {
+        Some(addr) => {
+            let game_raw = deps.api.canonical_address(addr)?;
+            game(&mut deps.storage).save(&game_raw)?;
+        }
+        None => game(&mut deps.storage).remove(),
+    }
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "revoke"),
+            log("action", "update_game"),
             log("sender", env.message.sender),
-            log("spender", spender),
-            log("token_id", token_id),
+            log(
+                "game",
+                new_game.map(|addr| addr.to_string()).unwrap_or_default(),
+            ),
         ],
         data: None,
     })
 }
 
-pub fn _update_approvals<S: Storage, A: Api, Q: Querier>(
+pub fn handle_record_performance<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    env: &Env,
-    spender: &HumanAddr,
-    token_id: &str,
-    // if add == false, remove. if add == true, remove then set with this expiration
-    add: bool,
-    expires: Option<Expiration>,
-) -> StdResult<TokenInfo> {
-    let mut token = tokens(&mut deps.storage).load(token_id.as_bytes())?;
-    // ensure we have permissions
-    check_can_approve(&deps, &env, &token)?;
-
-    // update the approval list (remove any for the same spender before adding)
-    let spender_raw = deps.api.canonical_address(&spender)?;
-    token.approvals = token
-        .approvals
-        .into_iter()
-        .filter(|apr| apr.spender != spender_raw)
-        .collect();
-
-    // only difference between approve and revoke
-    if add {
-        // reject expired data as invalid
-        let expires = expires.unwrap_or_default();
-        if expires.is_expired(&env.block) {
-            return Err(StdError::generic_err(
-                "Cannot set approval that is already expired",
-            ));
-        }
-        let approval = Approval {
-            spender: spender_raw,
-            expires,
-        };
-        token.approvals.push(approval);
+    env: Env,
+    token_id: String,
+    xp: u64,
+    score: u64,
+) -> StdResult<HandleResponse> {
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    match game_read(&deps.storage).may_load()? {
+        Some(game) if game == sender_raw => {}
+        _ => return Err(StdError::unauthorized()),
     }
 
+    let mut token = tokens_read(&deps.storage).load(token_id.as_bytes())?;
+    let thresholds = load_level_thresholds(&deps.storage)?;
+    let old_level = level(&thresholds, token.stats.xp);
+
+    token.stats.xp = token
+        .stats
+        .xp
+        .checked_add(xp)
+        .ok_or_else(|| StdError::generic_err(format!("Xp of token {} overflows", token_id)))?;
+    token.stats.games_played += 1;
+    token.stats.best_score = token.stats.best_score.max(score);
     tokens(&mut deps.storage).save(token_id.as_bytes(), &token)?;
+    let new_level = level(&thresholds, token.stats.xp);
+
+    let mut logs = vec![
+        log("action", "record_performance"),
+        log("sender", env.message.sender),
+        log("token_id", token_id),
+        log("xp", xp),
+        log("score", score),
+    ];
+    if new_level > old_level {
+        logs.push(log("level_up", new_level));
+    }
 
-    Ok(token)
+    Ok(HandleResponse {
+        messages: vec![],
+        log: logs,
+        data: None,
+    })
 }
 
-pub fn handle_approve_all<S: Storage, A: Api, Q: Querier>(
+/// Tokens start at level 1 and gain one level for each threshold their xp reached
+fn level(thresholds: &[u64], xp: u64) -> u32 {
+    1 + thresholds.iter().filter(|threshold| xp >= **threshold).count() as u32
+}
+
+pub fn handle_update_base_uri<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    operator: HumanAddr,
-    expires: Option<Expiration>,
+    base_uri: Option<String>,
 ) -> StdResult<HandleResponse> {
-    // reject expired data as invalid
-    let expires = expires.unwrap_or_default();
-    if expires.is_expired(&env.block) {
-        return Err(StdError::generic_err(
-            "Cannot set approval that is already expired",
-        ));
+    let minter = mint(&mut deps.storage).load()?;
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+
+    if sender_raw != minter {
+        return Err(StdError::unauthorized());
     }
 
-    // set the operator for us
-    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
-    let operator_raw = deps.api.canonical_address(&operator)?;
-    operators(&mut deps.storage, &sender_raw).save(operator_raw.as_slice(), &expires)?;
+    contract_info(&mut deps.storage).update(|mut info| {
+        info.base_uri = base_uri;
+        Ok(info)
+    })?;
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "approve_all"),
-            log("sender", env.message.sender),
-            log("operator", operator),
+            log("action", "update_base_uri"),
+            log("minter", env.message.sender),
         ],
         data: None,
     })
 }
 
-pub fn handle_revoke_all<S: Storage, A: Api, Q: Querier>(
+pub fn handle_update_caps<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    operator: HumanAddr,
+    caps: Vec<CapUpdate>,
 ) -> StdResult<HandleResponse> {
+    let minter = mint_read(&deps.storage).load()?;
     let sender_raw = deps.api.canonical_address(&env.message.sender)?;
-    let operator_raw = deps.api.canonical_address(&operator)?;
-    operators(&mut deps.storage, &sender_raw).remove(operator_raw.as_slice());
+
+    if sender_raw != minter {
+        return Err(StdError::unauthorized());
+    }
+    if is_finalized(&deps.storage)? {
+        return Err(StdError::generic_err("Supply is finalized"));
+    }
+
+    // check every update before saving any
+    let mut updated = Vec::with_capacity(caps.len());
+    for update in caps.iter() {
+        let mut rank = load_rank(&deps.storage, &update.rank)?;
+        let supply = rank_num_tokens(&deps.storage, &rank.id)?;
+        if update.cap < Uint128::from(supply) {
+            return Err(StdError::generic_err(format!(
+                "Cap of rank {} cannot be below its supply of {}",
+                rank.id, supply
+            )));
+        }
+        rank.cap = update.cap;
+        updated.push(rank);
+    }
+    for rank in updated.iter() {
+        ranks(&mut deps.storage).save(rank.id.as_bytes(), rank)?;
+    }
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "revoke_all"),
+            log("action", "update_caps"),
             log("sender", env.message.sender),
-            log("operator", operator),
+            log(
+                "ranks",
+                caps.into_iter().map(|update| update.rank).collect::<Vec<_>>().join(","),
+            ),
         ],
         data: None,
     })
 }
 
-/// returns true iff the sender can execute approve or reject on the contract
-fn check_can_approve<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    env: &Env,
-    token: &TokenInfo,
-) -> StdResult<()> {
-    // owner can approve
-    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
-    if token.owner == sender_raw {
-        return Ok(());
-    }
-    // operator can approve
-    let op = operators_read(&deps.storage, &token.owner).may_load(sender_raw.as_slice())?;
-    match op {
-        Some(ex) => {
-            if ex.is_expired(&env.block) {
-                Err(StdError::unauthorized())
-            } else {
-                Ok(())
-            }
-        }
-        None => Err(StdError::unauthorized()),
-    }
-}
-
-/// returns true iff the sender can transfer ownership of the token
-fn check_ownership<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    env: &Env,
-    token: &TokenInfo,
-) -> StdResult<()> {
-    // owner can send
+pub fn handle_finalize_supply<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let minter = mint_read(&deps.storage).load()?;
     let sender_raw = deps.api.canonical_address(&env.message.sender)?;
-    if token.owner == sender_raw {
-        return Ok(());
-    }
 
-    // contract address can send
-    if env.contract.address == env.message.sender {
-        return Ok(());
+    if sender_raw != minter {
+        return Err(StdError::unauthorized());
     }
 
-    // any non-expired token approval can send
-    if token
-        .approvals
-        .iter()
-        .any(|apr| apr.spender == sender_raw && !apr.expires.is_expired(&env.block))
-    {
-        return Ok(());
-    }
+    finalized(&mut deps.storage).save(&true)?;
 
-    // operator can send
-    let op = operators_read(&deps.storage, &token.owner).may_load(sender_raw.as_slice())?;
-    match op {
-        Some(ex) => {
-            if ex.is_expired(&env.block) {
-                Err(StdError::unauthorized())
-            } else {
-                Ok(())
-            }
-        }
-        None => Err(StdError::unauthorized()),
-    }
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "finalize_supply"),
+            log("minter", env.message.sender),
+        ],
+        data: None,
+    })
 }
 
-fn generate_token_id<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    rank: String,
-) -> StdResult<String> {
-
-    let contract_info = query_contract_info(&deps).unwrap();
-    let mut token_id: String = contract_info.symbol.to_owned();
-
-    if rank.eq("S"){
-        let rank_string: &str = "S";
-        let silver_count = query_silver_tokens(&deps).unwrap().count + 1;
-
-        token_id.push_str(rank_string);
-        token_id.push_str(&silver_count.to_string());
-    } else if rank.eq("G"){
-        let rank_string: &str = "G";
-        let gold_count = query_gold_tokens(&deps).unwrap().count + 1;
-
-        token_id.push_str(rank_string);
-        token_id.push_str(&gold_count.to_string());
-    } else  {
-        let rank_string: &str = "B";
-        let base_count = query_base_tokens(&deps).unwrap().count + 1;
+pub fn handle_freeze<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let minter = mint(&mut deps.storage).load()?;
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
 
-        token_id.push_str(rank_string);
-        token_id.push_str(&base_count.to_string());
+    if sender_raw != minter {
+        return Err(StdError::unauthorized());
     }
 
-    Ok(token_id)
-}
+    frozen(&mut deps.storage).save(&true)?;
 
-pub fn query<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    msg: QueryMsg,
-) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Minter {} => to_binary(&query_minter(deps)?),
-        QueryMsg::ContractInfo {} => to_binary(&query_contract_info(deps)?),
-        QueryMsg::NftInfo { token_id } => to_binary(&query_nft_info(deps, token_id)?),
-        QueryMsg::OwnerOf { token_id } => to_binary(&query_owner_of(deps, token_id)?),
-        QueryMsg::AllNftInfo { token_id } => to_binary(&query_all_nft_info(deps, token_id)?),
-        QueryMsg::ApprovedForAll {
-            owner,
-            start_after,
-            limit,
-        } => to_binary(&query_all_approvals(deps, owner, start_after, limit)?),
-        QueryMsg::BaseTokens {} => to_binary(&query_base_tokens(deps)?),
-        QueryMsg::AllBaseTokens { start_after, limit } => {
-            to_binary(&query_all_base_tokens(deps, start_after, limit)?)
-        },
-        QueryMsg::SilverTokens {} => to_binary(&query_silver_tokens(deps)?),
-        QueryMsg::AllSilverTokens { start_after, limit } => {
-            to_binary(&query_all_silver_tokens(deps, start_after, limit)?)
-        },
-        QueryMsg::GoldTokens {} => to_binary(&query_gold_tokens(deps)?),
-        QueryMsg::AllGoldTokens { start_after, limit } => {
-            to_binary(&query_all_gold_tokens(deps, start_after, limit)?)
-        },
-        QueryMsg::IsMintable { rank } => to_binary(&query_mintable(deps, rank)?),
-    }
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "freeze"),
+            log("minter", env.message.sender),
+        ],
+        data: None,
+    })
 }
 
-fn query_mintable<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    rank: String,
-) -> StdResult<bool> {
-
-    let contract_info = query_contract_info(&deps).unwrap();
-    let mut is_mintable = true;
-
-    if rank.eq("S"){
-        let silver_count = query_silver_tokens(&deps).unwrap();
+pub fn handle_upgrade<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token_ids: Vec<String>,
+) -> StdResult<HandleResponse> {
+    let first = match token_ids.first() {
+        Some(token_id) => tokens_read(&deps.storage).load(token_id.as_bytes())?,
+        None => return Err(StdError::generic_err("No tokens to upgrade")),
+    };
+    let from = load_rank(&deps.storage, &first.rank)?;
+    let recipe = match from.upgrade {
+        Some(recipe) => recipe,
+        None => return Err(StdError::generic_err(format!("Rank {} cannot be upgraded", from.id))),
+    };
+    if token_ids.len() != recipe.count as usize {
+        return Err(StdError::generic_err(format!(
+            "Upgrading rank {} takes {} tokens",
+            from.id, recipe.count
+        )));
+    }
 
-        if Uint128::from(silver_count.count) == contract_info.silver_cap {
-            is_mintable = false
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    for (i, token_id) in token_ids.iter().enumerate() {
+        if token_ids[..i].contains(token_id) {
+            return Err(StdError::generic_err(format!("Token {} is used twice", token_id)));
         }
-    } else if rank.eq("G"){
-        let gold_count = query_gold_tokens(&deps).unwrap();
 
-        if Uint128::from(gold_count.count) == contract_info.gold_cap {
-            is_mintable = false
+        let token = tokens_read(&deps.storage).load(token_id.as_bytes())?;
+        // only the owner can upgrade, approvals and operators are not enough
+        if token.owner != sender_raw {
+            return Err(StdError::unauthorized());
         }
-    } else  {
-        let base_count = query_base_tokens(&deps).unwrap();
-
-        if Uint128::from(base_count.count) == contract_info.base_cap {
-            is_mintable = false
+        if token.rank != from.id {
+            return Err(StdError::generic_err(format!("Token {} is not of rank {}", token_id, from.id)));
         }
+        check_unlocked(&deps.storage, &env.block, token_id)?;
     }
 
-    Ok(is_mintable)
-}
+    // check the cap before burning, so nothing is burned for an upgrade that cannot be minted
+    if is_frozen(&deps.storage)? {
+        return Err(StdError::generic_err("Contract is frozen"));
+    }
+    if !query_mintable(deps, recipe.to.clone())? {
+        return Err(StdError::generic_err("Minting cannot exceed the cap"));
+    }
 
-fn query_minter<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<MinterResponse> {
-    let minter_raw = mint_read(&deps.storage).load()?;
-    let minter = deps.api.human_address(&minter_raw)?;
-    Ok(MinterResponse { minter })
-}
+    for token_id in token_ids.iter() {
+        _burn(deps, token_id)?;
+    }
+    let token_id = _mint(deps, &env.message.sender, recipe.to.clone(), Metadata::default())?;
 
-fn query_contract_info<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<ContractInfo> {
-    contract_info_read(&deps.storage).load()
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "upgrade"),
+            log("sender", env.message.sender),
+            log("from", from.id),
+            log("to", recipe.to),
+            log("burned", token_ids.join(",")),
+            log("token_id", token_id),
+        ],
+        data: None,
+    })
 }
 
-fn query_base_tokens<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<NumTokensResponse> {
-    let count = base_num_tokens(&deps.storage)?;
-    Ok(NumTokensResponse { count })
-}
+pub fn handle_burn<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token_id: String,
+) -> StdResult<HandleResponse> {
+    let token = tokens_read(&deps.storage).load(token_id.as_bytes())?;
+    // ensure we have permissions
+    check_ownership(&deps, &env, &token)?;
+    // operators can hold soulbound tokens through ApproveAll, but only the owner can burn them
+    if !load_rank(&deps.storage, &token.rank)?.transferable
+        && token.owner != deps.api.canonical_address(&env.message.sender)?
+    {
+        return Err(StdError::generic_err(format!(
+            "Token {} can only be burned by its owner",
+            token_id
+        )));
+    }
+    check_unlocked(&deps.storage, &env.block, &token_id)?;
+    _burn(deps, &token_id)?;
 
-fn query_silver_tokens<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<NumTokensResponse> {
-    let count = silver_num_tokens(&deps.storage)?;
-    Ok(NumTokensResponse { count })
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "burn"),
+            log("sender", env.message.sender),
+            log("token_id", token_id),
+        ],
+        data: None,
+    })
 }
 
-fn query_gold_tokens<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<NumTokensResponse> {
-    let count = gold_num_tokens(&deps.storage)?;
-    Ok(NumTokensResponse { count })
+/// Removes the token and moves it from the rank's supply to its burned count.
+/// Callers are responsible for checking permissions and locks
+pub fn _burn<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    token_id: &str,
+) -> StdResult<TokenInfo> {
+    let token = tokens_read(&deps.storage).load(token_id.as_bytes())?;
+    tokens(&mut deps.storage).remove(token_id.as_bytes());
+    locks(&mut deps.storage).remove(token_id.as_bytes());
+
+    owner_tokens(&mut deps.storage, &token.owner).remove(token_id.as_bytes());
+    owner_rank_tokens(&mut deps.storage, &token.owner, &token.rank).remove(token_id.as_bytes());
+    rank_tokens(&mut deps.storage, &token.rank).remove(token_id.as_bytes());
+    decrement_rank_tokens(&mut deps.storage, &token.rank)?;
+    increment_burned_tokens(&mut deps.storage, &token.rank)?;
+
+    Ok(token)
 }
 
-fn query_nft_info<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
+pub fn handle_transfer_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipient: HumanAddr,
     token_id: String,
-) -> StdResult<NftInfoResponse> {
-    let info = tokens_read(&deps.storage).load(token_id.as_bytes())?;
-    Ok(NftInfoResponse {
-        rank: info.rank,
+) -> StdResult<HandleResponse> {
+    _transfer_nft(deps, &env, &recipient, &token_id)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "transfer_nft"),
+            log("sender", env.message.sender),
+            log("recipient", recipient),
+            log("token_id", token_id),
+        ],
+        data: None,
     })
 }
 
-fn query_owner_of<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
+pub fn handle_send_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
     token_id: String,
-) -> StdResult<OwnerOfResponse> {
-    let info = tokens_read(&deps.storage).load(token_id.as_bytes())?;
-    Ok(OwnerOfResponse {
-        owner: deps.api.human_address(&info.owner)?,
-        approvals: humanize_approvals(deps.api, &info)?,
+    msg: Option<Binary>,
+) -> StdResult<HandleResponse> {
+    // Transfer token
+    _transfer_nft(deps, &env, &contract, &token_id)?;
+
+    // Let the contract know it received the token
+    let receive = Cw721ReceiveMsg {
+        sender: env.message.sender.clone(),
+        token_id: token_id.clone(),
+        msg,
+    };
+
+    Ok(HandleResponse {
+        messages: vec![receive.into_cosmos_msg(contract.clone())?],
+        log: vec![
+            log("action", "send_nft"),
+            log("sender", env.message.sender),
+            log("recipient", contract),
+            log("token_id", token_id),
+        ],
+        data: None,
     })
 }
 
-const DEFAULT_LIMIT: u32 = 10;
-const MAX_LIMIT: u32 = 30;
+pub fn handle_batch_transfer_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipient: HumanAddr,
+    token_ids: Vec<String>,
+) -> StdResult<HandleResponse> {
+    if token_ids.is_empty() {
+        return Err(StdError::generic_err("No tokens to transfer"));
+    }
+    for token_id in token_ids.iter() {
+        _transfer_nft(deps, &env, &recipient, token_id)?;
+    }
 
-fn query_all_approvals<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    owner: HumanAddr,
-    start_after: Option<HumanAddr>,
-    limit: Option<u32>,
-) -> StdResult<ApprovedForAllResponse> {
-    let owner_raw = deps.api.canonical_address(&owner)?;
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start_human(deps.api, start_after)?;
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "batch_transfer_nft"),
+            log("sender", env.message.sender),
+            log("recipient", recipient),
+            log("token_ids", token_ids.join(",")),
+        ],
+        data: None,
+    })
+}
 
-    let res: StdResult<Vec<_>> = operators_read(&deps.storage, &owner_raw)
-        .range(start.as_deref(), None, Order::Ascending)
-        .take(limit)
-        .map(|item| {
-            item.and_then(|(k, expires)| {
-                Ok(cw721::Approval {
-                    spender: deps.api.human_address(&k.into())?,
-                    expires,
-                })
-            })
-        })
-        .collect();
-    Ok(ApprovedForAllResponse { operators: res? })
-}
-
-fn query_all_base_tokens<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> StdResult<TokensResponse> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start_string(start_after);
-
-    let tokens: StdResult<Vec<String>> = tokens_read(&deps.storage)
-        .range(start.as_deref(), None, Order::Ascending)
-        .take(limit)
-        .map(|item| item.map(|(k, _)| String::from_utf8_lossy(&k).to_string()))
-        .collect();
-    Ok(TokensResponse { tokens: tokens? })
-}
+pub fn handle_batch_send_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+    token_ids: Vec<String>,
+    msg: Option<Binary>,
+) -> StdResult<HandleResponse> {
+    if token_ids.is_empty() {
+        return Err(StdError::generic_err("No tokens to send"));
+    }
 
-fn query_all_silver_tokens<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> StdResult<TokensResponse> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start_string(start_after);
+    // Transfer tokens, letting the contract know about each of them
+    let mut msgs: Vec<CosmosMsg> = Vec::with_capacity(token_ids.len());
+    for token_id in token_ids.iter() {
+        _transfer_nft(deps, &env, &contract, token_id)?;
+        let receive = Cw721ReceiveMsg {
+            sender: env.message.sender.clone(),
+            token_id: token_id.clone(),
+            msg: msg.clone(),
+        };
+        msgs.push(receive.into_cosmos_msg(contract.clone())?);
+    }
 
-    let tokens: StdResult<Vec<String>> = tokens_read(&deps.storage)
-        .range(start.as_deref(), None, Order::Ascending)
-        .take(limit)
-        .map(|item| item.map(|(k, _)| String::from_utf8_lossy(&k).to_string()))
-        .collect();
-    Ok(TokensResponse { tokens: tokens? })
+    Ok(HandleResponse {
+        messages: msgs,
+        log: vec![
+            log("action", "batch_send_nft"),
+            log("sender", env.message.sender),
+            log("recipient", contract),
+            log("token_ids", token_ids.join(",")),
+        ],
+        data: None,
+    })
 }
 
-fn query_all_gold_tokens<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> StdResult<TokensResponse> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start_string(start_after);
-
-    let tokens: StdResult<Vec<String>> = tokens_read(&deps.storage)
-        .range(start.as_deref(), None, Order::Ascending)
-        .take(limit)
-        .map(|item| item.map(|(k, _)| String::from_utf8_lossy(&k).to_string()))
-        .collect();
-    Ok(TokensResponse { tokens: tokens? })
+pub fn _transfer_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    recipient: &HumanAddr,
+    token_id: &str,
+) -> StdResult<TokenInfo> {
+    let mut token = tokens(&mut deps.storage).load(token_id.as_bytes())?;
+    // ensure we have permissions
+    check_ownership(&deps, env, &token)?;
+    check_transferable(&deps.storage, &token, token_id)?;
+    check_unlocked(&deps.storage, &env.block, token_id)?;
+    // set owner and remove existing approvals
+    owner_tokens(&mut deps.storage, &token.owner).remove(token_id.as_bytes());
+    owner_rank_tokens(&mut deps.storage, &token.owner, &token.rank).remove(token_id.as_bytes());
+    token.owner = deps.api.canonical_address(recipient)?;
+    token.approvals = vec![];
+    tokens(&mut deps.storage).save(token_id.as_bytes(), &token)?;
+    owner_tokens(&mut deps.storage, &token.owner).save(token_id.as_bytes(), &token.rank)?;
+    owner_rank_tokens(&mut deps.storage, &token.owner, &token.rank).save(token_id.as_bytes(), &true)?;
+    Ok(token)
 }
 
-fn query_all_nft_info<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
+pub fn handle_approve<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    spender: HumanAddr,
     token_id: String,
-) -> StdResult<AllNftInfoResponse> {
-    let info = tokens_read(&deps.storage).load(token_id.as_bytes())?;
-    Ok(AllNftInfoResponse {
-        access: OwnerOfResponse {
-            owner: deps.api.human_address(&info.owner)?,
-            approvals: humanize_approvals(deps.api, &info)?,
-        },
-        info: NftInfoResponse {
-            rank: info.rank,
-        },
+    expires: Option<Expiration>,
+) -> StdResult<HandleResponse> {
+    _update_approvals(deps, &env, &spender, &token_id, true, expires)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "approve"),
+            log("sender", env.message.sender),
+            log("spender", spender),
+            log("token_id", token_id),
+        ],
+        data: None,
     })
 }
 
-fn humanize_approvals<A: Api>(api: A, info: &TokenInfo) -> StdResult<Vec<cw721::Approval>> {
-    info.approvals
-        .iter()
-        .map(|apr| humanize_approval(api, apr))
-        .collect()
-}
+pub fn handle_revoke<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    spender: HumanAddr,
+    token_id: String,
+) -> StdResult<HandleResponse> {
+    _update_approvals(deps, &env, &spender, &token_id, false, None)?;
 
-fn humanize_approval<A: Api>(api: A, approval: &Approval) -> StdResult<cw721::Approval> {
-    Ok(cw721::Approval {
-        spender: api.human_address(&approval.spender)?,
-        expires: approval.expires,
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "revoke"),
+            log("sender", env.message.sender),
+            log("spender", spender),
+            log("token_id", token_id),
+        ],
+        data: None,
     })
 }
 
+pub fn _update_approvals<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    spender: &HumanAddr,
+    token_id: &str,
+    // if add == false, remove. if add == true, remove then set with this expiration
+    add: bool,
+    expires: Option<Expiration>,
+) -> StdResult<TokenInfo> {
+    let mut token = tokens(&mut deps.storage).load(token_id.as_bytes())?;
+    // ensure we have permissions
+    check_can_approve(&deps, &env, &token)?;
+    // revoking is still allowed, so approvals given before can be cleaned up
+    if add {
+        check_transferable(&deps.storage, &token, token_id)?;
+    }
 
-#[cfg(test)]
-mod tests {
-    use cosmwasm_std::testing::{mock_dependencies, mock_env};
-    use cosmwasm_std::{StdError, WasmMsg, Uint128};
+    // update the approval list (remove any for the same spender before adding)
+    let spender_raw = deps.api.canonical_address(&spender)?;
+    token.approvals = token
+        .approvals
+        .into_iter()
+        .filter(|apr| apr.spender != spender_raw)
+        .collect();
 
-    use super::*;
-    use cw721::ApprovedForAllResponse;
+    // only difference between approve and revoke
+    if add {
+        // reject expired data as invalid
+        let expires = expires.unwrap_or_default();
+        if expires.is_expired(&env.block) {
+            return Err(StdError::generic_err(
+                "Cannot set approval that is already expired",
+            ));
+        }
+        let approval = Approval {
+            spender: spender_raw,
+            expires,
+        };
+        token.approvals.push(approval);
+    }
 
-    const MINTER: &str = "cosmos2contract";
-    const CONTRACT_NAME: &str = "Lebron Token";
-    const SYMBOL: &str = "LBJ";
-    const BASE_CAP: Uint128 = Uint128(2);
-    const SILVER_CAP: Uint128 = Uint128(2);
-    const GOLD_CAP: Uint128 = Uint128(2);
+    tokens(&mut deps.storage).save(token_id.as_bytes(), &token)?;
 
-    fn setup_contract<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>) {
-        let msg = InitMsg {
-            name: CONTRACT_NAME.to_string(),
-            symbol: SYMBOL.to_string(),
-            minter: MINTER.into(),
-            base_cap: BASE_CAP,
-            silver_cap: SILVER_CAP,
-            gold_cap: GOLD_CAP,
-        };
-        let env = mock_env("creator", &[]);
-        let res = init(deps, env, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+    Ok(token)
+}
+
+pub fn handle_approve_all<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    operator: HumanAddr,
+    expires: Option<Expiration>,
+) -> StdResult<HandleResponse> {
+    // reject expired data as invalid
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(StdError::generic_err(
+            "Cannot set approval that is already expired",
+        ));
     }
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies(20, &[]);
+    // set the operator for us
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    let operator_raw = deps.api.canonical_address(&operator)?;
+    operators(&mut deps.storage, &sender_raw).save(operator_raw.as_slice(), &expires)?;
 
-        let msg = InitMsg {
-            name: CONTRACT_NAME.to_string(),
-            symbol: SYMBOL.to_string(),
-            minter: MINTER.into(),
-            base_cap: BASE_CAP,
-            silver_cap: SILVER_CAP,
-            gold_cap: GOLD_CAP,
-        };
-        let env = mock_env("creator", &[]);
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "approve_all"),
+            log("sender", env.message.sender),
+            log("operator", operator),
+        ],
+        data: None,
+    })
+}
 
-        // we can just call .unwrap() to assert this was a success
-        let res = init(&mut deps, env, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+pub fn handle_revoke_all<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    operator: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    let operator_raw = deps.api.canonical_address(&operator)?;
+    operators(&mut deps.storage, &sender_raw).remove(operator_raw.as_slice());
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "revoke_all"),
+            log("sender", env.message.sender),
+            log("operator", operator),
+        ],
+        data: None,
+    })
+}
+
+/// returns true iff the sender can execute approve or reject on the contract
+fn check_can_approve<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    token: &TokenInfo,
+) -> StdResult<()> {
+    // owner can approve
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    if token.owner == sender_raw {
+        return Ok(());
+    }
+    // operator can approve
+    let op = operators_read(&deps.storage, &token.owner).may_load(sender_raw.as_slice())?;
+    match op {
+        Some(ex) => {
+            if ex.is_expired(&env.block) {
+                Err(StdError::unauthorized())
+            } else {
+                Ok(())
+            }
+        }
+        None => Err(StdError::unauthorized()),
+    }
+}
+
+/// returns true iff the sender can transfer ownership of the token
+fn check_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    token: &TokenInfo,
+) -> StdResult<()> {
+    // owner can send
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    if token.owner == sender_raw {
+        return Ok(());
+    }
+
+    // contract address can send
+    if env.contract.address == env.message.sender {
+        return Ok(());
+    }
+
+    // any non-expired token approval can send
+    if token
+        .approvals
+        .iter()
+        .any(|apr| apr.spender == sender_raw && !apr.expires.is_expired(&env.block))
+    {
+        return Ok(());
+    }
+
+    // operator can send
+    let op = operators_read(&deps.storage, &token.owner).may_load(sender_raw.as_slice())?;
+    match op {
+        Some(ex) => {
+            if ex.is_expired(&env.block) {
+                Err(StdError::unauthorized())
+            } else {
+                Ok(())
+            }
+        }
+        None => Err(StdError::unauthorized()),
+    }
+}
+
+/// Only the locker can lock and unlock tokens
+fn check_locker<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<()> {
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    match locker_read(&deps.storage).may_load()? {
+        Some(locker) if locker == sender_raw => Ok(()),
+        _ => Err(StdError::unauthorized()),
+    }
+}
+
+/// Errors if the token's rank is soulbound
+fn check_transferable<S: ReadonlyStorage>(
+    storage: &S,
+    token: &TokenInfo,
+    token_id: &str,
+) -> StdResult<()> {
+    if !load_rank(storage, &token.rank)?.transferable {
+        return Err(StdError::generic_err(format!(
+            "Token {} cannot be transferred",
+            token_id
+        )));
+    }
+    Ok(())
+}
+
+/// Errors if the token has a lock that has not expired yet
+fn check_unlocked<S: ReadonlyStorage>(
+    storage: &S,
+    block: &BlockInfo,
+    token_id: &str,
+) -> StdResult<()> {
+    match locks_read(storage).may_load(token_id.as_bytes())? {
+        Some(until) if !until.is_expired(block) => Err(StdError::generic_err(format!(
+            "Token {} is locked until {}",
+            token_id, until
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn generate_token_id<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    rank: &str,
+    serial: u64,
+) -> StdResult<String> {
+    let contract_info = query_contract_info(&deps)?;
+    Ok(format!("{}{}{}", contract_info.symbol, rank, serial))
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Minter {} => to_binary(&query_minter(deps)?),
+        QueryMsg::Minters {} => to_binary(&query_minters(deps)?),
+        QueryMsg::ContractInfo {} => to_binary(&query_contract_info(deps)?),
+        QueryMsg::NftInfo { token_id } => to_binary(&query_nft_info(deps, token_id)?),
+        QueryMsg::OwnerOf { token_id } => to_binary(&query_owner_of(deps, token_id)?),
+        QueryMsg::AllNftInfo { token_id } => to_binary(&query_all_nft_info(deps, token_id)?),
+        QueryMsg::ApprovedForAll {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_all_approvals(deps, owner, start_after, limit)?),
+        QueryMsg::Ranks {} => to_binary(&query_ranks(deps)?),
+        QueryMsg::RankSupply { rank } => to_binary(&query_rank_supply(deps, rank)?),
+        QueryMsg::Tokens {
+            owner,
+            rank,
+            start_after,
+            limit,
+        } => to_binary(&query_tokens(deps, owner, rank, start_after, limit)?),
+        QueryMsg::AllTokens { start_after, limit } => {
+            to_binary(&query_all_tokens(deps, start_after, limit)?)
+        }
+        QueryMsg::TokensByRank {
+            rank,
+            start_after,
+            limit,
+        } => to_binary(&query_tokens_by_rank(deps, rank, start_after, limit)?),
+        QueryMsg::IsMintable { rank } => to_binary(&query_mintable(deps, rank)?),
+        QueryMsg::IsFrozen {} => to_binary(&is_frozen(&deps.storage)?),
+        QueryMsg::IsFinalized {} => to_binary(&is_finalized(&deps.storage)?),
+        QueryMsg::BurnedTokens { rank } => to_binary(&query_burned_tokens(deps, rank)?),
+        QueryMsg::LockInfo { token_id } => to_binary(&query_lock_info(deps, token_id)?),
+    }
+}
+
+fn query_mintable<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    rank: String,
+) -> StdResult<bool> {
+
+    // retired athletes cannot be minted at any rank
+    if is_frozen(&deps.storage)? {
+        return Ok(false);
+    }
+
+    let rank = load_rank(&deps.storage, &rank)?;
+    let supply = rank_num_tokens(&deps.storage, &rank.id)?;
+    // a full rank stays unmintable even if its supply somehow went past the cap
+    let is_mintable = Uint128::from(supply) < rank.cap;
+
+    Ok(is_mintable)
+}
+
+fn query_minter<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<MinterResponse> {
+    let minter_raw = mint_read(&deps.storage).load()?;
+    let minter = deps.api.human_address(&minter_raw)?;
+    Ok(MinterResponse { minter })
+}
+
+fn query_minters<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<MintersResponse> {
+    let minters: StdResult<Vec<_>> = minters_read(&deps.storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| {
+            item.and_then(|(k, info)| {
+                Ok(MinterItem {
+                    minter: deps.api.human_address(&k.into())?,
+                    ranks: info.ranks,
+                    quota: info.quota,
+                    minted: info.minted,
+                })
+            })
+        })
+        .collect();
+    Ok(MintersResponse { minters: minters? })
+}
+
+fn query_contract_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<ContractInfo> {
+    contract_info_read(&deps.storage).load()
+}
+
+fn query_ranks<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<RanksResponse> {
+    let ranks: StdResult<Vec<_>> = ranks_read(&deps.storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| item.map(|(_, rank)| rank))
+        .collect();
+    Ok(RanksResponse { ranks: ranks? })
+}
+
+fn query_rank_supply<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    rank: String,
+) -> StdResult<RankSupplyResponse> {
+    let rank = load_rank(&deps.storage, &rank)?;
+    Ok(RankSupplyResponse {
+        supply: rank_num_tokens(&deps.storage, &rank.id)?,
+        burned: burned_num_tokens(&deps.storage, &rank.id)?,
+        cap: rank.cap,
+    })
+}
+
+fn query_burned_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    rank: String,
+) -> StdResult<NumTokensResponse> {
+    let count = burned_num_tokens(&deps.storage, &rank)?;
+    Ok(NumTokensResponse { count })
+}
+
+fn query_lock_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: String,
+) -> StdResult<LockInfoResponse> {
+    let until = locks_read(&deps.storage).may_load(token_id.as_bytes())?;
+    Ok(LockInfoResponse { until })
+}
+
+fn query_nft_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: String,
+) -> StdResult<NftInfoResponse> {
+    let info = tokens_read(&deps.storage).load(token_id.as_bytes())?;
+    nft_info(deps, &token_id, info)
+}
+
+/// Builds ERC-721 metadata for the token, filling in what was not set at mint
+fn nft_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: &str,
+    info: TokenInfo,
+) -> StdResult<NftInfoResponse> {
+    let contract_info = query_contract_info(deps)?;
+    let rank = load_rank(&deps.storage, &info.rank)?;
+    let metadata = info.metadata;
+    let serial = info.serial;
+    let thresholds = load_level_thresholds(&deps.storage)?;
+
+    let mut attributes = vec![
+        Trait {
+            trait_type: "Rank".to_string(),
+            value: rank.name.clone(),
+        },
+        Trait {
+            trait_type: "Serial".to_string(),
+            value: serial.to_string(),
+        },
+    ];
+    attributes.extend(metadata.attributes);
+
+    Ok(NftInfoResponse {
+        name: metadata.name.unwrap_or_else(|| {
+            format!("{} {} #{}", contract_info.name, rank.name, serial)
+        }),
+        description: metadata.description.unwrap_or_default(),
+        image: metadata.image,
+        token_uri: contract_info.base_uri.map(|uri| format!("{}{}", uri, token_id)),
+        attributes,
+        rank: info.rank,
+        stats: TokenStats {
+            level: level(&thresholds, info.stats.xp),
+            xp: info.stats.xp,
+            games_played: info.stats.games_played,
+            best_score: info.stats.best_score,
+        },
+    })
+}
+
+fn query_owner_of<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: String,
+) -> StdResult<OwnerOfResponse> {
+    let info = tokens_read(&deps.storage).load(token_id.as_bytes())?;
+    Ok(OwnerOfResponse {
+        owner: deps.api.human_address(&info.owner)?,
+        approvals: humanize_approvals(deps.api, &info)?,
+    })
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+fn query_all_approvals<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: HumanAddr,
+    start_after: Option<HumanAddr>,
+    limit: Option<u32>,
+) -> StdResult<ApprovedForAllResponse> {
+    let owner_raw = deps.api.canonical_address(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start_human(deps.api, start_after)?;
+
+    let res: StdResult<Vec<_>> = operators_read(&deps.storage, &owner_raw)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.and_then(|(k, expires)| {
+                Ok(cw721::Approval {
+                    spender: deps.api.human_address(&k.into())?,
+                    expires,
+                })
+            })
+        })
+        .collect();
+    Ok(ApprovedForAllResponse { operators: res? })
+}
+
+fn query_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: HumanAddr,
+    rank: Option<String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let owner_raw = deps.api.canonical_address(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start_string(start_after);
+
+    // with a rank, only the owner's tokens of that rank are read
+    let tokens: StdResult<Vec<String>> = match rank {
+        Some(rank) => owner_rank_tokens_read(&deps.storage, &owner_raw, &rank)
+            .range(start.as_deref(), None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(k, _)| String::from_utf8_lossy(&k).to_string()))
+            .collect(),
+        None => owner_tokens_read(&deps.storage, &owner_raw)
+            .range(start.as_deref(), None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(k, _)| String::from_utf8_lossy(&k).to_string()))
+            .collect(),
+    };
+    Ok(TokensResponse { tokens: tokens? })
+}
+
+fn query_all_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start_string(start_after);
+
+    let tokens: StdResult<Vec<String>> = tokens_read(&deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(k, _)| String::from_utf8_lossy(&k).to_string()))
+        .collect();
+    Ok(TokensResponse { tokens: tokens? })
+}
+
+fn query_tokens_by_rank<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    rank: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start_string(start_after);
+
+    let tokens: StdResult<Vec<String>> = rank_tokens_read(&deps.storage, &rank)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(k, _)| String::from_utf8_lossy(&k).to_string()))
+        .collect();
+    Ok(TokensResponse { tokens: tokens? })
+}
+
+fn query_all_nft_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: String,
+) -> StdResult<AllNftInfoResponse> {
+    let info = tokens_read(&deps.storage).load(token_id.as_bytes())?;
+    Ok(AllNftInfoResponse {
+        access: OwnerOfResponse {
+            owner: deps.api.human_address(&info.owner)?,
+            approvals: humanize_approvals(deps.api, &info)?,
+        },
+        info: nft_info(deps, &token_id, info)?,
+    })
+}
+
+fn humanize_approvals<A: Api>(api: A, info: &TokenInfo) -> StdResult<Vec<cw721::Approval>> {
+    info.approvals
+        .iter()
+        .map(|apr| humanize_approval(api, apr))
+        .collect()
+}
+
+fn humanize_approval<A: Api>(api: A, approval: &Approval) -> StdResult<cw721::Approval> {
+    Ok(cw721::Approval {
+        spender: api.human_address(&approval.spender)?,
+        expires: approval.expires,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{from_binary, StdError, WasmMsg, Uint128};
+
+    use super::*;
+    use cw721::ApprovedForAllResponse;
+    use crate::state::{RankInfo, UpgradeRecipe};
+
+    const MINTER: &str = "cosmos2contract";
+    const CONTRACT_NAME: &str = "Lebron Token";
+    const SYMBOL: &str = "LBJ";
+    const BASE_CAP: Uint128 = Uint128(2);
+    const SILVER_CAP: Uint128 = Uint128(2);
+    const GOLD_CAP: Uint128 = Uint128(2);
+
+    /// 3 base make a silver and 5 silver make a gold
+    fn rank_table(base_cap: Uint128, silver_cap: Uint128, gold_cap: Uint128) -> Vec<RankInfo> {
+        vec![
+            RankInfo {
+                id: "B".into(),
+                name: "Base".into(),
+                cap: base_cap,
+                upgrade: Some(UpgradeRecipe { count: 3, to: "S".into() }),
+                transferable: true,
+            },
+            RankInfo {
+                id: "S".into(),
+                name: "Silver".into(),
+                cap: silver_cap,
+                upgrade: Some(UpgradeRecipe { count: 5, to: "G".into() }),
+                transferable: true,
+            },
+            RankInfo {
+                id: "G".into(),
+                name: "Gold".into(),
+                cap: gold_cap,
+                upgrade: None,
+                transferable: true,
+            },
+        ]
+    }
+
+    fn setup_contract<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>) {
+        let msg = InitMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            minter: MINTER.into(),
+            base_uri: None,
+            level_thresholds: vec![],
+            ranks: rank_table(BASE_CAP, SILVER_CAP, GOLD_CAP),
+        };
+        let env = mock_env("creator", &[]);
+        let res = init(deps, env, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            minter: MINTER.into(),
+            base_uri: None,
+            level_thresholds: vec![],
+            ranks: rank_table(BASE_CAP, SILVER_CAP, GOLD_CAP),
+        };
+        let env = mock_env("creator", &[]);
+
+        // we can just call .unwrap() to assert this was a success
+        let res = init(&mut deps, env, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // it worked, let's query the state
+        let res = query_minter(&deps).unwrap();
+        assert_eq!(MINTER, res.minter.as_str());
+        let info = query_contract_info(&deps).unwrap();
+        assert_eq!(
+            info,
+            ContractInfo {
+                name: CONTRACT_NAME.to_string(),
+                symbol: SYMBOL.to_string(),
+                base_uri: None,
+            }
+        );
+        assert_eq!(rank_table(BASE_CAP, SILVER_CAP, GOLD_CAP).len(), query_ranks(&deps).unwrap().ranks.len());
+
+        let supply = query_rank_supply(&deps, "B".into()).unwrap();
+        assert_eq!(0, supply.supply);
+        assert_eq!(BASE_CAP, supply.cap);
+
+        // list the token_ids
+        let tokens = query_tokens_by_rank(&deps, "B".into(), None, None).unwrap();
+        assert_eq!(0, tokens.tokens.len());
+    }
+
+    #[test]
+    fn minting() {
+        let mut deps = mock_dependencies(100, &[]);
+        setup_contract(&mut deps);
+
+        // token ids are generated from the symbol, the rank and the serial
+        let token_id = format!("{}B1", SYMBOL);
+        let token_id2 = format!("{}B2", SYMBOL);
+        let rank = "B".to_string();
+
+        let mint_msg = HandleMsg::Mint {
+            owner: "medusa".into(),
+            rank: rank.clone(),
+            metadata: None,
+        };
+
+        // random cannot mint
+        let random = mock_env("random", &[]);
+        let err = handle(&mut deps, random, mint_msg.clone()).unwrap_err();
+        match err {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // minter can mint
+        let allowed = mock_env(MINTER, &[]);
+        let res = handle(&mut deps, allowed, mint_msg).unwrap();
+        assert_eq!(log("token_id", &token_id), res.log[2]);
+
+        // ensure num tokens increases
+        let count = query_rank_supply(&deps, "B".into()).unwrap();
+        assert_eq!(1, count.supply);
+
+        // unknown nft returns error
+        let _ = query_nft_info(&deps, "unknown".to_string()).unwrap_err();
+
+        // this nft info is correct
+        let info = query_nft_info(&deps, token_id.clone()).unwrap();
+        assert_eq!(
+            info,
+            NftInfoResponse {
+                name: "Lebron Token Base #1".to_string(),
+                description: "".to_string(),
+                image: None,
+                token_uri: None,
+                attributes: vec![
+                    Trait { trait_type: "Rank".into(), value: "Base".into() },
+                    Trait { trait_type: "Serial".into(), value: "1".into() },
+                ],
+                rank: rank.clone(),
+                stats: TokenStats { level: 1, ..Default::default() },
+            }
+        );
+
+        // owner info is correct
+        let owner = query_owner_of(&deps, token_id.clone()).unwrap();
+        assert_eq!(
+            owner,
+            OwnerOfResponse {
+                owner: "medusa".into(),
+                approvals: vec![],
+            }
+        );
+
+        // minting the same rank again gets the next serial
+        let mint_msg2 = HandleMsg::Mint {
+            owner: "hercules".into(),
+            rank: "B".into(),
+            metadata: None,
+        };
+        let allowed = mock_env(MINTER, &[]);
+        let res = handle(&mut deps, allowed, mint_msg2.clone()).unwrap();
+        assert_eq!(log("token_id", &token_id2), res.log[2]);
+        let owner = query_owner_of(&deps, token_id2.clone()).unwrap();
+        assert_eq!(HumanAddr::from("hercules"), owner.owner);
+
+        // Cannot mint more than the cap
+        let allowed = mock_env(MINTER, &[]);
+        let err = handle(&mut deps, allowed, mint_msg2).unwrap_err();
+        match err {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Minting cannot exceed the cap")
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // list the token_ids
+        let tokens = query_tokens_by_rank(&deps, "B".into(), None, None).unwrap();
+        assert_eq!(vec![token_id, token_id2], tokens.tokens);
+        let count = query_rank_supply(&deps, "B".into()).unwrap();
+        assert_eq!(2, count.supply);
+    }
+
+    #[test]
+    fn update_nft() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // Mint a token
+        let token_id = format!("{}B1", SYMBOL);
+
+        let mint_msg = HandleMsg::Mint {
+            owner: "venus".into(),
+            rank: "B".into(),
+            metadata: None,
+        };
+
+        let minter = mock_env(MINTER, &[]);
+        handle(&mut deps, minter, mint_msg).unwrap();
+
+        // random cannot transfer
+        let random = mock_env("random", &[]);
+        let transfer_msg = HandleMsg::TransferNft {
+            recipient: "random".into(),
+            token_id: token_id.clone(),
+        };
+
+        let err = handle(&mut deps, random, transfer_msg.clone()).unwrap_err();
+
+        match err {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // owner can transfer
+        let random = mock_env("venus", &[]);
+        let transfer_msg = HandleMsg::TransferNft {
+            recipient: "random".into(),
+            token_id: token_id.clone(),
+        };
+
+        let res = handle(&mut deps, random, transfer_msg.clone()).unwrap();
+
+        assert_eq!(
+            res,
+            HandleResponse {
+                messages: vec![],
+                log: vec![
+                    log("action", "transfer_nft"),
+                    log("sender", "venus"),
+                    log("recipient", "random"),
+                    log("token_id", token_id.clone()),
+                ],
+                data: None,
+            }
+        );
+    }
+
+    #[test]
+    fn sending_nft() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // Mint a token
+        let token_id = format!("{}B1", SYMBOL);
+
+        let mint_msg = HandleMsg::Mint {
+            owner: "venus".into(),
+            rank: "B".into(),
+            metadata: None,
+        };
+
+        let minter = mock_env(MINTER, &[]);
+        handle(&mut deps, minter, mint_msg).unwrap();
+
+        // random cannot send
+        let msg = to_binary("You now have the melting power").unwrap();
+        let send_msg = HandleMsg::SendNft {
+            contract: "another_contract".into(),
+            token_id: token_id.clone(),
+            msg: Some(msg.clone()),
+        };
+
+        let random = mock_env("random", &[]);
+        let err = handle(&mut deps, random, send_msg.clone()).unwrap_err();
+        match err {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // but owner can
+        let random = mock_env("venus", &[]);
+        let res = handle(&mut deps, random, send_msg).unwrap();
+        let payload = Cw721ReceiveMsg {
+            sender: "venus".into(),
+            token_id: token_id.clone(),
+            msg: Some(msg),
+        };
+        assert_eq!(
+            res,
+            HandleResponse {
+                messages: vec![payload.into_cosmos_msg("another_contract".into()).unwrap()],
+                log: vec![
+                    log("action", "send_nft"),
+                    log("sender", "venus"),
+                    log("recipient", "another_contract"),
+                    log("token_id", token_id),
+                ],
+                data: None,
+            }
+        );
+    }
+
+    #[test]
+    fn approving_revoking() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // Mint a token
+        let token_id = format!("{}B1", SYMBOL);
+
+        let mint_msg = HandleMsg::Mint {
+            owner: "demeter".into(),
+            rank: "B".into(),
+            metadata: None,
+        };
+
+        let minter = mock_env(MINTER, &[]);
+        handle(&mut deps, minter, mint_msg).unwrap();
+
+        // Give random transferring power
+        let approve_msg = HandleMsg::Approve {
+            spender: "random".into(),
+            token_id: token_id.clone(),
+            expires: None,
+        };
+        let owner = mock_env("demeter", &[]);
+        let res = handle(&mut deps, owner, approve_msg).unwrap();
+        assert_eq!(
+            res,
+            HandleResponse {
+                messages: vec![],
+                log: vec![
+                    log("action", "approve"),
+                    log("sender", "demeter"),
+                    log("spender", "random"),
+                    log("token_id", token_id.clone()),
+                ],
+                data: None,
+            }
+        );
+
+        // random can now transfer
+        let random = mock_env("random", &[]);
+        let transfer_msg = HandleMsg::TransferNft {
+            recipient: "person".into(),
+            token_id: token_id.clone(),
+        };
+        handle(&mut deps, random, transfer_msg).unwrap();
+
+        // Approvals are removed / cleared
+        let query_msg = QueryMsg::OwnerOf {
+            token_id: token_id.clone(),
+        };
+        let res: OwnerOfResponse = from_binary(&query(&deps, query_msg.clone()).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            OwnerOfResponse {
+                owner: "person".into(),
+                approvals: vec![],
+            }
+        );
+
+        // Approve, revoke, and check for empty, to test revoke
+        let approve_msg = HandleMsg::Approve {
+            spender: "random".into(),
+            token_id: token_id.clone(),
+            expires: None,
+        };
+        let owner = mock_env("person", &[]);
+        handle(&mut deps, owner.clone(), approve_msg).unwrap();
+
+        let revoke_msg = HandleMsg::Revoke {
+            spender: "random".into(),
+            token_id: token_id.clone(),
+        };
+        handle(&mut deps, owner, revoke_msg).unwrap();
+
+        // Approvals are now removed / cleared
+        let res: OwnerOfResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            OwnerOfResponse {
+                owner: "person".into(),
+                approvals: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn approving_all_revoking_all() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // Mint a couple tokens (from the same owner)
+        let token_id1 = format!("{}B1", SYMBOL);
+        let token_id2 = format!("{}B2", SYMBOL);
+
+        let mint_msg1 = HandleMsg::Mint {
+            owner: "demeter".into(),
+            rank: "B".into(),
+            metadata: None,
+        };
+
+        let minter = mock_env(MINTER, &[]);
+        handle(&mut deps, minter.clone(), mint_msg1).unwrap();
+
+        let mint_msg2 = HandleMsg::Mint {
+            owner: "demeter".into(),
+            rank: "B".into(),
+            metadata: None,
+        };
+
+        handle(&mut deps, minter, mint_msg2).unwrap();
+
+        // paginate the token_ids
+        let tokens = query_tokens_by_rank(&deps, "B".into(), None, Some(1)).unwrap();
+        assert_eq!(1, tokens.tokens.len());
+        assert_eq!(vec![token_id1.clone()], tokens.tokens);
+        let tokens = query_tokens_by_rank(&deps, "B".into(), Some(token_id1.clone()), Some(3)).unwrap();
+        assert_eq!(1, tokens.tokens.len());
+        assert_eq!(vec![token_id2.clone()], tokens.tokens);
+
+        // demeter gives random full (operator) power over her tokens
+        let approve_all_msg = HandleMsg::ApproveAll {
+            operator: "random".into(),
+            expires: None,
+        };
+        let owner = mock_env("demeter", &[]);
+        let res = handle(&mut deps, owner, approve_all_msg).unwrap();
+        assert_eq!(
+            res,
+            HandleResponse {
+                messages: vec![],
+                log: vec![
+                    log("action", "approve_all"),
+                    log("sender", "demeter"),
+                    log("operator", "random"),
+                ],
+                data: None,
+            }
+        );
+
+        // random can now transfer
+        let random = mock_env("random", &[]);
+        let transfer_msg = HandleMsg::TransferNft {
+            recipient: "person".into(),
+            token_id: token_id1.clone(),
+        };
+        handle(&mut deps, random.clone(), transfer_msg).unwrap();
+
+        // random can now send
+        let inner_msg = WasmMsg::Execute {
+            contract_addr: "another_contract".into(),
+            msg: to_binary("You now also have the growing power").unwrap(),
+            send: vec![],
+        };
+        let msg: CosmosMsg = CosmosMsg::Wasm(inner_msg);
+
+        let send_msg = HandleMsg::SendNft {
+            contract: "another_contract".into(),
+            token_id: token_id2.clone(),
+            msg: Some(to_binary(&msg).unwrap()),
+        };
+        handle(&mut deps, random, send_msg).unwrap();
+
+        // Approve_all, revoke_all, and check for empty, to test revoke_all
+        let approve_all_msg = HandleMsg::ApproveAll {
+            operator: "operator".into(),
+            expires: None,
+        };
+        // person is now the owner of the tokens
+        let owner = mock_env("person", &[]);
+        handle(&mut deps, owner.clone(), approve_all_msg).unwrap();
+
+        let res = query_all_approvals(&deps, "person".into(), None, None).unwrap();
+        assert_eq!(
+            res,
+            ApprovedForAllResponse {
+                operators: vec![cw721::Approval {
+                    spender: "operator".into(),
+                    expires: Expiration::Never {}
+                }]
+            }
+        );
+
+        // second approval
+        let buddy_expires = Expiration::AtHeight(1234567);
+        let approve_all_msg = HandleMsg::ApproveAll {
+            operator: "buddy".into(),
+            expires: Some(buddy_expires),
+        };
+        let owner = mock_env("person", &[]);
+        handle(&mut deps, owner.clone(), approve_all_msg).unwrap();
 
-        // it worked, let's query the state
-        let res = query_minter(&deps).unwrap();
-        assert_eq!(MINTER, res.minter.as_str());
-        let info = query_contract_info(&deps).unwrap();
+        // and paginate queries
+        let res = query_all_approvals(&deps, "person".into(), None, Some(1)).unwrap();
         assert_eq!(
-            info,
-            ContractInfo {
-                name: CONTRACT_NAME.to_string(),
-                symbol: SYMBOL.to_string(),
-                base_cap: BASE_CAP,
-                silver_cap: SILVER_CAP,
-                gold_cap: GOLD_CAP,
+            res,
+            ApprovedForAllResponse {
+                operators: vec![cw721::Approval {
+                    spender: "buddy".into(),
+                    expires: buddy_expires,
+                }]
+            }
+        );
+        let res =
+            query_all_approvals(&deps, "person".into(), Some("buddy".into()), Some(2)).unwrap();
+        assert_eq!(
+            res,
+            ApprovedForAllResponse {
+                operators: vec![cw721::Approval {
+                    spender: "operator".into(),
+                    expires: Expiration::Never {}
+                }]
             }
         );
 
-        let count = query_base_tokens(&deps).unwrap();
-        assert_eq!(0, count.count);
+        let revoke_all_msg = HandleMsg::RevokeAll {
+            operator: "operator".into(),
+        };
+        handle(&mut deps, owner, revoke_all_msg).unwrap();
 
-        // list the token_ids
-        let tokens = query_all_base_tokens(&deps, None, None).unwrap();
-        assert_eq!(0, tokens.tokens.len());
+        // Approvals are removed / cleared without affecting others
+        let res = query_all_approvals(&deps, "person".into(), None, None).unwrap();
+        assert_eq!(
+            res,
+            ApprovedForAllResponse {
+                operators: vec![cw721::Approval {
+                    spender: "buddy".into(),
+                    expires: buddy_expires,
+                }]
+            }
+        );
     }
-
     #[test]
-    fn minting() {
-        let mut deps = mock_dependencies(100, &[]);
+    fn freezing() {
+        let mut deps = mock_dependencies(20, &[]);
         setup_contract(&mut deps);
 
-        let token_id = "petrify".to_string();
-        let token_id2 = "second".to_string();
-        //let token_id3 = "cap".to_string();
-        // let name = "Petrify with Gaze".to_string();
-        // let description = "Allows the owner to petrify anyone looking at him or her".to_string();
-        let rank = "base".to_string();
-
         let mint_msg = HandleMsg::Mint {
-            //token_id: token_id.clone(),
             owner: "medusa".into(),
-            // name: name.clone(),
-            // description: Some(description.clone()),
-            // image: None,
-            rank: rank.clone(),
+            rank: "B".into(),
+            metadata: None,
         };
+        let allowed = mock_env(MINTER, &[]);
+        handle(&mut deps, allowed.clone(), mint_msg.clone()).unwrap();
 
-        // random cannot mint
+        // only the minter can freeze
         let random = mock_env("random", &[]);
-        let err = handle(&mut deps, random, mint_msg.clone()).unwrap_err();
-        match err {
+        match handle(&mut deps, random, HandleMsg::Freeze {}).unwrap_err() {
             StdError::Unauthorized { .. } => {}
             e => panic!("unexpected error: {}", e),
         }
+        handle(&mut deps, allowed.clone(), HandleMsg::Freeze {}).unwrap();
+        assert!(is_frozen(&deps.storage).unwrap());
+
+        // no rank can be minted anymore
+        assert!(!query_mintable(&deps, "B".into()).unwrap());
+        assert!(!query_mintable(&deps, "G".into()).unwrap());
+        match handle(&mut deps, allowed, mint_msg).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Contract is frozen"),
+            e => panic!("unexpected error: {}", e),
+        }
 
-        // minter can mint
-        let allowed = mock_env(MINTER, &[]);
-        let _ = handle(&mut deps, allowed, mint_msg.clone()).unwrap();
+        // existing tokens can still move
+        let owner = mock_env("medusa", &[]);
+        let transfer_msg = HandleMsg::TransferNft {
+            recipient: "random".into(),
+            token_id: "LBJB1".into(),
+        };
+        handle(&mut deps, owner, transfer_msg).unwrap();
+    }
 
-        // ensure num tokens increases
-        let count = query_base_tokens(&deps).unwrap();
-        assert_eq!(1, count.count);
+    #[test]
+    fn upgrading_ranks() {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            minter: MINTER.into(),
+            base_uri: None,
+            level_thresholds: vec![],
+            ranks: rank_table(Uint128(10), Uint128(1), GOLD_CAP),
+        };
+        init(&mut deps, mock_env("creator", &[]), msg).unwrap();
 
-        // unknown nft returns error
-        let _ = query_nft_info(&deps, "unknown".to_string()).unwrap_err();
+        let minter = mock_env(MINTER, &[]);
+        for owner in &["medusa", "medusa", "medusa", "medusa", "hercules"] {
+            let mint_msg = HandleMsg::Mint {
+                owner: (*owner).into(),
+                rank: "B".into(),
+                metadata: None,
+            };
+            handle(&mut deps, minter.clone(), mint_msg).unwrap();
+        }
+        let upgrade = |token_ids: &[&str]| HandleMsg::Upgrade {
+            token_ids: token_ids.iter().map(|id| id.to_string()).collect(),
+        };
+        let medusa = mock_env("medusa", &[]);
 
-        // this nft info is correct
-        let info = query_nft_info(&deps, token_id.clone()).unwrap();
+        // only the owner's own tokens, each once
+        match handle(&mut deps, medusa.clone(), upgrade(&["LBJB1", "LBJB2", "LBJB5"])).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        handle(&mut deps, medusa.clone(), upgrade(&["LBJB1", "LBJB2", "LBJB2"])).unwrap_err();
+
+        // the recipe takes exactly 3 base tokens
+        match handle(&mut deps, medusa.clone(), upgrade(&["LBJB1", "LBJB2"])).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Upgrading rank B takes 3 tokens"),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // an operator cannot upgrade on the owner's behalf
+        let approve_all = HandleMsg::ApproveAll {
+            operator: "operator".into(),
+            expires: None,
+        };
+        handle(&mut deps, medusa.clone(), approve_all).unwrap();
+        let operator = mock_env("operator", &[]);
+        handle(&mut deps, operator, upgrade(&["LBJB1", "LBJB2", "LBJB3"])).unwrap_err();
+
+        // the silver token is minted in the same handler and the base tokens are burned
+        let res = handle(&mut deps, medusa.clone(), upgrade(&["LBJB1", "LBJB2", "LBJB3"])).unwrap();
+        assert_eq!(0, res.messages.len());
+        assert_eq!(log("action", "upgrade"), res.log[0]);
+        assert_eq!(log("token_id", "LBJS1"), res.log[5]);
         assert_eq!(
-            info,
-            NftInfoResponse {
-                // name: name.clone(),
-                // description: description.clone(),
-                // image: None,
-                rank: rank.clone(),
-            }
+            "medusa",
+            query_owner_of(&deps, "LBJS1".into()).unwrap().owner.as_str()
         );
+        assert_eq!("S", query_nft_info(&deps, "LBJS1".into()).unwrap().rank);
+        query_owner_of(&deps, "LBJB1".into()).unwrap_err();
+        assert_eq!(1, query_rank_supply(&deps, "S".into()).unwrap().supply);
+        assert_eq!(2, query_rank_supply(&deps, "B".into()).unwrap().supply);
+        assert_eq!(3, query_burned_tokens(&deps, "B".into()).unwrap().count);
+
+        // tokens of the wrong rank are rejected
+        match handle(&mut deps, medusa.clone(), upgrade(&["LBJB4", "LBJS1", "LBJB6"])).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Token LBJS1 is not of rank B"),
+            e => panic!("unexpected error: {}", e),
+        }
 
-        // owner info is correct
-        let owner = query_owner_of(&deps, token_id.clone()).unwrap();
-        assert_eq!(
-            owner,
-            OwnerOfResponse {
+        // the cap is checked before anything is burned
+        for _ in 0..2 {
+            let mint_msg = HandleMsg::Mint {
                 owner: "medusa".into(),
-                approvals: vec![],
-            }
+                rank: "B".into(),
+                metadata: None,
+            };
+            handle(&mut deps, minter.clone(), mint_msg).unwrap();
+        }
+        match handle(&mut deps, medusa, upgrade(&["LBJB4", "LBJB6", "LBJB7"])).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Minting cannot exceed the cap"),
+            e => panic!("unexpected error: {}", e),
+        }
+        assert_eq!(
+            "medusa",
+            query_owner_of(&deps, "LBJB4".into()).unwrap().owner.as_str()
         );
+    }
 
-        // Cannot mint same token_id again
-        let mint_msg2 = HandleMsg::Mint {
-            //token_id: token_id.clone(),
-            owner: "hercules".into(),
-            // name: "copy cat".into(),
-            // description: None,
-            // image: None,
-            rank: "base".into(),
+    #[test]
+    fn burning() {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            minter: MINTER.into(),
+            base_uri: None,
+            level_thresholds: vec![],
+            ranks: rank_table(Uint128(2), SILVER_CAP, GOLD_CAP),
         };
+        init(&mut deps, mock_env("creator", &[]), msg).unwrap();
 
-        let allowed = mock_env(MINTER, &[]);
-        let err = handle(&mut deps, allowed, mint_msg2).unwrap_err();
-        match err {
-            StdError::GenericErr { msg, .. } => {
-                assert_eq!(msg.as_str(), "token_id already claimed")
-            }
-            e => panic!("unexpected error: {}", e),
-        }
-
-        let mint_msg3 = HandleMsg::Mint {
-            //token_id: token_id2.clone(),
-            owner: "hercules".into(),
-            // name: "below cap".into(),
-            // description: None,
-            // image: None,
-            rank: "base".into(),
+        let minter = mock_env(MINTER, &[]);
+        let mint_msg = HandleMsg::Mint {
+            owner: "medusa".into(),
+            rank: "B".into(),
+            metadata: None,
         };
-        let allowed = mock_env(MINTER, &[]);
-        let _ = handle(&mut deps, allowed, mint_msg3).unwrap();
+        handle(&mut deps, minter.clone(), mint_msg.clone()).unwrap();
+        handle(&mut deps, minter.clone(), mint_msg.clone()).unwrap();
+        handle(&mut deps, minter.clone(), mint_msg.clone()).unwrap_err();
 
-        // Cannot mint more than the cap
-        let mint_msg4 = HandleMsg::Mint {
-            //token_id: token_id3.clone(),
-            owner: "hercules".into(),
-            // name: "over the cap".into(),
-            // description: None,
-            // image: None,
-            rank: "base".into(),
+        // only someone allowed to transfer the token can burn it
+        let burn_msg = HandleMsg::Burn {
+            token_id: "LBJB1".into(),
         };
-        let allowed = mock_env(MINTER, &[]);
-        let err = handle(&mut deps, allowed, mint_msg4).unwrap_err();
-        match err {
-            StdError::GenericErr { msg, .. } => {
-                assert_eq!(msg.as_str(), "Minting cannot exceed the cap")
-            }
+        match handle(&mut deps, mock_env("hercules", &[]), burn_msg.clone()).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
             e => panic!("unexpected error: {}", e),
         }
 
-        // list the token_ids
-        let tokens = query_all_base_tokens(&deps, None, None).unwrap();
-        assert_eq!(2, tokens.tokens.len());
-        assert_eq!(vec![token_id.clone(), token_id2.clone()], tokens.tokens);
+        let res = handle(&mut deps, mock_env("medusa", &[]), burn_msg.clone()).unwrap();
+        assert_eq!(log("action", "burn"), res.log[0]);
+        query_owner_of(&deps, "LBJB1".into()).unwrap_err();
+        assert_eq!(1, query_rank_supply(&deps, "B".into()).unwrap().supply);
+        assert_eq!(1, query_burned_tokens(&deps, "B".into()).unwrap().count);
+        handle(&mut deps, mock_env("medusa", &[]), burn_msg).unwrap_err();
+
+        // the burned token frees its slot under the cap, without reusing its id
+        let res = handle(&mut deps, minter, mint_msg).unwrap();
+        assert_eq!(log("token_id", "LBJB3"), res.log[2]);
+        assert_eq!(2, query_rank_supply(&deps, "B".into()).unwrap().supply);
+
+        // the supply of a rank never goes below zero
+        match decrement_rank_tokens(&mut deps.storage, "S").unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg, "No tokens of rank S left"),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn rank_table_validation() {
+        let mut deps = mock_dependencies(20, &[]);
+        let init_with = |ranks: Vec<RankInfo>| InitMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            minter: MINTER.into(),
+            ranks,
+            base_uri: None,
+            level_thresholds: vec![],
+        };
+        let env = mock_env("creator", &[]);
 
-        
-        // list the number of tokens
-        // burnt token must be deducted to the number of tokens
-        let num_tokens = query_base_tokens(&deps).unwrap();
-        assert_eq!(1, num_tokens.count);
+        init(&mut deps, env.clone(), init_with(vec![])).unwrap_err();
 
-        // burnt token is now owned by the burn address
-        let owner = query_owner_of(&deps, token_id2.clone()).unwrap();
-        assert_eq!(
-            owner,
-            OwnerOfResponse {
-                owner: "terra1jrg2hv92xpjl4wwgd84jcm4cs2pfmzdxl6y2sx".into(),
-                approvals: vec![],
-            }
-        );
+        // ids are followed by the serial in token ids, so they cannot contain digits
+        let mut ranks = rank_table(BASE_CAP, SILVER_CAP, GOLD_CAP);
+        ranks[2].id = "G1".into();
+        init(&mut deps, env.clone(), init_with(ranks)).unwrap_err();
+
+        let mut ranks = rank_table(BASE_CAP, SILVER_CAP, GOLD_CAP);
+        ranks[2].id = "S".into();
+        init(&mut deps, env.clone(), init_with(ranks)).unwrap_err();
+
+        let mut ranks = rank_table(BASE_CAP, SILVER_CAP, GOLD_CAP);
+        ranks[1].upgrade = Some(UpgradeRecipe { count: 5, to: "P".into() });
+        init(&mut deps, env, init_with(ranks)).unwrap_err();
     }
 
     #[test]
-    fn update_nft() {
+    fn metadata() {
         let mut deps = mock_dependencies(20, &[]);
         setup_contract(&mut deps);
 
-        // Mint a token
-        let token_id = "melt".to_string();
-        //let name = "Melting power".to_string();
-        //let description = "Allows the owner to melt anyone looking at him or her".to_string();
-
+        let season = Trait {
+            trait_type: "Season".into(),
+            value: "2020-21".into(),
+        };
+        let jersey = Trait {
+            trait_type: "Jersey Number".into(),
+            value: "23".into(),
+        };
         let mint_msg = HandleMsg::Mint {
-            //token_id: token_id.clone(),
-            owner: "venus".into(),
-            // name: name.clone(),
-            // description: Some(description.clone()),
-            // image: None,
-            rank: "base".into()
+            owner: "medusa".into(),
+            rank: "G".into(),
+            metadata: Some(Metadata {
+                name: None,
+                description: Some("Rookie season".into()),
+                image: Some("ipfs://image/lbj.png".into()),
+                attributes: vec![season.clone(), jersey.clone()],
+            }),
         };
-
         let minter = mock_env(MINTER, &[]);
-        handle(&mut deps, minter, mint_msg).unwrap();
+        handle(&mut deps, minter.clone(), mint_msg).unwrap();
 
-        // random cannot transfer
-        let random = mock_env("random", &[]);
-        let transfer_msg = HandleMsg::TransferNft {
-            recipient: "random".into(),
-            token_id: token_id.clone(),
+        // rank and serial are added by the contract and cannot be set at mint
+        let mint_msg = HandleMsg::Mint {
+            owner: "medusa".into(),
+            rank: "G".into(),
+            metadata: Some(Metadata {
+                attributes: vec![Trait { trait_type: "Serial".into(), value: "1".into() }],
+                ..Metadata::default()
+            }),
         };
+        handle(&mut deps, minter, mint_msg).unwrap_err();
 
-        let err = handle(&mut deps, random, transfer_msg.clone()).unwrap_err();
-
-        match err {
-            StdError::Unauthorized { .. } => {}
-            e => panic!("unexpected error: {}", e),
-        }
-
-        // owner can transfer
-        let random = mock_env("venus", &[]);
-        let transfer_msg = HandleMsg::TransferNft {
-            recipient: "random".into(),
-            token_id: token_id.clone(),
+        // only the minter can set the base uri
+        let update_msg = HandleMsg::UpdateBaseUri {
+            base_uri: Some("ipfs://meta/".into()),
         };
+        handle(&mut deps, mock_env("medusa", &[]), update_msg.clone()).unwrap_err();
+        handle(&mut deps, mock_env(MINTER, &[]), update_msg).unwrap();
 
-        let res = handle(&mut deps, random, transfer_msg.clone()).unwrap();
-
+        let info = query_nft_info(&deps, "LBJG1".into()).unwrap();
         assert_eq!(
-            res,
-            HandleResponse {
-                messages: vec![],
-                log: vec![
-                    log("action", "transfer_nft"),
-                    log("sender", "venus"),
-                    log("recipient", "random"),
-                    log("token_id", token_id.clone()),
+            info,
+            NftInfoResponse {
+                name: "Lebron Token Gold #1".into(),
+                description: "Rookie season".into(),
+                image: Some("ipfs://image/lbj.png".into()),
+                token_uri: Some("ipfs://meta/LBJG1".into()),
+                attributes: vec![
+                    Trait { trait_type: "Rank".into(), value: "Gold".into() },
+                    Trait { trait_type: "Serial".into(), value: "1".into() },
+                    season,
+                    jersey,
                 ],
-                data: None,
+                rank: "G".into(),
+                stats: TokenStats { level: 1, ..Default::default() },
             }
         );
+        assert_eq!(info, query_all_nft_info(&deps, "LBJG1".into()).unwrap().info);
     }
 
     #[test]
-    fn sending_nft() {
+    fn enumerating_tokens() {
         let mut deps = mock_dependencies(20, &[]);
         setup_contract(&mut deps);
 
-        // Mint a token
-        let token_id = "melt".to_string();
-        //let name = "Melting power".to_string();
-        //let description = "Allows the owner to melt anyone looking at him or her".to_string();
+        let minter = mock_env(MINTER, &[]);
+        for (owner, rank) in &[("medusa", "B"), ("medusa", "G"), ("medusa", "B"), ("hercules", "G")] {
+            let mint_msg = HandleMsg::Mint {
+                owner: (*owner).into(),
+                rank: (*rank).into(),
+                metadata: None,
+            };
+            handle(&mut deps, minter.clone(), mint_msg).unwrap();
+        }
 
-        let mint_msg = HandleMsg::Mint {
-            //token_id: token_id.clone(),
-            owner: "venus".into(),
-            // name: name.clone(),
-            // description: Some(description.clone()),
-            // image: None,
-            rank: "base".into()
+        let tokens = query_tokens(&deps, "medusa".into(), None, None, None).unwrap();
+        assert_eq!(vec!["LBJB1", "LBJB2", "LBJG1"], tokens.tokens);
+        let tokens = query_tokens(&deps, "medusa".into(), Some("G".into()), None, None).unwrap();
+        assert_eq!(vec!["LBJG1"], tokens.tokens);
+        let tokens = query_tokens(&deps, "medusa".into(), Some("B".into()), None, Some(1)).unwrap();
+        assert_eq!(vec!["LBJB1"], tokens.tokens);
+        let tokens =
+            query_tokens(&deps, "medusa".into(), Some("B".into()), Some("LBJB1".into()), None).unwrap();
+        assert_eq!(vec!["LBJB2"], tokens.tokens);
+
+        // the index follows transfers and burns
+        let transfer_msg = HandleMsg::TransferNft {
+            recipient: "hercules".into(),
+            token_id: "LBJB1".into(),
         };
+        handle(&mut deps, mock_env("medusa", &[]), transfer_msg).unwrap();
+        let burn_msg = HandleMsg::Burn {
+            token_id: "LBJG1".into(),
+        };
+        handle(&mut deps, mock_env("medusa", &[]), burn_msg).unwrap();
+
+        let tokens = query_tokens(&deps, "medusa".into(), None, None, None).unwrap();
+        assert_eq!(vec!["LBJB2"], tokens.tokens);
+        let tokens = query_tokens(&deps, "hercules".into(), None, None, None).unwrap();
+        assert_eq!(vec!["LBJB1", "LBJG2"], tokens.tokens);
+        let tokens = query_tokens(&deps, "hercules".into(), Some("B".into()), None, None).unwrap();
+        assert_eq!(vec!["LBJB1"], tokens.tokens);
+        let tokens = query_tokens(&deps, "medusa".into(), Some("G".into()), None, None).unwrap();
+        assert!(tokens.tokens.is_empty());
+        let tokens = query_tokens(&deps, "nobody".into(), None, None, None).unwrap();
+        assert!(tokens.tokens.is_empty());
+
+        let tokens = query_all_tokens(&deps, None, None).unwrap();
+        assert_eq!(vec!["LBJB1", "LBJB2", "LBJG2"], tokens.tokens);
+        let tokens = query_all_tokens(&deps, Some("LBJB2".into()), Some(1)).unwrap();
+        assert_eq!(vec!["LBJG2"], tokens.tokens);
+    }
 
-        let minter = mock_env(MINTER, &[]);
-        handle(&mut deps, minter, mint_msg).unwrap();
+    #[test]
+    fn batch_operations() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
 
-        // random cannot send
-        let inner_msg = WasmMsg::Execute {
-            contract_addr: "another_contract".into(),
-            msg: to_binary("You now have the melting power").unwrap(),
-            send: vec![],
+        let mint = |owner: &str, rank: &str| MintMsg {
+            owner: owner.into(),
+            rank: rank.into(),
+            metadata: None,
         };
-        let msg: CosmosMsg = CosmosMsg::Wasm(inner_msg);
-
-        let send_msg = HandleMsg::SendNft {
-            contract: "another_contract".into(),
-            token_id: token_id.clone(),
-            msg: Some(to_binary(&msg).unwrap()),
+        let batch_mint = HandleMsg::BatchMint {
+            mints: vec![mint("medusa", "B"), mint("medusa", "G"), mint("hercules", "B")],
         };
 
-        let random = mock_env("random", &[]);
-        let err = handle(&mut deps, random, send_msg.clone()).unwrap_err();
-        match err {
+        // random cannot mint
+        match handle(&mut deps, mock_env("random", &[]), batch_mint.clone()).unwrap_err() {
             StdError::Unauthorized { .. } => {}
             e => panic!("unexpected error: {}", e),
         }
 
-        // but owner can
-        let random = mock_env("venus", &[]);
-        let res = handle(&mut deps, random, send_msg).unwrap();
+        // one set of events for the whole batch
+        let res = handle(&mut deps, mock_env(MINTER, &[]), batch_mint).unwrap();
         assert_eq!(
-            res,
-            HandleResponse {
-                messages: vec![msg],
-                log: vec![
-                    log("action", "send_nft"),
-                    log("sender", "venus"),
-                    log("recipient", "another_contract"),
-                    log("token_id", token_id),
-                ],
-                data: None,
+            res.log,
+            vec![
+                log("action", "batch_mint"),
+                log("minter", MINTER),
+                log("token_ids", "LBJB1,LBJG1,LBJB2"),
+            ]
+        );
+        assert_eq!(
+            "hercules",
+            query_owner_of(&deps, "LBJB2".into()).unwrap().owner.as_str()
+        );
+
+        // the caps apply to every token of the batch
+        let over_cap = HandleMsg::BatchMint {
+            mints: vec![mint("medusa", "B")],
+        };
+        handle(&mut deps, mock_env(MINTER, &[]), over_cap).unwrap_err();
+
+        // every token must be transferable by the sender
+        let transfer_msg = HandleMsg::BatchTransferNft {
+            recipient: "venus".into(),
+            token_ids: vec!["LBJB2".into(), "LBJB1".into()],
+        };
+        match handle(&mut deps, mock_env("medusa", &[]), transfer_msg).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let transfer_msg = HandleMsg::BatchTransferNft {
+            recipient: "venus".into(),
+            token_ids: vec!["LBJB1".into(), "LBJG1".into()],
+        };
+        let res = handle(&mut deps, mock_env("medusa", &[]), transfer_msg).unwrap();
+        assert_eq!(log("token_ids", "LBJB1,LBJG1"), res.log[3]);
+        let tokens = query_tokens(&deps, "venus".into(), None, None, None).unwrap();
+        assert_eq!(vec!["LBJB1", "LBJG1"], tokens.tokens);
+
+        // the action is triggered once for the whole batch
+        let msg = to_binary("You now have the melting power").unwrap();
+        let send_msg = HandleMsg::BatchSendNft {
+            contract: "another_contract".into(),
+            token_ids: vec!["LBJB1".into(), "LBJG1".into()],
+            msg: Some(msg.clone()),
+        };
+        let res = handle(&mut deps, mock_env("venus", &[]), send_msg).unwrap();
+        let receive = |token_id: &str| {
+            Cw721ReceiveMsg {
+                sender: "venus".into(),
+                token_id: token_id.into(),
+                msg: Some(msg.clone()),
             }
-        );
+            .into_cosmos_msg("another_contract".into())
+            .unwrap()
+        };
+        assert_eq!(vec![receive("LBJB1"), receive("LBJG1")], res.messages);
+        let tokens = query_tokens(&deps, "another_contract".into(), None, None, None).unwrap();
+        assert_eq!(vec!["LBJB1", "LBJG1"], tokens.tokens);
     }
 
     #[test]
-    fn approving_revoking() {
+    fn per_rank_minters() {
         let mut deps = mock_dependencies(20, &[]);
         setup_contract(&mut deps);
 
-        // Mint a token
-        let token_id = "grow".to_string();
-        //let name = "Growing power".to_string();
-        //let description = "Allows the owner to grow anything".to_string();
+        let mint = |rank: &str| HandleMsg::Mint {
+            owner: "medusa".into(),
+            rank: rank.into(),
+            metadata: None,
+        };
+        let promo = mock_env("promo", &[]);
+        match handle(&mut deps, promo.clone(), mint("S")).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
 
-        let mint_msg = HandleMsg::Mint {
-            //token_id: token_id.clone(),
-            owner: "demeter".into(),
-            // name: name.clone(),
-            // description: Some(description.clone()),
-            // image: None,
-            rank: "base".into()
+        // only the minter manages the minter set, with known ranks
+        let add_promo = HandleMsg::AddMinter {
+            minter: "promo".into(),
+            ranks: Some(vec!["S".into()]),
+            quota: Some(1),
         };
+        handle(&mut deps, promo.clone(), add_promo.clone()).unwrap_err();
+        let add_unknown = HandleMsg::AddMinter {
+            minter: "promo".into(),
+            ranks: Some(vec!["P".into()]),
+            quota: None,
+        };
+        handle(&mut deps, mock_env(MINTER, &[]), add_unknown).unwrap_err();
+        handle(&mut deps, mock_env(MINTER, &[]), add_promo).unwrap();
 
-        let minter = mock_env(MINTER, &[]);
-        handle(&mut deps, minter, mint_msg).unwrap();
+        // promo can mint its rank until its quota is used up
+        match handle(&mut deps, promo.clone(), mint("B")).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Not allowed to mint rank B"),
+            e => panic!("unexpected error: {}", e),
+        }
+        handle(&mut deps, promo.clone(), mint("S")).unwrap();
+        match handle(&mut deps, promo.clone(), mint("S")).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Minting quota is used up"),
+            e => panic!("unexpected error: {}", e),
+        }
 
-        // Give random transferring power
-        let approve_msg = HandleMsg::Approve {
-            spender: "random".into(),
-            token_id: token_id.clone(),
-            expires: None,
+        // raising the quota keeps the count of tokens minted so far
+        let raise = HandleMsg::AddMinter {
+            minter: "promo".into(),
+            ranks: Some(vec!["S".into()]),
+            quota: Some(2),
         };
-        let owner = mock_env("demeter", &[]);
-        let res = handle(&mut deps, owner, approve_msg).unwrap();
+        handle(&mut deps, mock_env(MINTER, &[]), raise).unwrap();
         assert_eq!(
-            res,
-            HandleResponse {
-                messages: vec![],
-                log: vec![
-                    log("action", "approve"),
-                    log("sender", "demeter"),
-                    log("spender", "random"),
-                    log("token_id", token_id.clone()),
-                ],
-                data: None,
-            }
+            query_minters(&deps).unwrap().minters,
+            vec![MinterItem {
+                minter: "promo".into(),
+                ranks: Some(vec!["S".into()]),
+                quota: Some(2),
+                minted: 1,
+            }]
         );
+        let batch = HandleMsg::BatchMint {
+            mints: vec![MintMsg { owner: "medusa".into(), rank: "S".into(), metadata: None }],
+        };
+        handle(&mut deps, promo.clone(), batch).unwrap();
+        handle(&mut deps, promo.clone(), mint("S")).unwrap_err();
 
-        // random can now transfer
-        let random = mock_env("random", &[]);
-        let transfer_msg = HandleMsg::TransferNft {
-            recipient: "person".into(),
+        let remove = HandleMsg::RemoveMinter {
+            minter: "promo".into(),
+        };
+        handle(&mut deps, mock_env(MINTER, &[]), remove.clone()).unwrap();
+        handle(&mut deps, mock_env(MINTER, &[]), remove).unwrap_err();
+        assert!(query_minters(&deps).unwrap().minters.is_empty());
+    }
+
+    #[test]
+    fn locking_tokens() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let mint = HandleMsg::Mint {
+            owner: "medusa".into(),
+            rank: "B".into(),
+            metadata: None,
+        };
+        handle(&mut deps, mock_env(MINTER, &[]), mint).unwrap();
+        let token_id = format!("{}B1", SYMBOL);
+
+        // only a locker set by the minter can lock
+        let lock = HandleMsg::Lock {
             token_id: token_id.clone(),
+            until: Expiration::AtHeight(12_400),
         };
-        handle(&mut deps, random, transfer_msg).unwrap();
+        handle(&mut deps, mock_env("fantasy", &[]), lock.clone()).unwrap_err();
+        let set_locker = HandleMsg::UpdateLocker {
+            locker: Some("fantasy".into()),
+        };
+        handle(&mut deps, mock_env("fantasy", &[]), set_locker.clone()).unwrap_err();
+        handle(&mut deps, mock_env(MINTER, &[]), set_locker).unwrap();
 
-        // Approvals are removed / cleared
-        let query_msg = QueryMsg::OwnerOf {
+        let expired = HandleMsg::Lock {
             token_id: token_id.clone(),
+            until: Expiration::AtHeight(12_345),
         };
-        let res: OwnerOfResponse = from_binary(&query(&deps, query_msg.clone()).unwrap()).unwrap();
-        assert_eq!(
-            res,
-            OwnerOfResponse {
-                owner: "person".into(),
-                approvals: vec![],
+        match handle(&mut deps, mock_env("fantasy", &[]), expired).unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Cannot set lock that is already expired")
             }
+            e => panic!("unexpected error: {}", e),
+        }
+        handle(&mut deps, mock_env("fantasy", &[]), lock.clone()).unwrap();
+        assert_eq!(
+            query_lock_info(&deps, token_id.clone()).unwrap().until,
+            Some(Expiration::AtHeight(12_400))
         );
 
-        // Approve, revoke, and check for empty, to test revoke
-        let approve_msg = HandleMsg::Approve {
-            spender: "random".into(),
+        // the owner can neither transfer, send nor burn a locked token
+        let transfer = HandleMsg::TransferNft {
+            recipient: "venus".into(),
             token_id: token_id.clone(),
-            expires: None,
         };
-        let owner = mock_env("person", &[]);
-        handle(&mut deps, owner.clone(), approve_msg).unwrap();
-
-        let revoke_msg = HandleMsg::Revoke {
-            spender: "random".into(),
+        match handle(&mut deps, mock_env("medusa", &[]), transfer.clone()).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(
+                msg,
+                format!("Token {} is locked until expiration height: 12400", token_id)
+            ),
+            e => panic!("unexpected error: {}", e),
+        }
+        let send = HandleMsg::SendNft {
+            contract: "another_contract".into(),
             token_id: token_id.clone(),
+            msg: None,
         };
-        handle(&mut deps, owner, revoke_msg).unwrap();
+        handle(&mut deps, mock_env("medusa", &[]), send).unwrap_err();
+        let burn = HandleMsg::Burn {
+            token_id: token_id.clone(),
+        };
+        handle(&mut deps, mock_env("medusa", &[]), burn.clone()).unwrap_err();
 
-        // Approvals are now removed / cleared
-        let res: OwnerOfResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
-        assert_eq!(
-            res,
-            OwnerOfResponse {
-                owner: "person".into(),
-                approvals: vec![],
-            }
-        );
+        // the lock lifts once expired
+        let mut later = mock_env("medusa", &[]);
+        later.block.height = 12_400;
+        handle(&mut deps, later, transfer).unwrap();
+
+        // or when the locker unlocks it
+        handle(&mut deps, mock_env("fantasy", &[]), lock).unwrap();
+        let unlock = HandleMsg::Unlock {
+            token_id: token_id.clone(),
+        };
+        handle(&mut deps, mock_env("venus", &[]), unlock.clone()).unwrap_err();
+        handle(&mut deps, mock_env("fantasy", &[]), unlock.clone()).unwrap();
+        handle(&mut deps, mock_env("fantasy", &[]), unlock).unwrap_err();
+        assert_eq!(query_lock_info(&deps, token_id.clone()).unwrap().until, None);
+        handle(&mut deps, mock_env("venus", &[]), burn).unwrap();
     }
 
     #[test]
-    fn approving_all_revoking_all() {
+    fn soulbound_ranks() {
         let mut deps = mock_dependencies(20, &[]);
-        setup_contract(&mut deps);
-
-        // Mint a couple tokens (from the same owner)
-        let token_id1 = "grow1".to_string();
-        //let name1 = "Growing power".to_string();
-        //let description1 = "Allows the owner the power to grow anything".to_string();
-        let token_id2 = "grow2".to_string();
-        //let name2 = "More growing power".to_string();
-        //let description2 = "Allows the owner the power to grow anything even faster".to_string();
-
-        let mint_msg1 = HandleMsg::Mint {
-            //token_id: token_id1.clone(),
-            owner: "demeter".into(),
-            // name: name1.clone(),
-            // description: Some(description1.clone()),
-            // image: None,
-            rank: "base".into()
+        let mut ranks = rank_table(BASE_CAP, SILVER_CAP, GOLD_CAP);
+        ranks[2].transferable = false;
+        let msg = InitMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            minter: MINTER.into(),
+            base_uri: None,
+            level_thresholds: vec![],
+            ranks,
         };
+        init(&mut deps, mock_env("creator", &[]), msg).unwrap();
 
-        let minter = mock_env(MINTER, &[]);
-        handle(&mut deps, minter.clone(), mint_msg1).unwrap();
-
-        let mint_msg2 = HandleMsg::Mint {
-            //token_id: token_id2.clone(),
-            owner: "demeter".into(),
-            // name: name2.clone(),
-            // description: Some(description2.clone()),
-            // image: None,
-            rank: "base".into()
+        let mint = HandleMsg::Mint {
+            owner: "medusa".into(),
+            rank: "G".into(),
+            metadata: None,
         };
+        handle(&mut deps, mock_env(MINTER, &[]), mint).unwrap();
+        let token_id = format!("{}G1", SYMBOL);
 
-        handle(&mut deps, minter, mint_msg2).unwrap();
-
-        // paginate the token_ids
-        let tokens = query_all_base_tokens(&deps, None, Some(1)).unwrap();
-        assert_eq!(1, tokens.tokens.len());
-        assert_eq!(vec![token_id1.clone()], tokens.tokens);
-        let tokens = query_all_base_tokens(&deps, Some(token_id1.clone()), Some(3)).unwrap();
-        assert_eq!(1, tokens.tokens.len());
-        assert_eq!(vec![token_id2.clone()], tokens.tokens);
+        let transfer = HandleMsg::TransferNft {
+            recipient: "venus".into(),
+            token_id: token_id.clone(),
+        };
+        match handle(&mut deps, mock_env("medusa", &[]), transfer).unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg, format!("Token {} cannot be transferred", token_id))
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+        let send = HandleMsg::SendNft {
+            contract: "another_contract".into(),
+            token_id: token_id.clone(),
+            msg: None,
+        };
+        handle(&mut deps, mock_env("medusa", &[]), send).unwrap_err();
+        let approve = HandleMsg::Approve {
+            spender: "venus".into(),
+            token_id: token_id.clone(),
+            expires: None,
+        };
+        handle(&mut deps, mock_env("medusa", &[]), approve).unwrap_err();
 
-        // demeter gives random full (operator) power over her tokens
-        let approve_all_msg = HandleMsg::ApproveAll {
-            operator: "random".into(),
+        // an operator cannot move or burn it
+        let approve_all = HandleMsg::ApproveAll {
+            operator: "venus".into(),
             expires: None,
         };
-        let owner = mock_env("demeter", &[]);
-        let res = handle(&mut deps, owner, approve_all_msg).unwrap();
-        assert_eq!(
-            res,
-            HandleResponse {
-                messages: vec![],
-                log: vec![
-                    log("action", "approve_all"),
-                    log("sender", "demeter"),
-                    log("operator", "random"),
-                ],
-                data: None,
+        handle(&mut deps, mock_env("medusa", &[]), approve_all).unwrap();
+        let transfer = HandleMsg::TransferNft {
+            recipient: "venus".into(),
+            token_id: token_id.clone(),
+        };
+        handle(&mut deps, mock_env("venus", &[]), transfer).unwrap_err();
+        let burn = HandleMsg::Burn {
+            token_id: token_id.clone(),
+        };
+        match handle(&mut deps, mock_env("venus", &[]), burn).unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg, format!("Token {} can only be burned by its owner", token_id))
             }
-        );
+            e => panic!("unexpected error: {}", e),
+        }
 
-        // random can now transfer
-        let random = mock_env("random", &[]);
-        let transfer_msg = HandleMsg::TransferNft {
-            recipient: "person".into(),
-            token_id: token_id1.clone(),
+        // the owner can still burn it
+        let burn = HandleMsg::Burn {
+            token_id: token_id.clone(),
         };
-        handle(&mut deps, random.clone(), transfer_msg).unwrap();
+        handle(&mut deps, mock_env("medusa", &[]), burn).unwrap();
+        query_owner_of(&deps, token_id).unwrap_err();
+    }
 
-        // random can now send
-        let inner_msg = WasmMsg::Execute {
-            contract_addr: "another_contract".into(),
-            msg: to_binary("You now also have the growing power").unwrap(),
-            send: vec![],
+    #[test]
+    fn recording_performances() {
+        let mut deps = mock_dependencies(20, &[]);
+        let init_with = |level_thresholds: Vec<u64>| InitMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            minter: MINTER.into(),
+            base_uri: None,
+            level_thresholds,
+            ranks: rank_table(BASE_CAP, SILVER_CAP, GOLD_CAP),
         };
-        let msg: CosmosMsg = CosmosMsg::Wasm(inner_msg);
+        init(&mut deps, mock_env("creator", &[]), init_with(vec![100, 100])).unwrap_err();
+        init(&mut deps, mock_env("creator", &[]), init_with(vec![100, 250])).unwrap();
 
-        let send_msg = HandleMsg::SendNft {
-            contract: "another_contract".into(),
-            token_id: token_id2.clone(),
-            msg: Some(to_binary(&msg).unwrap()),
+        let mint = HandleMsg::Mint {
+            owner: "medusa".into(),
+            rank: "B".into(),
+            metadata: None,
         };
-        handle(&mut deps, random, send_msg).unwrap();
+        handle(&mut deps, mock_env(MINTER, &[]), mint).unwrap();
+        let token_id = format!("{}B1", SYMBOL);
 
-        // Approve_all, revoke_all, and check for empty, to test revoke_all
-        let approve_all_msg = HandleMsg::ApproveAll {
-            operator: "operator".into(),
-            expires: None,
+        // only the game set by the minter records performances
+        let record = |xp: u64, score: u64| HandleMsg::RecordPerformance {
+            token_id: token_id.clone(),
+            xp,
+            score,
         };
-        // person is now the owner of the tokens
-        let owner = mock_env("person", &[]);
-        handle(&mut deps, owner.clone(), approve_all_msg).unwrap();
+        handle(&mut deps, mock_env("fantasy", &[]), record(80, 12)).unwrap_err();
+        let set_game = HandleMsg::UpdateGame {
+            game: Some("fantasy".into()),
+        };
+        handle(&mut deps, mock_env("medusa", &[]), set_game.clone()).unwrap_err();
+        handle(&mut deps, mock_env(MINTER, &[]), set_game).unwrap();
 
-        let res = query_all_approvals(&deps, "person".into(), None, None).unwrap();
+        let res = handle(&mut deps, mock_env("fantasy", &[]), record(80, 12)).unwrap();
+        assert_eq!(5, res.log.len());
+        let res = handle(&mut deps, mock_env("fantasy", &[]), record(30, 9)).unwrap();
+        assert_eq!(log("level_up", 2), res.log[5]);
+
+        let info = query_nft_info(&deps, token_id.clone()).unwrap();
         assert_eq!(
-            res,
-            ApprovedForAllResponse {
-                operators: vec![cw721::Approval {
-                    spender: "operator".into(),
-                    expires: Expiration::Never {}
-                }]
+            info.stats,
+            TokenStats {
+                xp: 110,
+                games_played: 2,
+                best_score: 12,
+                level: 2,
             }
         );
 
-        // second approval
-        let buddy_expires = Expiration::AtHeight(1234567);
-        let approve_all_msg = HandleMsg::ApproveAll {
-            operator: "buddy".into(),
-            expires: Some(buddy_expires),
+        // stats stay with the token when it changes hands
+        let transfer = HandleMsg::TransferNft {
+            recipient: "venus".into(),
+            token_id: token_id.clone(),
         };
-        let owner = mock_env("person", &[]);
-        handle(&mut deps, owner.clone(), approve_all_msg).unwrap();
+        handle(&mut deps, mock_env("medusa", &[]), transfer).unwrap();
+        handle(&mut deps, mock_env("fantasy", &[]), record(150, 31)).unwrap();
+        let info = query_nft_info(&deps, token_id.clone()).unwrap();
+        assert_eq!(3, info.stats.level);
+        assert_eq!(31, info.stats.best_score);
 
-        // and paginate queries
-        let res = query_all_approvals(&deps, "person".into(), None, Some(1)).unwrap();
-        assert_eq!(
-            res,
-            ApprovedForAllResponse {
-                operators: vec![cw721::Approval {
-                    spender: "buddy".into(),
-                    expires: buddy_expires,
-                }]
-            }
-        );
-        let res =
-            query_all_approvals(&deps, "person".into(), Some("buddy".into()), Some(2)).unwrap();
-        assert_eq!(
-            res,
-            ApprovedForAllResponse {
-                operators: vec![cw721::Approval {
-                    spender: "operator".into(),
-                    expires: Expiration::Never {}
-                }]
+        // xp that would overflow is rejected and the stats are left as they were
+        match handle(&mut deps, mock_env("fantasy", &[]), record(u64::MAX, 40)).unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg, format!("Xp of token {} overflows", token_id))
             }
-        );
+            e => panic!("unexpected error: {}", e),
+        }
+        let info = query_nft_info(&deps, token_id).unwrap();
+        assert_eq!(260, info.stats.xp);
+        assert_eq!(31, info.stats.best_score);
+    }
 
-        let revoke_all_msg = HandleMsg::RevokeAll {
-            operator: "operator".into(),
-        };
-        handle(&mut deps, owner, revoke_all_msg).unwrap();
+    #[test]
+    fn updating_caps() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
 
-        // Approvals are removed / cleared without affecting others
-        let res = query_all_approvals(&deps, "person".into(), None, None).unwrap();
-        assert_eq!(
-            res,
-            ApprovedForAllResponse {
-                operators: vec![cw721::Approval {
-                    spender: "buddy".into(),
-                    expires: buddy_expires,
-                }]
+        let minter = mock_env(MINTER, &[]);
+        let mint = HandleMsg::Mint {
+            owner: "medusa".into(),
+            rank: "G".into(),
+            metadata: None,
+        };
+        handle(&mut deps, minter.clone(), mint.clone()).unwrap();
+        handle(&mut deps, minter.clone(), mint.clone()).unwrap();
+
+        let update = |gold_cap: u128| HandleMsg::UpdateCaps {
+            caps: vec![
+                CapUpdate { rank: "S".into(), cap: Uint128(7) },
+                CapUpdate { rank: "G".into(), cap: Uint128(gold_cap) },
+            ],
+        };
+        handle(&mut deps, mock_env("medusa", &[]), update(2)).unwrap_err();
+        match handle(&mut deps, minter.clone(), update(1)).unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Cap of rank G cannot be below its supply of 2")
             }
-        );
+            e => panic!("unexpected error: {}", e),
+        }
+        // nothing is saved when one of the updates is rejected
+        assert_eq!(SILVER_CAP, query_rank_supply(&deps, "S".into()).unwrap().cap);
+
+        // the cap can be lowered to the current supply, which stops minting
+        handle(&mut deps, minter.clone(), update(2)).unwrap();
+        assert_eq!(Uint128(7), query_rank_supply(&deps, "S".into()).unwrap().cap);
+        assert!(!query_mintable(&deps, "G".into()).unwrap());
+        handle(&mut deps, minter.clone(), mint.clone()).unwrap_err();
+
+        handle(&mut deps, minter.clone(), update(3)).unwrap();
+        assert!(query_mintable(&deps, "G".into()).unwrap());
+
+        // once finalized, caps cannot change anymore
+        let finalize = HandleMsg::FinalizeSupply {};
+        handle(&mut deps, mock_env("medusa", &[]), finalize.clone()).unwrap_err();
+        handle(&mut deps, minter.clone(), finalize).unwrap();
+        match handle(&mut deps, minter.clone(), update(4)).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Supply is finalized"),
+            e => panic!("unexpected error: {}", e),
+        }
+        handle(&mut deps, minter, mint).unwrap();
+        assert!(!query_mintable(&deps, "G".into()).unwrap());
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,7 @@
 use cosmwasm_std::{Binary, HumanAddr, Uint128};
 use cw721::Expiration;
+
+use crate::state::{Metadata, RankInfo};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -15,12 +17,14 @@ pub struct InitMsg {
     /// or contract. You will likely replace this with custom logic in custom NFTs
     pub minter: HumanAddr,
 
-    // Maximum number of base tokens
-    pub base_cap: Uint128,
-    // Maximum number of silver tokens
-    pub silver_cap: Uint128,
-    // Maximum number of gold tokens
-    pub gold_cap: Uint128,
+    /// The ranks tokens can be minted at, with their caps and upgrade recipes
+    pub ranks: Vec<RankInfo>,
+    /// Collection-level URI that token_ids are appended to, e.g. "ipfs://<cid>/"
+    pub base_uri: Option<String>,
+    /// XP needed to reach levels 2, 3 and so on, in increasing order.
+    /// Tokens start at level 1
+    #[serde(default)]
+    pub level_thresholds: Vec<u64>,
 }
 
 /// This is like Cw721HandleMsg but we add a Mint command for an owner
@@ -35,12 +39,23 @@ pub enum HandleMsg {
         token_id: String,
     },
     /// Send is a base message to transfer a token to a contract and trigger an action
-    /// on the receiving contract.
+    /// on the receiving contract, which gets a ReceiveNft with the msg.
     SendNft {
         contract: HumanAddr,
         token_id: String,
         msg: Option<Binary>,
     },
+    /// Transfers several tokens to the same recipient at once
+    BatchTransferNft {
+        recipient: HumanAddr,
+        token_ids: Vec<String>,
+    },
+    /// Sends several tokens to the same contract at once, with one ReceiveNft per token
+    BatchSendNft {
+        contract: HumanAddr,
+        token_ids: Vec<String>,
+        msg: Option<Binary>,
+    },
     /// Allows operator to transfer / send the token from the owner's account.
     /// If expiration is set, then this allowance has a time/height limit
     Approve {
@@ -62,22 +77,10 @@ pub enum HandleMsg {
     /// Remove previously granted ApproveAll permission
     RevokeAll { operator: HumanAddr },
 
-    /// Converts Base NFTs to Silver Rank
-    BaseToSilver {
-        /// NFTs to Burn
-        base_1: String,
-        base_2: String,
-        base_3: String,
-    },
-
-    /// Converts Silver NFTs to Gold Rank
-    SilverToGold {
-        /// NFTs to Burn
-        silver_1: String,
-        silver_2: String,
-        silver_3: String,
-        silver_4: String,
-        silver_5: String,
+    /// Burns tokens of one rank for a token of the rank its upgrade recipe gives
+    Upgrade {
+        /// NFTs to Burn, as many as the recipe takes
+        token_ids: Vec<String>,
     },
 
     /// Mint a new NFT, can only be called by the contract minter
@@ -86,13 +89,83 @@ pub enum HandleMsg {
         owner: HumanAddr,
         /// Describes the rank of the NFT 
         rank: String,
+        /// Name, description, image and attributes of the NFT
+        metadata: Option<Metadata>,
     },
+
+    /// Mint several NFTs at once, can only be called by the contract minter
+    BatchMint { mints: Vec<MintMsg> },
+
+    /// Change the collection's base URI, can only be called by the minter
+    UpdateBaseUri { base_uri: Option<String> },
     
     /// Change the minter for the token, can only be called by the current minter
     UpdateMinter {
         /// Address of the new minter
         minter: HumanAddr,
-    }
+    },
+
+    /// Allows another address to mint, optionally limited to some ranks and a number of
+    /// tokens. Updates the limits of an existing minter. Can only be called by the minter
+    AddMinter {
+        minter: HumanAddr,
+        ranks: Option<Vec<String>>,
+        quota: Option<u64>,
+    },
+    /// Revokes an address added with AddMinter, can only be called by the minter
+    RemoveMinter { minter: HumanAddr },
+
+    /// Sets or removes the contract allowed to lock tokens, can only be called by the minter
+    UpdateLocker { locker: Option<HumanAddr> },
+    /// Prevents a token from being transferred, sent or burned until `until` expires,
+    /// e.g. while it is in a contest lineup. Can only be called by the locker
+    Lock { token_id: String, until: Expiration },
+    /// Lifts a lock before it expires, can only be called by the locker
+    Unlock { token_id: String },
+
+    /// Sets or removes the contract allowed to record performances, can only be called
+    /// by the minter
+    UpdateGame { game: Option<HumanAddr> },
+    /// Adds the xp a token earned in a game to its stats and keeps its best score,
+    /// can only be called by the game
+    RecordPerformance {
+        token_id: String,
+        xp: u64,
+        /// The token's score in this game, which can be unrelated to the xp it earned
+        score: u64,
+    },
+
+    /// Changes the caps of some ranks, which can never go below their current supply.
+    /// Can only be called by the minter, until the supply is finalized
+    UpdateCaps { caps: Vec<CapUpdate> },
+    /// Permanently prevents any further cap changes, can only be called by the minter
+    FinalizeSupply {},
+
+    /// Permanently stops minting when the athlete retires, can only be called by the minter
+    Freeze {},
+
+    /// Destroys a token, freeing its slot under the rank's cap.
+    /// Can be called by anyone allowed to transfer the token.
+    /// Soulbound tokens can only be burned by their owner
+    Burn { token_id: String },
+}
+
+/// One NFT of a BatchMint
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MintMsg {
+    /// The owner of the newly minter NFT
+    pub owner: HumanAddr,
+    /// Describes the rank of the NFT
+    pub rank: String,
+    /// Name, description, image and attributes of the NFT
+    pub metadata: Option<Metadata>,
+}
+
+/// New cap of one rank in UpdateCaps
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct CapUpdate {
+    pub rank: String,
+    pub cap: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -111,26 +184,33 @@ pub enum QueryMsg {
         limit: Option<u32>,
     },
 
-    /// Total number of base tokens issued
-    BaseTokens {},
+    /// The rank table set at init
+    /// Return type: RanksResponse
+    Ranks {},
+    /// Number of tokens of the given rank in supply, burned and its cap
+    /// Return type: RankSupplyResponse
+    RankSupply { rank: String },
     /// With Enumerable extension.
-    /// Requires pagination. Lists all token_ids controlled by the contract.
+    /// Returns all tokens owned by the given address, optionally filtered by rank.
     /// Return type: TokensResponse.
-    AllBaseTokens {
+    Tokens {
+        owner: HumanAddr,
+        rank: Option<String>,
         start_after: Option<String>,
         limit: Option<u32>,
     },
-
-    /// Total number of silver tokens issued
-    SilverTokens {},
-    AllSilverTokens {
+    /// With Enumerable extension.
+    /// Requires pagination. Lists all token_ids controlled by the contract.
+    /// Return type: TokensResponse.
+    AllTokens {
         start_after: Option<String>,
         limit: Option<u32>,
     },
-
-    /// Total number of gold tokens issued
-    GoldTokens {},
-    AllGoldTokens {
+    /// With Enumerable extension.
+    /// Requires pagination. Lists the token_ids of the given rank.
+    /// Return type: TokensResponse.
+    TokensByRank {
+        rank: String,
         start_after: Option<String>,
         limit: Option<u32>,
     },
@@ -152,10 +232,23 @@ pub enum QueryMsg {
     },
     // Return the minter
     Minter {},
+    /// Minters added with AddMinter, with their limits
+    /// Return type: MintersResponse
+    Minters {},
     // Returns a boolean determining if the base token is mintable
     IsMintable {
         rank: String,
     },
+    // Returns a boolean determining if the contract is frozen
+    IsFrozen {},
+    // Returns a boolean determining if the caps can no longer be updated
+    IsFinalized {},
+    /// Total number of tokens of the given rank that were burned
+    /// Return type: NumTokensResponse
+    BurnedTokens { rank: String },
+    /// The lock on the given token, if any
+    /// Return type: LockInfoResponse
+    LockInfo { token_id: String },
 }
 
 /// Shows who can mint these tokens
@@ -163,3 +256,29 @@ pub enum QueryMsg {
 pub struct MinterResponse {
     pub minter: HumanAddr,
 }
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MinterItem {
+    pub minter: HumanAddr,
+    /// Ranks this minter can mint, any rank if None
+    pub ranks: Option<Vec<String>>,
+    /// Maximum number of tokens this minter can mint, unlimited if None
+    pub quota: Option<u64>,
+    pub minted: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MintersResponse {
+    pub minters: Vec<MinterItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RanksResponse {
+    pub ranks: Vec<RankInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct LockInfoResponse {
+    /// None if the token is not locked. The lock no longer applies once this is expired
+    pub until: Option<Expiration>,
+}
@@ -1,22 +1,32 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{CanonicalAddr, ReadonlyStorage, StdResult, Storage, Uint128};
+use cosmwasm_std::{CanonicalAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128};
 use cosmwasm_storage::{
     bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
     Singleton,
 };
-use cw721::{Expiration};
+use cw721::{Expiration, Trait};
 
 pub const CONFIG_KEY: &[u8] = b"config";
 pub const MINTER_KEY: &[u8] = b"minter";
 pub const CONTRACT_INFO_KEY: &[u8] = b"nft_info";
-pub const BASE_TOKENS_KEY: &[u8] = b"base_tokens";
-pub const SILVER_TOKENS_KEY: &[u8] = b"silver_tokens";
-pub const GOLD_TOKENS_KEY: &[u8] = b"gold_tokens";
+pub const FROZEN_KEY: &[u8] = b"frozen";
+pub const FINALIZED_KEY: &[u8] = b"finalized";
+pub const LOCKER_KEY: &[u8] = b"locker";
+pub const GAME_KEY: &[u8] = b"game";
+pub const LEVELS_KEY: &[u8] = b"levels";
 
 pub const TOKEN_PREFIX: &[u8] = b"tokens";
 pub const OPERATOR_PREFIX: &[u8] = b"operators";
+pub const RANK_PREFIX: &[u8] = b"ranks";
+pub const RANK_SUPPLY_PREFIX: &[u8] = b"rank_supply";
+pub const BURNED_PREFIX: &[u8] = b"burned";
+pub const RANK_TOKENS_PREFIX: &[u8] = b"rank_tokens";
+pub const OWNER_TOKENS_PREFIX: &[u8] = b"owner_tokens";
+pub const OWNER_RANK_TOKENS_PREFIX: &[u8] = b"owner_rank_tokens";
+pub const MINTERS_PREFIX: &[u8] = b"minters";
+pub const LOCKS_PREFIX: &[u8] = b"locks";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TokenInfo {
@@ -26,6 +36,35 @@ pub struct TokenInfo {
     pub approvals: Vec<Approval>,
     /// Describes the rank of the NFT 
     pub rank: String,
+    /// Position of the token among all tokens ever minted at its rank, starting at 1
+    #[serde(default)]
+    pub serial: u64,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub stats: Stats,
+}
+
+/// Results recorded by the game contract. The level is derived from the xp
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct Stats {
+    pub xp: u64,
+    pub games_played: u64,
+    pub best_score: u64,
+}
+
+/// Optional token metadata set at mint. Rank and serial are always added to the
+/// attributes by the contract
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct Metadata {
+    /// Defaults to the collection name, rank name and serial, e.g. "LeBron James Gold #3"
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// URI of the token's image
+    pub image: Option<String>,
+    /// e.g. season and jersey number
+    #[serde(default)]
+    pub attributes: Vec<Trait>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -34,10 +73,47 @@ pub struct ContractInfo {
     pub name: String,
     /// symbol of the NFT contract
     pub symbol: String, // Becomes LB
-    /// cap is the maximum number of tokens that could be minted
-    pub base_cap: Uint128,
-    pub silver_cap: Uint128,
-    pub gold_cap: Uint128,
+    /// token_ids are appended to this to build each token's URI
+    #[serde(default)]
+    pub base_uri: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RankInfo {
+    /// Short id used in token ids and messages, e.g. "B"
+    pub id: String,
+    /// Display name, e.g. "Base"
+    pub name: String,
+    /// cap is the maximum number of tokens of this rank that could be minted
+    pub cap: Uint128,
+    /// How tokens of this rank upgrade to a higher one, if they can
+    pub upgrade: Option<UpgradeRecipe>,
+    /// Soulbound ranks, e.g. season trophies, cannot be transferred, sent or approved.
+    /// Only their owners can burn and upgrade them, operators cannot
+    #[serde(default = "default_transferable")]
+    pub transferable: bool,
+}
+
+fn default_transferable() -> bool {
+    true
+}
+
+/// `count` tokens of a rank can be burned for one token of rank `to`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UpgradeRecipe {
+    pub count: u32,
+    pub to: String,
+}
+
+/// A minter added by the main minter, limited to some ranks and a number of tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MinterInfo {
+    /// Ranks this minter can mint, any rank if None
+    pub ranks: Option<Vec<String>>,
+    /// Maximum number of tokens this minter can mint, unlimited if None
+    pub quota: Option<u64>,
+    /// Number of tokens minted so far
+    pub minted: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -66,60 +142,111 @@ pub fn mint_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, Canoni
     singleton_read(storage, MINTER_KEY)
 }
 
-// BASE TOKEN
-fn base_count<S: Storage>(storage: &mut S) -> Singleton<S, u64> {
-    singleton(storage, BASE_TOKENS_KEY)
+pub fn minters<S: Storage>(storage: &mut S) -> Bucket<S, MinterInfo> {
+    bucket(MINTERS_PREFIX, storage)
 }
 
-fn base_count_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, u64> {
-    singleton_read(storage, BASE_TOKENS_KEY)
+pub fn minters_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, MinterInfo> {
+    bucket_read(MINTERS_PREFIX, storage)
 }
 
-pub fn base_num_tokens<S: ReadonlyStorage>(storage: &S) -> StdResult<u64> {
-    Ok(base_count_read(storage).may_load()?.unwrap_or_default())
+/// Contract allowed to lock tokens, e.g. while they are entered in a contest
+pub fn locker<S: Storage>(storage: &mut S) -> Singleton<S, CanonicalAddr> {
+    singleton(storage, LOCKER_KEY)
 }
 
-pub fn increment_base_tokens<S: Storage>(storage: &mut S) -> StdResult<u64> {
-    let val = base_num_tokens(storage)? + 1;
-    base_count(storage).save(&val)?;
-    Ok(val)
+pub fn locker_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, CanonicalAddr> {
+    singleton_read(storage, LOCKER_KEY)
 }
 
-// SILVER TOKEN
-fn silver_count<S: Storage>(storage: &mut S) -> Singleton<S, u64> {
-    singleton(storage, SILVER_TOKENS_KEY)
+/// Contract allowed to record the performance of tokens, e.g. from contest results
+pub fn game<S: Storage>(storage: &mut S) -> Singleton<S, CanonicalAddr> {
+    singleton(storage, GAME_KEY)
 }
 
-fn silver_count_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, u64> {
-    singleton_read(storage, SILVER_TOKENS_KEY)
+pub fn game_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, CanonicalAddr> {
+    singleton_read(storage, GAME_KEY)
 }
 
-pub fn silver_num_tokens<S: ReadonlyStorage>(storage: &S) -> StdResult<u64> {
-    Ok(silver_count_read(storage).may_load()?.unwrap_or_default())
+/// XP needed to reach levels 2, 3 and so on
+pub fn level_thresholds<S: Storage>(storage: &mut S) -> Singleton<S, Vec<u64>> {
+    singleton(storage, LEVELS_KEY)
 }
 
-pub fn increment_silver_tokens<S: Storage>(storage: &mut S) -> StdResult<u64> {
-    let val = silver_num_tokens(storage)? + 1;
-    silver_count(storage).save(&val)?;
-    Ok(val)
+pub fn load_level_thresholds<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<u64>> {
+    Ok(singleton_read(storage, LEVELS_KEY).may_load()?.unwrap_or_default())
+}
+
+/// Locked tokens by token_id, with the expiration of their lock
+pub fn locks<S: Storage>(storage: &mut S) -> Bucket<S, Expiration> {
+    bucket(LOCKS_PREFIX, storage)
+}
+
+pub fn locks_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, Expiration> {
+    bucket_read(LOCKS_PREFIX, storage)
+}
+
+/// Set once the athlete retires, after which no more tokens can be minted
+pub fn frozen<S: Storage>(storage: &mut S) -> Singleton<S, bool> {
+    singleton(storage, FROZEN_KEY)
 }
 
-// GOLD TOKEN
-fn gold_count<S: Storage>(storage: &mut S) -> Singleton<S, u64> {
-    singleton(storage, GOLD_TOKENS_KEY)
+pub fn is_frozen<S: ReadonlyStorage>(storage: &S) -> StdResult<bool> {
+    Ok(singleton_read(storage, FROZEN_KEY).may_load()?.unwrap_or_default())
 }
 
-fn gold_count_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, u64> {
-    singleton_read(storage, GOLD_TOKENS_KEY)
+/// Set by FinalizeSupply, after which the rank caps can no longer change
+pub fn finalized<S: Storage>(storage: &mut S) -> Singleton<S, bool> {
+    singleton(storage, FINALIZED_KEY)
 }
 
-pub fn gold_num_tokens<S: ReadonlyStorage>(storage: &S) -> StdResult<u64> {
-    Ok(gold_count_read(storage).may_load()?.unwrap_or_default())
+pub fn is_finalized<S: ReadonlyStorage>(storage: &S) -> StdResult<bool> {
+    Ok(singleton_read(storage, FINALIZED_KEY).may_load()?.unwrap_or_default())
 }
 
-pub fn increment_gold_tokens<S: Storage>(storage: &mut S) -> StdResult<u64> {
-    let val = gold_num_tokens(storage)? + 1;
-    gold_count(storage).save(&val)?;
+pub fn ranks<S: Storage>(storage: &mut S) -> Bucket<S, RankInfo> {
+    bucket(RANK_PREFIX, storage)
+}
+
+pub fn ranks_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, RankInfo> {
+    bucket_read(RANK_PREFIX, storage)
+}
+
+pub fn load_rank<S: ReadonlyStorage>(storage: &S, rank: &str) -> StdResult<RankInfo> {
+    ranks_read(storage)
+        .may_load(rank.as_bytes())?
+        .ok_or_else(|| StdError::generic_err(format!("Unknown rank {}", rank)))
+}
+
+// TOKENS IN SUPPLY, by rank
+pub fn rank_num_tokens<S: ReadonlyStorage>(storage: &S, rank: &str) -> StdResult<u64> {
+    let supply: ReadonlyBucket<S, u64> = bucket_read(RANK_SUPPLY_PREFIX, storage);
+    Ok(supply.may_load(rank.as_bytes())?.unwrap_or_default())
+}
+
+pub fn increment_rank_tokens<S: Storage>(storage: &mut S, rank: &str) -> StdResult<u64> {
+    let val = rank_num_tokens(storage, rank)? + 1;
+    bucket(RANK_SUPPLY_PREFIX, storage).save(rank.as_bytes(), &val)?;
+    Ok(val)
+}
+
+pub fn decrement_rank_tokens<S: Storage>(storage: &mut S, rank: &str) -> StdResult<u64> {
+    let val = rank_num_tokens(storage, rank)?
+        .checked_sub(1)
+        .ok_or_else(|| StdError::generic_err(format!("No tokens of rank {} left", rank)))?;
+    bucket(RANK_SUPPLY_PREFIX, storage).save(rank.as_bytes(), &val)?;
+    Ok(val)
+}
+
+// BURNED TOKENS, by rank
+pub fn burned_num_tokens<S: ReadonlyStorage>(storage: &S, rank: &str) -> StdResult<u64> {
+    let burned: ReadonlyBucket<S, u64> = bucket_read(BURNED_PREFIX, storage);
+    Ok(burned.may_load(rank.as_bytes())?.unwrap_or_default())
+}
+
+pub fn increment_burned_tokens<S: Storage>(storage: &mut S, rank: &str) -> StdResult<u64> {
+    let val = burned_num_tokens(storage, rank)? + 1;
+    bucket(BURNED_PREFIX, storage).save(rank.as_bytes(), &val)?;
     Ok(val)
 }
 
@@ -131,6 +258,51 @@ pub fn tokens_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, TokenIn
     bucket_read(TOKEN_PREFIX, storage)
 }
 
+/// Index of the token ids of each rank
+pub fn rank_tokens<'a, S: Storage>(storage: &'a mut S, rank: &str) -> Bucket<'a, S, bool> {
+    Bucket::multilevel(&[RANK_TOKENS_PREFIX, rank.as_bytes()], storage)
+}
+
+pub fn rank_tokens_read<'a, S: ReadonlyStorage>(
+    storage: &'a S,
+    rank: &str,
+) -> ReadonlyBucket<'a, S, bool> {
+    ReadonlyBucket::multilevel(&[RANK_TOKENS_PREFIX, rank.as_bytes()], storage)
+}
+
+/// Index of the token ids held by each owner, storing each token's rank
+pub fn owner_tokens<'a, S: Storage>(
+    storage: &'a mut S,
+    owner: &CanonicalAddr,
+) -> Bucket<'a, S, String> {
+    Bucket::multilevel(&[OWNER_TOKENS_PREFIX, owner.as_slice()], storage)
+}
+
+pub fn owner_tokens_read<'a, S: ReadonlyStorage>(
+    storage: &'a S,
+    owner: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, String> {
+    ReadonlyBucket::multilevel(&[OWNER_TOKENS_PREFIX, owner.as_slice()], storage)
+}
+
+/// Index of the token ids held by each owner at each rank, so they can be listed by rank
+/// without going through the owner's other tokens
+pub fn owner_rank_tokens<'a, S: Storage>(
+    storage: &'a mut S,
+    owner: &CanonicalAddr,
+    rank: &str,
+) -> Bucket<'a, S, bool> {
+    Bucket::multilevel(&[OWNER_RANK_TOKENS_PREFIX, owner.as_slice(), rank.as_bytes()], storage)
+}
+
+pub fn owner_rank_tokens_read<'a, S: ReadonlyStorage>(
+    storage: &'a S,
+    owner: &CanonicalAddr,
+    rank: &str,
+) -> ReadonlyBucket<'a, S, bool> {
+    ReadonlyBucket::multilevel(&[OWNER_RANK_TOKENS_PREFIX, owner.as_slice(), rank.as_bytes()], storage)
+}
+
 pub fn operators<'a, S: Storage>(
     storage: &'a mut S,
     owner: &CanonicalAddr,
@@ -0,0 +1,381 @@
+use cosmwasm_std::{
+    log, to_binary, Api, Binary, Env, Extern, HandleResponse, HumanAddr, InitResponse, Querier,
+    StdError, StdResult, Storage,
+};
+
+use cw2::set_contract_version;
+use cw721::{parse_receive_nft, Cw721Contract, Cw721HandleMsg, Cw721ReceiveMsg};
+
+use crate::msg::{
+    CollectionsResponse, DepositResponse, HandleMsg, InitMsg, QueryMsg, ReceiveMsg,
+};
+use crate::state::{config, config_read, deposits, deposits_read, Config};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:cw721-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    msg: InitMsg,
+) -> StdResult<InitResponse> {
+    set_contract_version(&mut deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    if msg.collections.is_empty() {
+        return Err(StdError::generic_err("At least one collection is required"));
+    }
+    let collections: StdResult<Vec<_>> = msg
+        .collections
+        .iter()
+        .map(|addr| deps.api.canonical_address(addr))
+        .collect();
+    config(&mut deps.storage).save(&Config {
+        collections: collections?,
+    })?;
+    Ok(InitResponse::default())
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> StdResult<HandleResponse> {
+    match msg {
+        HandleMsg::ReceiveNft(msg) => try_receive_nft(deps, env, msg),
+        HandleMsg::Withdraw {
+            collection,
+            token_id,
+            recipient,
+        } => try_withdraw(deps, env, collection, token_id, recipient),
+    }
+}
+
+pub fn try_receive_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    wrapper: Cw721ReceiveMsg,
+) -> StdResult<HandleResponse> {
+    let cfg = config_read(&deps.storage).load()?;
+    // the token was sent by env.message.sender, which must be an accepted collection
+    let msg: Option<ReceiveMsg> = parse_receive_nft(&deps.api, &env, &cfg.collections, &wrapper)?;
+    let owner = match msg {
+        Some(ReceiveMsg::Deposit { owner: Some(owner) }) => owner,
+        _ => wrapper.sender,
+    };
+
+    let collection_raw = deps.api.canonical_address(&env.message.sender)?;
+    let owner_raw = deps.api.canonical_address(&owner)?;
+    deposits(&mut deps.storage, &collection_raw).save(wrapper.token_id.as_bytes(), &owner_raw)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "deposit"),
+            log("collection", env.message.sender),
+            log("token_id", wrapper.token_id),
+            log("owner", owner),
+        ],
+        data: None,
+    })
+}
+
+pub fn try_withdraw<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    collection: HumanAddr,
+    token_id: String,
+    recipient: Option<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    let collection_raw = deps.api.canonical_address(&collection)?;
+    let owner_raw = deposits_read(&deps.storage, &collection_raw).load(token_id.as_bytes())?;
+    if deps.api.canonical_address(&env.message.sender)? != owner_raw {
+        return Err(StdError::unauthorized());
+    }
+    deposits(&mut deps.storage, &collection_raw).remove(token_id.as_bytes());
+
+    let recipient = recipient.unwrap_or_else(|| env.message.sender.clone());
+    let transfer = Cw721Contract(collection.clone()).call(Cw721HandleMsg::TransferNft {
+        recipient: recipient.clone(),
+        token_id: token_id.clone(),
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![transfer],
+        log: vec![
+            log("action", "withdraw"),
+            log("collection", collection),
+            log("token_id", token_id),
+            log("recipient", recipient),
+        ],
+        data: None,
+    })
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Collections {} => to_binary(&query_collections(deps)?),
+        QueryMsg::Deposit {
+            collection,
+            token_id,
+        } => to_binary(&query_deposit(deps, collection, token_id)?),
+    }
+}
+
+fn query_collections<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<CollectionsResponse> {
+    let cfg = config_read(&deps.storage).load()?;
+    let collections: StdResult<Vec<_>> = cfg
+        .collections
+        .iter()
+        .map(|addr| deps.api.human_address(addr))
+        .collect();
+    Ok(CollectionsResponse {
+        collections: collections?,
+    })
+}
+
+fn query_deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    collection: HumanAddr,
+    token_id: String,
+) -> StdResult<DepositResponse> {
+    let collection_raw = deps.api.canonical_address(&collection)?;
+    let owner_raw = deposits_read(&deps.storage, &collection_raw).load(token_id.as_bytes())?;
+    Ok(DepositResponse {
+        owner: deps.api.human_address(&owner_raw)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{from_binary, CosmosMsg, Uint128, WasmMsg};
+
+    use cw721_base::msg::{HandleMsg as NftHandleMsg, InitMsg as NftInitMsg};
+    use cw721_base::state::RankInfo;
+
+    const NFT: &str = "nft";
+    const OTHER_NFT: &str = "other_nft";
+    const VAULT: &str = "vault";
+
+    type Deps = Extern<MockStorage, MockApi, MockQuerier>;
+
+    /// env of a call to the given contract
+    fn env_at(contract: &str, sender: &str) -> Env {
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = contract.into();
+        env
+    }
+
+    fn setup_nft(contract: &str) -> Deps {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = NftInitMsg {
+            name: "Lebron Token".into(),
+            symbol: "LBJ".into(),
+            minter: "minter".into(),
+            ranks: vec![RankInfo {
+                id: "B".into(),
+                name: "Base".into(),
+                cap: Uint128(10),
+                upgrade: None,
+                transferable: true,
+            }],
+            base_uri: None,
+            level_thresholds: vec![],
+        };
+        cw721_base::contract::init(&mut deps, env_at(contract, "creator"), msg).unwrap();
+        for _ in 0..2 {
+            let mint = NftHandleMsg::Mint {
+                owner: "medusa".into(),
+                rank: "B".into(),
+                metadata: None,
+            };
+            cw721_base::contract::handle(&mut deps, env_at(contract, "minter"), mint).unwrap();
+        }
+        deps
+    }
+
+    fn setup_vault() -> Deps {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            collections: vec![NFT.into()],
+        };
+        init(&mut deps, env_at(VAULT, "creator"), msg).unwrap();
+        deps
+    }
+
+    /// Runs the wasm message emitted by `from` on the contract it targets
+    fn deliver(msg: &CosmosMsg, from: &str, expected_contract: &str) -> (Env, Binary) {
+        match msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                assert_eq!(contract_addr.as_str(), expected_contract);
+                (env_at(expected_contract, from), msg.clone())
+            }
+            m => panic!("unexpected message: {:?}", m),
+        }
+    }
+
+    fn owner_of(nft: &Deps, token_id: &str) -> HumanAddr {
+        let query = cw721_base::msg::QueryMsg::OwnerOf {
+            token_id: token_id.into(),
+        };
+        let res: cw721::OwnerOfResponse =
+            from_binary(&cw721_base::contract::query(nft, query).unwrap()).unwrap();
+        res.owner
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies(20, &[]);
+        let empty = InitMsg {
+            collections: vec![],
+        };
+        init(&mut deps, mock_env("creator", &[]), empty).unwrap_err();
+
+        let deps = setup_vault();
+        assert_eq!(
+            query_collections(&deps).unwrap().collections,
+            vec![HumanAddr::from(NFT)]
+        );
+    }
+
+    #[test]
+    fn send_and_withdraw() {
+        let mut nft = setup_nft(NFT);
+        let mut vault = setup_vault();
+
+        // medusa sends a token to the vault, which is told about it with ReceiveNft
+        let send = NftHandleMsg::SendNft {
+            contract: VAULT.into(),
+            token_id: "LBJB1".into(),
+            msg: None,
+        };
+        let res = cw721_base::contract::handle(&mut nft, env_at(NFT, "medusa"), send).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(owner_of(&nft, "LBJB1"), HumanAddr::from(VAULT));
+
+        let (env, msg) = deliver(&res.messages[0], NFT, VAULT);
+        let res = handle(&mut vault, env, from_binary(&msg).unwrap()).unwrap();
+        assert_eq!(log("owner", "medusa"), res.log[3]);
+        let deposit = query_deposit(&vault, NFT.into(), "LBJB1".into()).unwrap();
+        assert_eq!(deposit.owner, HumanAddr::from("medusa"));
+
+        // only the owner of the deposit can withdraw it
+        let withdraw = HandleMsg::Withdraw {
+            collection: NFT.into(),
+            token_id: "LBJB1".into(),
+            recipient: Some("venus".into()),
+        };
+        match handle(&mut vault, env_at(VAULT, "venus"), withdraw.clone()).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let res = handle(&mut vault, env_at(VAULT, "medusa"), withdraw.clone()).unwrap();
+        query_deposit(&vault, NFT.into(), "LBJB1".into()).unwrap_err();
+        handle(&mut vault, env_at(VAULT, "medusa"), withdraw).unwrap_err();
+
+        // the vault transfers the token out of its account
+        let (env, msg) = deliver(&res.messages[0], VAULT, NFT);
+        cw721_base::contract::handle(&mut nft, env, from_binary(&msg).unwrap()).unwrap();
+        assert_eq!(owner_of(&nft, "LBJB1"), HumanAddr::from("venus"));
+    }
+
+    #[test]
+    fn deposit_for_another_owner() {
+        let mut nft = setup_nft(NFT);
+        let mut vault = setup_vault();
+
+        // a batch is delivered as one ReceiveNft per token, all crediting venus
+        let send = NftHandleMsg::BatchSendNft {
+            contract: VAULT.into(),
+            token_ids: vec!["LBJB1".into(), "LBJB2".into()],
+            msg: Some(
+                to_binary(&ReceiveMsg::Deposit {
+                    owner: Some("venus".into()),
+                })
+                .unwrap(),
+            ),
+        };
+        let res = cw721_base::contract::handle(&mut nft, env_at(NFT, "medusa"), send).unwrap();
+        assert_eq!(2, res.messages.len());
+        for msg in res.messages.iter() {
+            let (env, msg) = deliver(msg, NFT, VAULT);
+            handle(&mut vault, env, from_binary(&msg).unwrap()).unwrap();
+        }
+        for token_id in ["LBJB1", "LBJB2"].iter() {
+            let deposit = query_deposit(&vault, NFT.into(), token_id.to_string()).unwrap();
+            assert_eq!(deposit.owner, HumanAddr::from("venus"));
+        }
+    }
+
+    #[test]
+    fn rejects_other_collections() {
+        let mut other = setup_nft(OTHER_NFT);
+        let mut vault = setup_vault();
+
+        let send = NftHandleMsg::SendNft {
+            contract: VAULT.into(),
+            token_id: "LBJB1".into(),
+            msg: None,
+        };
+        let res =
+            cw721_base::contract::handle(&mut other, env_at(OTHER_NFT, "medusa"), send).unwrap();
+        let (env, msg) = deliver(&res.messages[0], OTHER_NFT, VAULT);
+        match handle(&mut vault, env, from_binary(&msg).unwrap()).unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Tokens of other_nft are not accepted")
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // nor can anyone fake a ReceiveNft from an accepted collection
+        let fake = HandleMsg::ReceiveNft(Cw721ReceiveMsg {
+            sender: "medusa".into(),
+            token_id: "LBJB1".into(),
+            msg: None,
+        });
+        handle(&mut vault, env_at(VAULT, "medusa"), fake).unwrap_err();
+    }
+
+    #[test]
+    fn deposits_are_kept_per_collection() {
+        let mut vault = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            collections: vec![NFT.into(), OTHER_NFT.into()],
+        };
+        init(&mut vault, env_at(VAULT, "creator"), msg).unwrap();
+
+        // both collections deposit a token with the same id
+        for (collection, owner) in [(NFT, "medusa"), (OTHER_NFT, "venus")].iter() {
+            let receive = HandleMsg::ReceiveNft(Cw721ReceiveMsg {
+                sender: HumanAddr::from(*owner),
+                token_id: "LBJB1".into(),
+                msg: None,
+            });
+            handle(&mut vault, env_at(VAULT, collection), receive).unwrap();
+        }
+        let deposit = query_deposit(&vault, NFT.into(), "LBJB1".into()).unwrap();
+        assert_eq!(deposit.owner, HumanAddr::from("medusa"));
+        let deposit = query_deposit(&vault, OTHER_NFT.into(), "LBJB1".into()).unwrap();
+        assert_eq!(deposit.owner, HumanAddr::from("venus"));
+
+        // withdrawing one leaves the other in place
+        let withdraw = HandleMsg::Withdraw {
+            collection: NFT.into(),
+            token_id: "LBJB1".into(),
+            recipient: None,
+        };
+        handle(&mut vault, env_at(VAULT, "medusa"), withdraw).unwrap();
+        query_deposit(&vault, NFT.into(), "LBJB1".into()).unwrap_err();
+        let deposit = query_deposit(&vault, OTHER_NFT.into(), "LBJB1".into()).unwrap();
+        assert_eq!(deposit.owner, HumanAddr::from("venus"));
+    }
+}
@@ -0,0 +1,56 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::HumanAddr;
+use cw721::Cw721ReceiveMsg;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitMsg {
+    /// The cw721 contracts whose tokens the vault accepts
+    pub collections: Vec<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    /// Deposits a token sent with SendNft. The msg is an optional ReceiveMsg
+    ReceiveNft(Cw721ReceiveMsg),
+    /// Transfers a deposited token to the recipient, or back to its owner if unset.
+    /// Only the owner of the deposit can do this
+    Withdraw {
+        collection: HumanAddr,
+        token_id: String,
+        recipient: Option<HumanAddr>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Credits the token to owner instead of the account that sent it
+    Deposit { owner: Option<HumanAddr> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// The accepted cw721 contracts
+    /// Return type: CollectionsResponse
+    Collections {},
+    /// Who can withdraw the token, error if it was not deposited
+    /// Return type: DepositResponse
+    Deposit {
+        collection: HumanAddr,
+        token_id: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollectionsResponse {
+    pub collections: Vec<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositResponse {
+    pub owner: HumanAddr,
+}
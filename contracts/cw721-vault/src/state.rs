@@ -0,0 +1,39 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{CanonicalAddr, ReadonlyStorage, Storage};
+use cosmwasm_storage::{
+    singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton, Singleton,
+};
+
+pub const CONFIG_KEY: &[u8] = b"config";
+pub const DEPOSITS_PREFIX: &[u8] = b"deposits";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    /// The cw721 contracts whose tokens are accepted
+    pub collections: Vec<CanonicalAddr>,
+}
+
+pub fn config<S: Storage>(storage: &mut S) -> Singleton<'_, S, Config> {
+    singleton(storage, CONFIG_KEY)
+}
+
+pub fn config_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<'_, S, Config> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+/// Owner of each deposited token of the collection, by token_id
+pub fn deposits<'a, S: Storage>(
+    storage: &'a mut S,
+    collection: &CanonicalAddr,
+) -> Bucket<'a, S, CanonicalAddr> {
+    Bucket::multilevel(&[DEPOSITS_PREFIX, collection.as_slice()], storage)
+}
+
+pub fn deposits_read<'a, S: ReadonlyStorage>(
+    storage: &'a S,
+    collection: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, CanonicalAddr> {
+    ReadonlyBucket::multilevel(&[DEPOSITS_PREFIX, collection.as_slice()], storage)
+}
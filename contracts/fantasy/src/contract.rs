@@ -1,26 +1,53 @@
 use cosmwasm_std::{
-    from_binary, log, to_binary, Api, Binary, Env, Extern, HandleResponse, HumanAddr,
-    InitResponse, Querier, WasmQuery, StdError, StdResult, Storage, Uint128, Coin,
+    from_binary, log, to_binary, Api, BankMsg, Binary, CosmosMsg, Env, Extern, HandleResponse,
+    HumanAddr, InitResponse, Order, BlockInfo, Querier, WasmQuery, StdError, StdResult, Storage, Uint128,
+    CanonicalAddr, Coin, Decimal,
 };
 use cosmwasm_storage::to_length_prefixed;
 use cosmwasm_bignumber::{Decimal256};
 //use rand::distributions::{Distribution, Uniform};
 
+use cw0::{calc_range_start_string, Duration};
 use cw2::set_contract_version;
-use cw20::{Cw20HandleMsg};
+use cw20::{BalanceResponse, Cw20HandleMsg, Cw20QueryMsg, Cw20ReceiveMsg, MinterResponse};
+use cw721::{AllNftInfoResponse, Cw721HandleMsg, Cw721QueryMsg, Cw721ReceiveMsg};
 
 
-use crate::msg::{HandleMsg, InitMsg, QueryMsg, AnchorMsg, TokenMsg, ConfigResponse, StateResponse,
-                 QueryMintMsg, ContractCountResponse,                    
+use crate::msg::{HandleMsg, InitMsg, QueryMsg, AnchorMsg, TokenMsg, ConfigResponse, AnchorQueryMsg,
+                 EpochStateResponse, QueryMintMsg, ContractCountResponse, UserHistoryResponse, HistoryItem,
+                 ReferralStatsResponse, StarterPackResponse, SaleMsg, SaleStatusResponse,
+                 DutchAuctionMsg, RedeemOption, RetirementResponse, RetirementReserveResponse,
+                 ContestEntryResponse,
+                 RankPayout, YieldSplitResponse, Cw20HookMsg, ShareTokenResponse,
+                 WithdrawalQueueResponse, WithdrawalItem, TokenMint, Cw721HookMsg,
+                 RecipesResponse, RecipeItem,
 };
 use crate::state::{
-    increase_deposit, total_deposit, total_deposit_read, state, state_read, State, TokenData,
-    token_addresses, token_addresses_read,
+    increase_deposit, reduce_deposit, total_deposit, load_total_deposit, state, state_read, State,
+    TokenData, token_addresses, token_addresses_read,
     get_contract_count, increment_contract_count,
+    pause_state, pause_state_read, assert_not_paused, PauseScope, PauseState,
+    deposits, deposits_read,
+    append_history, history_read, parse_id, HistoryEntry, HistoryEvent, PulledAthlete,
+    referrals, referrals_read,
+    starter_claims, starter_claims_read, starter_merkle_root, starter_merkle_root_read,
+    sale, sale_read, next_sale_id, sale_purchases, presale_whitelist, presale_whitelist_read,
+    Sale, SalePhase, PackType, DutchAuction, dutch_auction, dutch_auction_read,
+    retired, is_retired, retirement_payouts, retirement_payouts_read,
+    retirement_reserve, retirement_reserve_read,
+    yield_split, yield_split_read, YieldSplit, treasury, treasury_read, Treasury,
+    share_token, share_token_read,
+    withdrawal_queue, withdrawal_queue_read, next_withdrawal_id, QueuedWithdrawal,
+    deposit_limits, deposit_limits_read, DepositLimits,
+    contest_config, contest_config_read, ContestConfig, contest_results, contest_results_read,
+    contest_claims, ContestPayout, ContestResult, contest_entries, contest_entries_read,
+    assert_lineup_active,
+    recipes, recipes_read, Recipe, CraftInput, craft_escrow, craft_escrow_read,
 };
 use crate::helpers::{
     encode_msg_execute,
     encode_raw_query,
+    verify_merkle_proof,
 };
 use crate::querier::{deduct_tax};
 
@@ -40,8 +67,16 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         anchor_addr: msg.anchor_addr,
         terrand_addr: msg.terrand_addr,
         pack_len: msg.pack_len,
+        owner: env.message.sender.clone(),
+        guardian: msg.guardian,
+        pack_price: msg.pack_price,
+        referral_rate: validate_rate(msg.referral_rate)?,
+        standard_rank: msg.standard_rank,
+        premium_rank: msg.premium_rank,
     };
 
+    // saved first, since adding tokens checks the sender is the owner
+    state(&mut deps.storage).save(&info)?;
     match msg.tokens {
         Some(m) => handle_add_token(deps, env, m)?,
         None => HandleResponse {
@@ -51,8 +86,9 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         },
     };
 
-    state(&mut deps.storage).save(&info)?;
-    total_deposit(&mut deps.storage).save(&0)?;
+    total_deposit(&mut deps.storage).save(&Uint128::zero())?;
+    pause_state(&mut deps.storage).save(&PauseState::default())?;
+    yield_split(&mut deps.storage).save(&YieldSplit::default())?;
     Ok(InitResponse::default())
 }
 
@@ -62,54 +98,167 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     msg: HandleMsg,
 ) -> StdResult<HandleResponse> {
     match msg {
-        HandleMsg::PurchasePack {} => handle_purchase(deps, env),
+        HandleMsg::PurchasePack {
+            referrer,
+            pack_type,
+        } => handle_purchase(deps, env, referrer, pack_type),
         HandleMsg::DepositStable {} => handle_deposit(deps, env),
-        HandleMsg::RedeemStable {
-            amount,
-        } => handle_redeem(deps, env, amount),
         HandleMsg::AddToken {
             tokens
         } => handle_add_token(deps, env, tokens),
+        HandleMsg::WithdrawStable {
+            amount,
+        } => handle_withdraw(deps, env, amount),
+        HandleMsg::Pause { scope } => handle_pause(deps, env, scope),
+        HandleMsg::Unpause { scope } => handle_unpause(deps, env, scope),
+        HandleMsg::ClaimReferral {} => handle_claim_referral(deps, env),
+        HandleMsg::UpdateConfig {
+            pack_price,
+            referral_rate,
+            standard_rank,
+            premium_rank,
+        } => handle_update_config(deps, env, pack_price, referral_rate, standard_rank, premium_rank),
+        HandleMsg::ClaimStarterPack {
+            proof,
+        } => handle_claim_starter_pack(deps, env, proof),
+        HandleMsg::UpdateMerkleRoot {
+            merkle_root,
+        } => handle_update_merkle_root(deps, env, merkle_root),
+        HandleMsg::SetSale { sale } => handle_set_sale(deps, env, sale),
+        HandleMsg::SetDutchAuction { auction } => handle_set_dutch_auction(deps, env, auction),
+        HandleMsg::RetireAthlete { athlete_id } => handle_retire_athlete(deps, env, athlete_id),
+        HandleMsg::SetRetirementPayout {
+            rank,
+            amount,
+        } => handle_set_retirement_payout(deps, env, rank, amount),
+        HandleMsg::FundReserve {} => handle_fund_reserve(deps, env),
+        HandleMsg::RedeemRetired {
+            athlete_id,
+            token_id,
+            redeem_for,
+        } => handle_redeem_retired(deps, env, athlete_id, token_id, redeem_for),
+        HandleMsg::Harvest {} => handle_harvest(deps, env),
+        HandleMsg::UpdateYieldSplit { split } => handle_update_yield_split(deps, env, split),
+        HandleMsg::RegisterShareToken { token } => handle_register_share_token(deps, env, token),
+        HandleMsg::Receive(msg) => handle_receive(deps, env, msg),
+        HandleMsg::ProcessQueue { limit } => handle_process_queue(deps, env, limit),
+        HandleMsg::CancelWithdrawal { id } => handle_cancel_withdrawal(deps, env, id),
+        HandleMsg::UpdateDepositLimits { limits } => handle_update_deposit_limits(deps, env, limits),
+        HandleMsg::UpdateContestConfig {
+            dispute_window,
+            multisig,
+        } => handle_update_contest_config(deps, env, dispute_window, multisig),
+        HandleMsg::EnterContest {
+            contest_id,
+            lineup,
+        } => handle_enter_contest(deps, env, contest_id, lineup),
+        HandleMsg::SettleContest {
+            contest_id,
+            payouts,
+        } => handle_settle_contest(deps, env, contest_id, payouts),
+        HandleMsg::CorrectResults {
+            contest_id,
+            payouts,
+        } => handle_correct_results(deps, env, contest_id, payouts),
+        HandleMsg::ClaimPayout { contest_id } => handle_claim_payout(deps, env, contest_id),
+        HandleMsg::SetRecipe {
+            recipe_id,
+            recipe,
+        } => handle_set_recipe(deps, env, recipe_id, recipe),
+        HandleMsg::Craft {
+            recipe_id,
+            inputs,
+        } => handle_craft(deps, env, recipe_id, inputs),
+        HandleMsg::ReceiveNft(msg) => handle_receive_nft(deps, env, msg),
+        HandleMsg::CancelCraft { recipe_id } => handle_cancel_craft(deps, env, recipe_id),
     }
 }
 
 pub fn handle_purchase<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    referrer: Option<HumanAddr>,
+    pack_type: Option<PackType>,
 ) -> StdResult<HandleResponse> {
+    assert_not_paused(&deps.storage, PauseScope::Purchases)?;
     let sender_raw = deps.api.canonical_address(&env.message.sender)?;
     let sender = deps.api.human_address(&sender_raw)?;
+    let info = query_state(deps)?;
 
-    // TODO: Generate N token ids based on the pack_len using Terrand
-    let athlete_pack = generate_pack(&deps).unwrap();
-    let mut mint_responses = vec![];
+    let pack_type = pack_type.unwrap_or(PackType::Standard);
+    let price = match pack_type {
+        PackType::Standard => {
+            record_sale_purchase(deps, &env, &sender_raw)?;
+            info.pack_price
+        }
+        PackType::Premium => record_auction_purchase(deps, &env)?,
+    };
 
-    for athlete in athlete_pack.iter() {
-        let token_address = query_token_address(&deps, athlete.clone().to_string()).unwrap();
+    let payment: Uint128 = env
+        .message
+        .sent_funds
+        .iter()
+        .find(|c| c.denom == info.stable_denom)
+        .map(|c| c.amount)
+        .unwrap_or_else(Uint128::zero);
+    if payment < price {
+        return Err(StdError::generic_err(format!(
+            "Pack price is {}{}",
+            price, info.stable_denom
+        )));
+    }
 
-        //TODO: Handle error from query_token_address. Ensure that the generated token ids are a subset of the token addresses
-        
-        let mint_msg = TokenMsg::Mint {
-            owner: sender.clone(),
-            rank: "B".to_string(),
-        };
+    // the referrer is credited first, the rest of the proceeds go to Anchor
+    let mut messages = vec![];
+    let referral_amount = match &referrer {
+        Some(referrer) => credit_referrer(deps, &sender_raw, referrer, price)?,
+        None => Uint128::zero(),
+    };
+    let proceeds = (price - referral_amount)?;
+    if !proceeds.is_zero() {
+        // sale revenue is not any depositor's principal, so it stays out of total_deposit
+        let (anchor_res, coin_deposit) = deposit_stable_msg(deps, proceeds)?;
+        let mut totals = treasury_read(&deps.storage).may_load()?.unwrap_or_default();
+        totals.pack_proceeds += coin_deposit.amount;
+        treasury(&mut deps.storage).save(&totals)?;
+        messages.push(anchor_res);
+    }
 
-        let mint_res = encode_msg_execute(
-            to_binary(&mint_msg).unwrap(),
-            token_address.clone(),
-            vec![]
+    // return anything paid above the pack price
+    let excess = (payment - price)?;
+    if !excess.is_zero() {
+        let refund = deduct_tax(
+            deps,
+            Coin {
+                denom: info.stable_denom.clone(),
+                amount: excess,
+            }
         )?;
+        messages.push(BankMsg::Send {
+            from_address: env.contract.address.clone(),
+            to_address: sender.clone(),
+            amount: vec![refund],
+        }.into());
+    }
 
-        // TODO: handle error from mint_res
+    let (mint_responses, pulled_athletes) = open_pack(deps, &sender, pack_type)?;
 
-        mint_responses.push(mint_res);
-    }
+    append_history(&mut deps.storage, &sender_raw, &HistoryEntry {
+        height: env.block.height,
+        event: HistoryEvent::PurchasePack { athletes: pulled_athletes },
+    })?;
+
+    messages.extend(mint_responses);
 
     let response = HandleResponse {
-        messages: mint_responses,
+        messages,
         log: vec![
             log("action", "purchase"),
             log("from", &sender),
+            log("pack_type", format!("{:?}", pack_type)),
+            log("price", price),
+            log("referrer", referrer.map(|r| r.to_string()).unwrap_or_default()),
+            log("referral_amount", referral_amount),
         ],
         data: None,
     };
@@ -117,231 +266,3577 @@ pub fn handle_purchase<S: Storage, A: Api, Q: Querier>(
     Ok(response)
 }
 
-pub fn handle_deposit<S: Storage, A: Api, Q: Querier>(
+/// Checks the scheduled sale (if any) allows the sender to buy a standard pack now and
+/// counts it. Premium packs skip it, as the dutch auction has its own window and supply
+fn record_sale_purchase<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    sender_raw: &CanonicalAddr,
+) -> StdResult<()> {
+    let mut current = match sale_read(&deps.storage).may_load()? {
+        Some(current) => current,
+        None => return Ok(()),
+    };
+
+    match current.phase(&env.block) {
+        SalePhase::NotStarted => return Err(StdError::generic_err("Sale has not started")),
+        SalePhase::Ended => return Err(StdError::generic_err("Sale has ended")),
+        SalePhase::SoldOut => return Err(StdError::generic_err("Sale is sold out")),
+        SalePhase::Presale => {
+            let whitelisted = presale_whitelist_read(&deps.storage, current.id)
+                .may_load(sender_raw.as_slice())?
+                .unwrap_or_default();
+            if !whitelisted {
+                return Err(StdError::generic_err(
+                    "Only whitelisted addresses can buy during the presale",
+                ));
+            }
+        }
+        SalePhase::Public | SalePhase::Unrestricted => {}
+    }
+
+    let max_per_address = current.max_per_address;
+    sale_purchases(&mut deps.storage, current.id).update(sender_raw.as_slice(), |bought| {
+        let bought = bought.unwrap_or_default() + 1;
+        if bought > max_per_address {
+            return Err(StdError::generic_err(format!(
+                "Cannot buy more than {} packs in this sale",
+                max_per_address
+            )));
+        }
+        Ok(bought)
+    })?;
+
+    current.sold += 1;
+    sale(&mut deps.storage).save(&current)
+}
+
+pub fn handle_set_sale<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    msg: Option<SaleMsg>,
 ) -> StdResult<HandleResponse> {
-    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
-    let sender = deps.api.human_address(&sender_raw)?;
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
 
-    let deposit_amount: Uint128 = env
-        .message
-        .sent_funds
-        .iter()
-        .find(|c| c.denom == "uusd")
-        .map(|c| Uint128::from(c.amount))
-        .unwrap_or_else(Uint128::zero);
-    
-    // coin deposit minus tax
-    let coin_deposit = deduct_tax(
-        &deps, 
-        Coin {
-            denom: "uusd".to_string(),
-            amount: deposit_amount
+    let msg = match msg {
+        Some(msg) => msg,
+        None => {
+            sale(&mut deps.storage).remove();
+            return Ok(HandleResponse {
+                messages: vec![],
+                log: vec![log("action", "remove_sale")],
+                data: None,
+            });
         }
-    )?;
-    let contract = query_state(&deps).unwrap().anchor_addr;
-    
-    // execute anchor's deposit stable contract
-    let deposit_msg = to_binary(&AnchorMsg::DepositStable{})?;
-    let anchor_res = encode_msg_execute(
-        deposit_msg,
-        contract.clone(),
-        vec![coin_deposit.clone()]
-    )?;
+    };
 
-    increase_deposit(&mut deps.storage, coin_deposit.amount.u128() as u64)?;
+    // heights and times do not compare, so `end <= start` would let a mixed range through
+    let mut bounds = vec![msg.start, msg.end];
+    bounds.extend(msg.presale_end);
+    if bounds.iter().any(|a| bounds.iter().any(|b| a.partial_cmp(b).is_none())) {
+        return Err(StdError::generic_err(
+            "Sale must start and end both at a height or both at a time",
+        ));
+    }
+    if msg.end <= msg.start {
+        return Err(StdError::generic_err("Sale must end after it starts"));
+    }
+    if msg.end.is_expired(&env.block) {
+        return Err(StdError::generic_err("Sale has already ended"));
+    }
 
-    let response = HandleResponse {
-        messages: vec![anchor_res],
+    let id = next_sale_id(&mut deps.storage)?;
+    for addr in msg.presale_whitelist.iter() {
+        let addr_raw = deps.api.canonical_address(addr)?;
+        presale_whitelist(&mut deps.storage, id).save(addr_raw.as_slice(), &true)?;
+    }
+
+    let new_sale = Sale {
+        id,
+        start: msg.start,
+        end: msg.end,
+        presale_end: msg.presale_end,
+        total_supply: msg.total_supply,
+        max_per_address: msg.max_per_address,
+        sold: 0,
+    };
+    sale(&mut deps.storage).save(&new_sale)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
         log: vec![
-            log("action", "deposit"),
-            log("from", &sender),
-            log("to", &contract),
-            log("deposit_amount", &coin_deposit.amount),
+            log("action", "set_sale"),
+            log("sale_id", id),
+            log("total_supply", msg.total_supply),
         ],
         data: None,
+    })
+}
+
+/// Checks the premium pack auction is running and has supply left, counts the
+/// purchase and returns the current price
+fn record_auction_purchase<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<Uint128> {
+    let mut auction = dutch_auction_read(&deps.storage)
+        .may_load()?
+        .ok_or_else(|| StdError::generic_err("Premium packs are not for sale"))?;
+
+    if !auction.start.is_expired(&env.block) {
+        return Err(StdError::generic_err("Auction has not started"));
+    }
+    if auction.end.is_expired(&env.block) {
+        return Err(StdError::generic_err("Auction has ended"));
+    }
+    if auction.sold >= auction.supply {
+        return Err(StdError::generic_err("Premium packs are sold out"));
+    }
+    let price = auction.price_at(&env.block)?;
+
+    auction.sold += 1;
+    dutch_auction(&mut deps.storage).save(&auction)?;
+    Ok(price)
+}
+
+pub fn handle_set_dutch_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: Option<DutchAuctionMsg>,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    let msg = match msg {
+        Some(msg) => msg,
+        None => {
+            dutch_auction(&mut deps.storage).remove();
+            return Ok(HandleResponse {
+                messages: vec![],
+                log: vec![log("action", "remove_dutch_auction")],
+                data: None,
+            });
+        }
     };
-    
-    Ok(response)
+
+    if msg.floor_price > msg.start_price {
+        return Err(StdError::generic_err("Floor price cannot be above the start price"));
+    }
+    let auction = DutchAuction {
+        start_price: msg.start_price,
+        floor_price: msg.floor_price,
+        start: msg.start,
+        end: msg.end,
+        supply: msg.supply,
+        sold: 0,
+    };
+    // rejects mixed height/time ranges and ranges that end before they start
+    auction.price_at(&env.block)?;
+    dutch_auction(&mut deps.storage).save(&auction)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_dutch_auction"),
+            log("start_price", msg.start_price),
+            log("floor_price", msg.floor_price),
+            log("supply", msg.supply),
+        ],
+        data: None,
+    })
 }
 
-pub fn handle_redeem<S: Storage, A: Api, Q: Querier>(
+/// Builds the mint messages for a new pack owned by `owner`
+fn open_pack<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &HumanAddr,
+    pack_type: PackType,
+) -> StdResult<(Vec<CosmosMsg>, Vec<PulledAthlete>)> {
+    let info = query_state(deps)?;
+    let athlete_pack = generate_pack(deps, pack_type)?;
+    // pulls are grouped by athlete contract, so each contract gets a single message
+    let mut mints: Vec<(HumanAddr, Vec<TokenMint>)> = vec![];
+    let mut pulled_athletes = vec![];
+
+    for (i, athlete) in athlete_pack.iter().enumerate() {
+        let token_address = query_token_address(deps, athlete.clone())?;
+
+        // premium packs guarantee the first pull is of the premium rank
+        let rank = match (pack_type, i) {
+            (PackType::Premium, 0) => &info.premium_rank,
+            _ => &info.standard_rank,
+        };
+        pulled_athletes.push(PulledAthlete {
+            athlete_id: athlete.clone(),
+            contract_addr: token_address.clone(),
+            rank: rank.to_string(),
+        });
+
+        let mint = TokenMint {
+            owner: owner.clone(),
+            rank: rank.to_string(),
+        };
+        match mints.iter_mut().find(|(addr, _)| *addr == token_address) {
+            Some((_, contract_mints)) => contract_mints.push(mint),
+            None => mints.push((token_address, vec![mint])),
+        }
+    }
+
+    let mut mint_responses = vec![];
+    for (token_address, mut contract_mints) in mints {
+        let mint_msg = if contract_mints.len() == 1 {
+            let mint = contract_mints.remove(0);
+            TokenMsg::Mint {
+                owner: mint.owner,
+                rank: mint.rank,
+            }
+        } else {
+            TokenMsg::BatchMint { mints: contract_mints }
+        };
+        mint_responses.push(encode_msg_execute(to_binary(&mint_msg)?, token_address, vec![])?);
+    }
+
+    Ok((mint_responses, pulled_athletes))
+}
+
+pub fn handle_claim_starter_pack<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    amount: Uint128,
+    proof: Vec<String>,
 ) -> StdResult<HandleResponse> {
+    assert_not_paused(&deps.storage, PauseScope::Purchases)?;
     let sender_raw = deps.api.canonical_address(&env.message.sender)?;
-    let sender = deps.api.human_address(&sender_raw)?;
-    let anchor_contract = query_state(&deps).unwrap().anchor_addr;
+    let sender = env.message.sender;
 
-     // get exchange rate from anchor state
-     let state_bin: Binary = encode_raw_query(
-        &deps, 
-        Binary::from(to_length_prefixed(b"state")),
-        anchor_contract.clone(),
-    )?;
+    // one starter pack per address
+    starter_claims(&mut deps.storage).update(sender_raw.as_slice(), |claimed| match claimed {
+        Some(_) => Err(StdError::generic_err("Starter pack already claimed")),
+        None => Ok(true),
+    })?;
 
-    // transform binary response to state response
-    let state_response: StateResponse = from_binary(&state_bin)?;
-    let exchange_rate: Decimal256 = state_response.prev_exchange_rate.into();
+    // if an allowlist is set, the sender must prove they are on it
+    if let Some(root) = starter_merkle_root_read(&deps.storage).may_load()? {
+        if !verify_merkle_proof(sender.as_str(), &proof, root.as_slice())? {
+            return Err(StdError::generic_err("Invalid merkle proof"));
+        }
+    }
 
-    let contract_msg = to_binary(&AnchorMsg::RedeemStable{})?;
-    let aust_amount = amount * (Decimal256::one() / exchange_rate).into();
+    let (mint_responses, pulled_athletes) = open_pack(deps, &sender, PackType::Standard)?;
 
-    let logs = vec![
-        log("action", "receive"),
-        log("from", &sender),
-        log("to", &anchor_contract),
-        log("amount", &amount),
-        log("aust_amount", &aust_amount),
-    ];
+    append_history(&mut deps.storage, &sender_raw, &HistoryEntry {
+        height: env.block.height,
+        event: HistoryEvent::StarterPack { athletes: pulled_athletes },
+    })?;
 
-    // get anchor usd (aust) contract address from anchor config
-    let config_bin: Binary = encode_raw_query(
-        &deps,
-        Binary::from(to_length_prefixed(b"config")),
-        anchor_contract.clone(),
-    )?;
+    Ok(HandleResponse {
+        messages: mint_responses,
+        log: vec![
+            log("action", "claim_starter_pack"),
+            log("from", &sender),
+        ],
+        data: None,
+    })
+}
 
-    // transform binary response to state response
-    let config_response: ConfigResponse = from_binary(&config_bin)?;
-    let aterra_contract = deps.api.human_address(&config_response.aterra_contract)?;
+pub fn handle_retire_athlete<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    athlete_id: String,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
 
-    // create a send message
-    let msg = to_binary(&Cw20HandleMsg::Send{
-        amount: aust_amount,
-        contract: anchor_contract,
-        msg: Some(contract_msg)
+    let token_address = query_token_address(deps, athlete_id.clone())?;
+    retired(&mut deps.storage).update(athlete_id.as_bytes(), |retired| match retired {
+        Some(_) => Err(StdError::generic_err("Athlete already retired")),
+        None => Ok(true),
     })?;
 
-    let anchor_response = encode_msg_execute(
-        msg,
-        aterra_contract,
-        vec![]
-    )?;
+    // this contract is the athlete contract's minter, so it can freeze it
+    let freeze_msg = encode_msg_execute(to_binary(&TokenMsg::Freeze {})?, token_address, vec![])?;
 
-    let res = HandleResponse {
-        messages: vec![anchor_response],
-        log: logs,
+    Ok(HandleResponse {
+        messages: vec![freeze_msg],
+        log: vec![
+            log("action", "retire_athlete"),
+            log("athlete_id", athlete_id),
+        ],
         data: None,
-    };
-    
-    Ok(res)
+    })
 }
 
-pub fn handle_add_token<S: Storage, A: Api, Q: Querier>(
+pub fn handle_set_retirement_payout<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    _env: Env,
-    tokens: Vec<TokenData>,
+    env: Env,
+    rank: String,
+    amount: Uint128,
 ) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
 
-    for token in tokens.iter() {
-        token_addresses(&mut deps.storage).update(token.athlete_id.as_bytes(), |old| match old {
-            Some(_) => Err(StdError::generic_err("athlete_id already claimed")),
-            None => Ok(token.contract_addr.clone()),
-        })?;
+    retirement_payouts(&mut deps.storage).save(rank.as_bytes(), &amount)?;
 
-        increment_contract_count(&mut deps.storage)?;
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_retirement_payout"),
+            log("rank", rank),
+            log("amount", amount),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_fund_reserve<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    let amount = env
+        .message
+        .sent_funds
+        .iter()
+        .find(|c| c.denom == info.stable_denom)
+        .map(|c| c.amount)
+        .unwrap_or_else(Uint128::zero);
+    if amount.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "No {} assets are provided to fund the reserve",
+            info.stable_denom
+        )));
     }
 
-    let response = HandleResponse {
+    let reserve = retirement_reserve_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_else(Uint128::zero)
+        + amount;
+    retirement_reserve(&mut deps.storage).save(&reserve)?;
+
+    Ok(HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "add_token"),
+            log("action", "fund_reserve"),
+            log("from", env.message.sender),
+            log("amount", amount),
+            log("reserve", reserve),
         ],
         data: None,
-    };
-    
-    Ok(response)
+    })
 }
 
-pub fn query<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    msg: QueryMsg,
-) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::State {} => to_binary(&query_state(deps)?),
-        QueryMsg::TotalDeposit {} => to_binary(&query_total_deposit(deps)?),
-        QueryMsg::TokenContract {
-            athlete_id
-        } => to_binary(&query_token_address(deps, athlete_id)?),
-        QueryMsg::IsTokenMintable {
-            athlete_id
-        } => to_binary(&query_token_mintable(deps, athlete_id)?),
-        QueryMsg::ContractCount {} => to_binary(&query_contract_count(deps)?),
+pub fn handle_redeem_retired<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    athlete_id: String,
+    token_id: String,
+    redeem_for: RedeemOption,
+) -> StdResult<HandleResponse> {
+    assert_not_paused(&deps.storage, PauseScope::Redeems)?;
+    if !is_retired(&deps.storage, &athlete_id)? {
+        return Err(StdError::generic_err("Athlete is not retired"));
     }
-}
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    let sender = env.message.sender.clone();
+    let token_address = query_token_address(deps, athlete_id.clone())?;
 
-fn query_state<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<State> {
-    state_read(&deps.storage).load()
-}
+    let nft: AllNftInfoResponse = deps.querier.query(&WasmQuery::Smart {
+        contract_addr: token_address.clone(),
+        msg: to_binary(&Cw721QueryMsg::AllNftInfo { token_id: token_id.clone() })?,
+    }.into())?;
+    if nft.access.owner != sender {
+        return Err(StdError::unauthorized());
+    }
 
-fn query_total_deposit<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<Uint128> {
-    Ok(Uint128(total_deposit_read(&deps.storage).load()? as u128))
-}
+    let burn_msg = Cw721HandleMsg::Burn {
+        token_id: token_id.clone(),
+    };
+    let mut messages = vec![encode_msg_execute(to_binary(&burn_msg)?, token_address, vec![])?];
 
-fn query_contract_count<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-) -> StdResult<ContractCountResponse> {
-    let count = get_contract_count(&deps.storage)?;
-    Ok(ContractCountResponse { count })
+    let event = match redeem_for {
+        RedeemOption::Payout => {
+            let amount = retirement_payouts_read(&deps.storage)
+                .may_load(nft.info.rank.as_bytes())?
+                .unwrap_or_else(Uint128::zero);
+            if amount.is_zero() {
+                return Err(StdError::generic_err(format!(
+                    "No payout is set for rank {}",
+                    nft.info.rank
+                )));
+            }
+            let reserve = retirement_reserve_read(&deps.storage)
+                .may_load()?
+                .unwrap_or_else(Uint128::zero);
+            let reserve = (reserve - amount)
+                .map_err(|_| StdError::generic_err("Retirement reserve cannot cover the payout"))?;
+            retirement_reserve(&mut deps.storage).save(&reserve)?;
+
+            let payout = deduct_tax(
+                deps,
+                Coin {
+                    denom: query_state(deps)?.stable_denom,
+                    amount,
+                },
+            )?;
+            messages.push(BankMsg::Send {
+                from_address: env.contract.address.clone(),
+                to_address: sender.clone(),
+                amount: vec![payout],
+            }.into());
+            HistoryEvent::RetirementPayout { token_id: token_id.clone(), amount }
+        }
+        RedeemOption::Pack => {
+            let (mint_responses, pulled_athletes) = open_pack(deps, &sender, PackType::Standard)?;
+            messages.extend(mint_responses);
+            HistoryEvent::ReplacementPack { token_id: token_id.clone(), athletes: pulled_athletes }
+        }
+    };
+
+    append_history(&mut deps.storage, &sender_raw, &HistoryEntry {
+        height: env.block.height,
+        event,
+    })?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "redeem_retired"),
+            log("from", &sender),
+            log("athlete_id", athlete_id),
+            log("token_id", token_id),
+            log("rank", nft.info.rank),
+        ],
+        data: None,
+    })
 }
 
-fn query_token_address<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    athlete_id: String
-) -> StdResult<HumanAddr> {
-    token_addresses_read(&deps.storage).load(athlete_id.as_bytes())
+pub fn handle_set_recipe<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipe_id: String,
+    recipe: Option<Recipe>,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    match &recipe {
+        Some(recipe) => {
+            if recipe.inputs.is_empty() {
+                return Err(StdError::generic_err("Recipe must have inputs"));
+            }
+            for (i, input) in recipe.inputs.iter().enumerate() {
+                if input.count == 0 {
+                    return Err(StdError::generic_err("Recipe inputs must take at least one token"));
+                }
+                // the same tokens listed twice could not be told apart when crafting
+                if recipe.inputs[..i]
+                    .iter()
+                    .any(|other| other.contract == input.contract && other.rank == input.rank)
+                {
+                    return Err(StdError::generic_err(format!(
+                        "Rank {} of {} is listed twice",
+                        input.rank, input.contract
+                    )));
+                }
+            }
+            recipes(&mut deps.storage).save(recipe_id.as_bytes(), recipe)?;
+        }
+        None => recipes(&mut deps.storage).remove(recipe_id.as_bytes()),
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_recipe"),
+            log("recipe_id", recipe_id),
+        ],
+        data: None,
+    })
 }
 
-fn query_token_mintable<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    athlete_id: String
-) -> StdResult<bool> {
-    let token_address = query_token_address(&deps, athlete_id).unwrap();
+pub fn handle_craft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipe_id: String,
+    inputs: Vec<CraftInput>,
+) -> StdResult<HandleResponse> {
+    assert_not_paused(&deps.storage, PauseScope::Purchases)?;
+    let recipe = load_recipe(deps, &recipe_id)?;
+    let sender = env.message.sender.clone();
 
-    // TODO: Query token_address if mintable using the NFT contract's IsMintable{} query
-    let msg = QueryMintMsg::IsMintable { rank: "B".to_string() };
-    let wasm = WasmQuery::Smart {
-        contract_addr: token_address,
-        msg: to_binary(&msg)?,
-    };
-    let is_mintable: bool = deps.querier.query(&wasm.into())?;
+    // every token must be the sender's and match one of the inputs, in the right counts
+    let counts = count_craft_inputs(deps, &recipe_id, &recipe, &inputs, &sender)?;
+    check_craft_counts(&recipe_id, &recipe, &counts, true)?;
 
-    Ok(is_mintable)
+    // burning fails unless this contract is an operator of the sender, which reverts the mint
+    craft(deps, &env, sender, recipe_id, recipe, inputs)
 }
 
+/// Holds an athlete token sent with SendNft for the recipe and crafts once all its inputs
+/// are received. The tokens are owned by this contract by now, so it can burn them
+pub fn handle_receive_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    wrapper: Cw721ReceiveMsg,
+) -> StdResult<HandleResponse> {
+    // rejecting the token reverts the SendNft, so it stays with the sender
+    assert_not_paused(&deps.storage, PauseScope::Purchases)?;
+    let recipe_id = match &wrapper.msg {
+        Some(bin) => match from_binary(bin)? {
+            Cw721HookMsg::Craft { recipe_id } => recipe_id,
+        },
+        None => return Err(StdError::generic_err("Missing the recipe to craft")),
+    };
+    let recipe = load_recipe(deps, &recipe_id)?;
+    // only the input contracts of the recipe can send tokens
+    let allowed = recipe
+        .inputs
+        .iter()
+        .map(|input| deps.api.canonical_address(&input.contract))
+        .collect::<StdResult<Vec<_>>>()?;
+    if !allowed.contains(&deps.api.canonical_address(&env.message.sender)?) {
+        return Err(StdError::generic_err(format!(
+            "Tokens of {} are not accepted",
+            env.message.sender
+        )));
+    }
+
+    let owner = wrapper.sender;
+    let owner_raw = deps.api.canonical_address(&owner)?;
+    let mut inputs = craft_escrow_read(&deps.storage, &owner_raw)
+        .may_load(recipe_id.as_bytes())?
+        .unwrap_or_default();
+    inputs.push(CraftInput {
+        contract: env.message.sender.clone(),
+        token_id: wrapper.token_id.clone(),
+    });
+
+    let counts = count_craft_inputs(deps, &recipe_id, &recipe, &inputs, &env.contract.address)?;
+    if check_craft_counts(&recipe_id, &recipe, &counts, false)? {
+        craft_escrow(&mut deps.storage, &owner_raw).remove(recipe_id.as_bytes());
+        return craft(deps, &env, owner, recipe_id, recipe, inputs);
+    }
+    craft_escrow(&mut deps.storage, &owner_raw).save(recipe_id.as_bytes(), &inputs)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "receive_craft_input"),
+            log("from", owner),
+            log("recipe_id", recipe_id),
+            log("token_id", wrapper.token_id),
+        ],
+        data: None,
+    })
+}
+
+/// Sends back the tokens held for a recipe the sender has not completed
+pub fn handle_cancel_craft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipe_id: String,
+) -> StdResult<HandleResponse> {
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    let inputs = craft_escrow_read(&deps.storage, &sender_raw)
+        .may_load(recipe_id.as_bytes())?
+        .ok_or_else(|| StdError::generic_err(format!("No tokens held for recipe {}", recipe_id)))?;
+    craft_escrow(&mut deps.storage, &sender_raw).remove(recipe_id.as_bytes());
+
+    let mut messages = vec![];
+    for input in inputs.iter() {
+        let transfer_msg = Cw721HandleMsg::TransferNft {
+            recipient: env.message.sender.clone(),
+            token_id: input.token_id.clone(),
+        };
+        messages.push(encode_msg_execute(to_binary(&transfer_msg)?, input.contract.clone(), vec![])?);
+    }
+    let returned: Vec<String> = inputs.into_iter().map(|input| input.token_id).collect();
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "cancel_craft"),
+            log("from", env.message.sender),
+            log("recipe_id", recipe_id),
+            log("returned", returned.join(",")),
+        ],
+        data: None,
+    })
+}
+
+fn load_recipe<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    recipe_id: &str,
+) -> StdResult<Recipe> {
+    recipes_read(&deps.storage)
+        .may_load(recipe_id.as_bytes())?
+        .ok_or_else(|| StdError::generic_err(format!("Unknown recipe {}", recipe_id)))
+}
+
+/// Counts the inputs by recipe input, checking each token is owned by `holder`
+fn count_craft_inputs<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    recipe_id: &str,
+    recipe: &Recipe,
+    inputs: &[CraftInput],
+    holder: &HumanAddr,
+) -> StdResult<Vec<u32>> {
+    let mut counts = vec![0u32; recipe.inputs.len()];
+    for (i, input) in inputs.iter().enumerate() {
+        if inputs[..i]
+            .iter()
+            .any(|other| other.contract == input.contract && other.token_id == input.token_id)
+        {
+            return Err(StdError::generic_err(format!("Token {} is used twice", input.token_id)));
+        }
+
+        let nft: AllNftInfoResponse = deps.querier.query(&WasmQuery::Smart {
+            contract_addr: input.contract.clone(),
+            msg: to_binary(&Cw721QueryMsg::AllNftInfo { token_id: input.token_id.clone() })?,
+        }.into())?;
+        if &nft.access.owner != holder {
+            return Err(StdError::unauthorized());
+        }
+        match recipe
+            .inputs
+            .iter()
+            .position(|r| r.contract == input.contract && r.rank == nft.info.rank)
+        {
+            Some(idx) => counts[idx] += 1,
+            None => {
+                return Err(StdError::generic_err(format!(
+                    "Token {} is not an input of recipe {}",
+                    input.token_id, recipe_id
+                )))
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// Returns whether all the inputs are there. Missing inputs are an error only if the recipe
+/// must be `complete`, more tokens than the recipe takes always are
+fn check_craft_counts(
+    recipe_id: &str,
+    recipe: &Recipe,
+    counts: &[u32],
+    complete: bool,
+) -> StdResult<bool> {
+    let mut done = true;
+    for (input, count) in recipe.inputs.iter().zip(counts) {
+        if *count > input.count || (complete && *count != input.count) {
+            return Err(StdError::generic_err(format!(
+                "Recipe {} takes {} tokens of rank {} from {}",
+                recipe_id, input.count, input.rank, input.contract
+            )));
+        }
+        done &= *count == input.count;
+    }
+    Ok(done)
+}
+
+/// Burns the inputs and mints the output to `owner`, in the same transaction
+fn craft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    owner: HumanAddr,
+    recipe_id: String,
+    recipe: Recipe,
+    inputs: Vec<CraftInput>,
+) -> StdResult<HandleResponse> {
+    let mut messages = vec![];
+    for input in inputs.iter() {
+        let burn_msg = Cw721HandleMsg::Burn {
+            token_id: input.token_id.clone(),
+        };
+        messages.push(encode_msg_execute(to_binary(&burn_msg)?, input.contract.clone(), vec![])?);
+    }
+    let mint_msg = TokenMsg::Mint {
+        owner: owner.clone(),
+        rank: recipe.output.rank.clone(),
+    };
+    messages.push(encode_msg_execute(to_binary(&mint_msg)?, recipe.output.contract, vec![])?);
+
+    let burned: Vec<String> = inputs.into_iter().map(|input| input.token_id).collect();
+    let owner_raw = deps.api.canonical_address(&owner)?;
+    append_history(&mut deps.storage, &owner_raw, &HistoryEntry {
+        height: env.block.height,
+        event: HistoryEvent::Craft {
+            recipe_id: recipe_id.clone(),
+            burned: burned.clone(),
+        },
+    })?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "craft"),
+            log("from", &owner),
+            log("recipe_id", recipe_id),
+            log("burned", burned.join(",")),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_update_merkle_root<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    merkle_root: Option<String>,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    // Ensure this is 32 bytes hex-encoded, and decode
+    match &merkle_root {
+        Some(root) => {
+            let root = hex::decode(root)
+                .map_err(|e| StdError::generic_err(format!("Error parsing merkle root: {}", e)))?;
+            if root.len() != 32 {
+                return Err(StdError::generic_err("Merkle root must be 64 characters"));
+            }
+            starter_merkle_root(&mut deps.storage).save(&Binary(root))?
+        }
+        None => starter_merkle_root(&mut deps.storage).remove(),
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "update_merkle_root"),
+            log("merkle_root", merkle_root.unwrap_or_default()),
+        ],
+        data: None,
+    })
+}
+
+/// Credits the referrer's claimable balance with their share of the pack price
+fn credit_referrer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    sender_raw: &CanonicalAddr,
+    referrer: &HumanAddr,
+    pack_price: Uint128,
+) -> StdResult<Uint128> {
+    let referrer_raw = deps.api.canonical_address(referrer)?;
+    if &referrer_raw == sender_raw {
+        return Err(StdError::generic_err("Cannot refer yourself"));
+    }
+
+    let amount = pack_price * query_state(deps)?.referral_rate;
+    referrals(&mut deps.storage).update(referrer_raw.as_slice(), |stats| {
+        let mut stats = stats.unwrap_or_default();
+        stats.referrals += 1;
+        stats.total_earned += amount;
+        stats.claimable += amount;
+        Ok(stats)
+    })?;
+    Ok(amount)
+}
+
+pub fn handle_claim_referral<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    // rewards are paid from pack proceeds, so they pause with purchases
+    assert_not_paused(&deps.storage, PauseScope::Purchases)?;
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    let mut stats = referrals_read(&deps.storage)
+        .may_load(sender_raw.as_slice())?
+        .unwrap_or_default();
+    if stats.claimable.is_zero() {
+        return Err(StdError::generic_err("No referral rewards to claim"));
+    }
+
+    let claim = deduct_tax(
+        deps,
+        Coin {
+            denom: query_state(deps)?.stable_denom,
+            amount: stats.claimable,
+        }
+    )?;
+    stats.claimable = Uint128::zero();
+    referrals(&mut deps.storage).save(sender_raw.as_slice(), &stats)?;
+
+    Ok(HandleResponse {
+        messages: vec![BankMsg::Send {
+            from_address: env.contract.address,
+            to_address: env.message.sender.clone(),
+            amount: vec![claim.clone()],
+        }.into()],
+        log: vec![
+            log("action", "claim_referral"),
+            log("to", env.message.sender),
+            log("amount", claim.amount),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_update_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pack_price: Option<Uint128>,
+    referral_rate: Option<Decimal>,
+    standard_rank: Option<String>,
+    premium_rank: Option<String>,
+) -> StdResult<HandleResponse> {
+    let mut info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    if let Some(pack_price) = pack_price {
+        info.pack_price = pack_price;
+    }
+    if let Some(referral_rate) = referral_rate {
+        info.referral_rate = validate_rate(referral_rate)?;
+    }
+    if let Some(standard_rank) = standard_rank {
+        info.standard_rank = standard_rank;
+    }
+    if let Some(premium_rank) = premium_rank {
+        info.premium_rank = premium_rank;
+    }
+    state(&mut deps.storage).save(&info)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "update_config"),
+            log("sender", env.message.sender),
+        ],
+        data: None,
+    })
+}
+
+fn validate_rate(rate: Decimal) -> StdResult<Decimal> {
+    if rate > Decimal::one() {
+        return Err(StdError::generic_err("Rate cannot be more than 100%"));
+    }
+    Ok(rate)
+}
+
+pub fn handle_deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    assert_not_paused(&deps.storage, PauseScope::Deposits)?;
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    let sender = deps.api.human_address(&sender_raw)?;
+
+    let info = query_state(deps)?;
+
+    let deposit_amount: Uint128 = env
+        .message
+        .sent_funds
+        .iter()
+        .find(|c| c.denom == info.stable_denom)
+        .map(|c| c.amount)
+        .unwrap_or_else(Uint128::zero);
+    
+    let contract = info.anchor_addr;
+    let (anchor_res, coin_deposit) = deposit_stable_msg(deps, deposit_amount)?;
+    assert_deposit_limits(deps, &sender, coin_deposit.amount, &info.stable_denom)?;
+
+    increase_deposit(&mut deps.storage, coin_deposit.amount)?;
+    append_history(&mut deps.storage, &sender_raw, &HistoryEntry {
+        height: env.block.height,
+        event: HistoryEvent::Deposit { amount: coin_deposit.amount },
+    })?;
+
+    // with a share token the position is transferable, otherwise it is tracked per user
+    let mut messages = vec![anchor_res];
+    match share_token_read(&deps.storage).may_load()? {
+        Some(token) => {
+            let mint_msg = Cw20HandleMsg::Mint {
+                recipient: sender.clone(),
+                amount: coin_deposit.amount,
+            };
+            messages.push(encode_msg_execute(to_binary(&mint_msg)?, token, vec![])?);
+        }
+        None => {
+            deposits(&mut deps.storage).update(sender_raw.as_slice(), |balance| {
+                Ok(balance.unwrap_or_default() + coin_deposit.amount)
+            })?;
+        }
+    }
+
+    let response = HandleResponse {
+        messages,
+        log: vec![
+            log("action", "deposit"),
+            log("from", &sender),
+            log("to", &contract),
+            log("deposit_amount", &coin_deposit.amount),
+        ],
+        data: None,
+    };
+    
+    Ok(response)
+}
+
+/// Returns an error if depositing `amount` breaks the deposit limits, saying how much room is left
+fn assert_deposit_limits<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    sender: &HumanAddr,
+    amount: Uint128,
+    denom: &str,
+) -> StdResult<()> {
+    let limits = deposit_limits_read(&deps.storage).may_load()?.unwrap_or_default();
+    if amount < limits.min_deposit {
+        return Err(StdError::generic_err(format!(
+            "Minimum deposit is {}{}",
+            limits.min_deposit, denom
+        )));
+    }
+
+    if let Some(cap) = limits.tvl_cap {
+        let room = (cap - query_total_deposit(deps)?).unwrap_or_else(|_| Uint128::zero());
+        if amount > room {
+            return Err(StdError::generic_err(format!(
+                "Deposit exceeds the TVL cap, {}{} left",
+                room, denom
+            )));
+        }
+    }
+
+    if let Some(cap) = limits.address_cap {
+        let room = (cap - query_position(deps, sender)?).unwrap_or_else(|_| Uint128::zero());
+        if amount > room {
+            return Err(StdError::generic_err(format!(
+                "Deposit exceeds the per-address cap, {}{} left",
+                room, denom
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Principal held by an address, either tracked here or as share tokens
+fn query_position<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<Uint128> {
+    let mut position = query_deposit(deps, address.clone())?;
+    if let Some(token) = share_token_read(&deps.storage).may_load()? {
+        let shares: BalanceResponse = deps.querier.query(&WasmQuery::Smart {
+            contract_addr: token,
+            msg: to_binary(&Cw20QueryMsg::Balance { address: address.clone() })?,
+        }.into())?;
+        position += shares.balance;
+    }
+    Ok(position)
+}
+
+pub fn handle_update_deposit_limits<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    limits: DepositLimits,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+    deposit_limits(&mut deps.storage).save(&limits)?;
+
+    let display = |cap: Option<Uint128>| cap.map(|c| c.to_string()).unwrap_or_else(|| "none".to_string());
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "update_deposit_limits"),
+            log("tvl_cap", display(limits.tvl_cap)),
+            log("address_cap", display(limits.address_cap)),
+            log("min_deposit", limits.min_deposit),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_withdraw<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Uint128,
+) -> StdResult<HandleResponse> {
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    let sender = deps.api.human_address(&sender_raw)?;
+
+    // only the sender's own principal can be withdrawn, this is never paused
+    deposits(&mut deps.storage).update(sender_raw.as_slice(), |balance| {
+        balance.unwrap_or_default() - amount
+    })?;
+
+    // wait in line if anchor cannot pay this out right now, or others are already waiting
+    let queue_empty = withdrawal_queue_read(&deps.storage)
+        .range(None, None, Order::Ascending)
+        .next()
+        .is_none();
+    if !queue_empty || anchor_liquidity(deps)? < amount {
+        let id = next_withdrawal_id(&mut deps.storage)?;
+        withdrawal_queue(&mut deps.storage).save(&id.to_be_bytes(), &QueuedWithdrawal {
+            owner: sender.clone(),
+            amount,
+            time: env.block.time,
+        })?;
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: vec![
+                log("action", "queue_withdrawal"),
+                log("from", &sender),
+                log("amount", amount),
+                log("id", id),
+            ],
+            data: None,
+        });
+    }
+
+    let (messages, coin_withdraw, aust_amount) = withdraw_msgs(deps, &env, &sender, amount)?;
+
+    let res = HandleResponse {
+        messages,
+        log: vec![
+            log("action", "withdraw"),
+            log("from", &sender),
+            log("amount", coin_withdraw.amount),
+            log("aust_amount", aust_amount),
+        ],
+        data: None,
+    };
+
+    Ok(res)
+}
+
+/// Stable coins anchor's money market holds and can pay out right away
+fn anchor_liquidity<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<Uint128> {
+    let info = query_state(deps)?;
+    Ok(deps.querier.query_balance(&info.anchor_addr, &info.stable_denom)?.amount)
+}
+
+/// Takes `amount` off the total principal and builds the messages that redeem it from
+/// Anchor and forward it to `recipient`. Returns them with the forwarded coin and the
+/// redeemed aUST amount
+fn withdraw_msgs<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    recipient: &HumanAddr,
+    amount: Uint128,
+) -> StdResult<(Vec<CosmosMsg>, Coin, Uint128)> {
+    let recipient_raw = deps.api.canonical_address(recipient)?;
+    reduce_deposit(&mut deps.storage, amount)?;
+    append_history(&mut deps.storage, &recipient_raw, &HistoryEntry {
+        height: env.block.height,
+        event: HistoryEvent::Withdraw { amount },
+    })?;
+
+    let (anchor_response, aust_amount) = redeem_stable_msg(deps, &env.block, amount)?;
+
+    // forward the redeemed stable coins to the recipient
+    let stable_denom = query_state(deps)?.stable_denom;
+    let coin_withdraw = deduct_tax(
+        deps,
+        Coin {
+            denom: stable_denom,
+            amount,
+        }
+    )?;
+    let bank_response: CosmosMsg = BankMsg::Send {
+        from_address: env.contract.address.clone(),
+        to_address: recipient.clone(),
+        amount: vec![coin_withdraw.clone()],
+    }.into();
+
+    Ok((vec![anchor_response, bank_response], coin_withdraw, aust_amount))
+}
+
+pub fn handle_process_queue<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    limit: Option<u32>,
+) -> StdResult<HandleResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let mut liquidity = anchor_liquidity(deps)?;
+
+    // strictly first-in first-out, stop at the first withdrawal that does not fit
+    let queued: StdResult<Vec<_>> = withdrawal_queue_read(&deps.storage)
+        .range(None, None, Order::Ascending)
+        .take(limit)
+        .collect();
+    let mut messages = vec![];
+    let mut processed = 0u64;
+    for (key, withdrawal) in queued? {
+        liquidity = match liquidity - withdrawal.amount {
+            Ok(left) => left,
+            Err(_) => break,
+        };
+        withdrawal_queue(&mut deps.storage).remove(&key);
+        let (msgs, _, _) = withdraw_msgs(deps, &env, &withdrawal.owner, withdrawal.amount)?;
+        messages.extend(msgs);
+        processed += 1;
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "process_queue"),
+            log("processed", processed),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_cancel_withdrawal<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    id: u64,
+) -> StdResult<HandleResponse> {
+    let withdrawal = withdrawal_queue_read(&deps.storage).load(&id.to_be_bytes())?;
+    if withdrawal.owner != env.message.sender {
+        return Err(StdError::unauthorized());
+    }
+    withdrawal_queue(&mut deps.storage).remove(&id.to_be_bytes());
+
+    let owner_raw = deps.api.canonical_address(&withdrawal.owner)?;
+    deposits(&mut deps.storage).update(owner_raw.as_slice(), |balance| {
+        Ok(balance.unwrap_or_default() + withdrawal.amount)
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "cancel_withdrawal"),
+            log("from", env.message.sender),
+            log("id", id),
+            log("amount", withdrawal.amount),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_register_share_token<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+    if share_token_read(&deps.storage).may_load()?.is_some() {
+        return Err(StdError::generic_err("Share token already registered"));
+    }
+
+    let minter: MinterResponse = deps.querier.query(&WasmQuery::Smart {
+        contract_addr: token.clone(),
+        msg: to_binary(&Cw20QueryMsg::Minter {})?,
+    }.into())?;
+    if minter.minter != env.contract.address {
+        return Err(StdError::generic_err("Share token must be minted by this contract"));
+    }
+    share_token(&mut deps.storage).save(&token)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "register_share_token"),
+            log("token", token),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    wrapper: Cw20ReceiveMsg,
+) -> StdResult<HandleResponse> {
+    let msg: Cw20HookMsg = match wrapper.msg {
+        Some(bin) => from_binary(&bin),
+        None => Err(StdError::parse_err("Cw20HookMsg", "no redeem message")),
+    }?;
+    match msg {
+        Cw20HookMsg::RedeemShares {} => {
+            handle_redeem_shares(deps, env, wrapper.sender, wrapper.amount)
+        }
+    }
+}
+
+/// Burns share tokens sent to this contract and returns the principal they stand for.
+/// Like WithdrawStable, this is never paused
+fn handle_redeem_shares<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    sender: HumanAddr,
+    amount: Uint128,
+) -> StdResult<HandleResponse> {
+    let token = share_token_read(&deps.storage)
+        .may_load()?
+        .ok_or_else(|| StdError::generic_err("No share token registered"))?;
+    if env.message.sender != token {
+        return Err(StdError::unauthorized());
+    }
+    // shares are not queued, the send reverts and can be retried once Anchor has liquidity
+    let burn_msg = encode_msg_execute(
+        to_binary(&Cw20HandleMsg::Burn { amount })?,
+        token,
+        vec![],
+    )?;
+    let (withdraw_msgs, coin_withdraw, aust_amount) = withdraw_msgs(deps, &env, &sender, amount)?;
+    let mut messages = vec![burn_msg];
+    messages.extend(withdraw_msgs);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "redeem_shares"),
+            log("from", &sender),
+            log("amount", coin_withdraw.amount),
+            log("aust_amount", aust_amount),
+        ],
+        data: None,
+    })
+}
+
+/// Builds the message that deposits `amount` uusd into Anchor, returns it
+/// together with the deposited coin (after tax)
+fn deposit_stable_msg<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    amount: Uint128,
+) -> StdResult<(CosmosMsg, Coin)> {
+    let info = query_state(deps)?;
+
+    // coin deposit minus tax
+    let coin_deposit = deduct_tax(
+        deps,
+        Coin {
+            denom: info.stable_denom,
+            amount,
+        }
+    )?;
+
+    // execute anchor's deposit stable contract
+    let deposit_msg = to_binary(&AnchorMsg::DepositStable{})?;
+    let anchor_res = encode_msg_execute(
+        deposit_msg,
+        info.anchor_addr,
+        vec![coin_deposit.clone()]
+    )?;
+
+    Ok((anchor_res, coin_deposit))
+}
+
+/// Builds the message that redeems `amount` uusd worth of aUST from Anchor at the
+/// exchange rate of the given block
+fn redeem_stable_msg<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    block: &BlockInfo,
+    amount: Uint128,
+) -> StdResult<(CosmosMsg, Uint128)> {
+    let anchor_contract = query_state(deps)?.anchor_addr;
+    let (exchange_rate, aterra_contract) = anchor_market(deps, block)?;
+
+    let contract_msg = to_binary(&AnchorMsg::RedeemStable{})?;
+    let aust_amount = amount * (Decimal256::one() / exchange_rate).into();
+
+    // create a send message
+    let msg = to_binary(&Cw20HandleMsg::Send{
+        amount: aust_amount,
+        contract: anchor_contract,
+        msg: Some(contract_msg)
+    })?;
+
+    let anchor_response = encode_msg_execute(
+        msg,
+        aterra_contract,
+        vec![]
+    )?;
+
+    Ok((anchor_response, aust_amount))
+}
+
+/// Queries anchor's live aUST exchange rate at the given block and reads the aUST contract address
+fn anchor_market<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    block: &BlockInfo,
+) -> StdResult<(Decimal256, HumanAddr)> {
+    let anchor_contract = query_state(deps)?.anchor_addr;
+
+    // the raw state only holds the rate of the last interest update, which lags behind
+    let epoch_state: EpochStateResponse = deps.querier.query(&WasmQuery::Smart {
+        contract_addr: anchor_contract.clone(),
+        msg: to_binary(&AnchorQueryMsg::EpochState { block_height: Some(block.height) })?,
+    }.into())?;
+
+    let config_bin: Binary = encode_raw_query(
+        deps,
+        Binary::from(to_length_prefixed(b"config")),
+        anchor_contract,
+    )?;
+    let config_response: ConfigResponse = from_binary(&config_bin)?;
+    let aterra_contract = deps.api.human_address(&config_response.aterra_contract)?;
+
+    Ok((epoch_state.exchange_rate, aterra_contract))
+}
+
+/// Returns the uusd value of the contract's aUST above the deposited principal
+fn query_pending_yield<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<Uint128> {
+    let (exchange_rate, aterra_contract) = anchor_market(deps, &env.block)?;
+    let balance: BalanceResponse = deps.querier.query(&WasmQuery::Smart {
+        contract_addr: aterra_contract,
+        msg: to_binary(&Cw20QueryMsg::Balance { address: env.contract.address.clone() })?,
+    }.into())?;
+
+    let value = balance.balance * exchange_rate.into();
+    let totals = treasury_read(&deps.storage).may_load()?.unwrap_or_default();
+    let principal = query_total_deposit(deps)? + totals.compounded + totals.pack_proceeds;
+    Ok((value - principal).unwrap_or_else(|_| Uint128::zero()))
+}
+
+pub fn handle_harvest<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    assert_not_paused(&deps.storage, PauseScope::Redeems)?;
+    let info = query_state(deps)?;
+
+    let pending = query_pending_yield(deps, &env)?;
+    if pending.is_zero() {
+        return Err(StdError::generic_err("No yield to harvest"));
+    }
+
+    // only the yield portion of aUST is redeemed, anchor deducts tax on the way out
+    let (redeem_msg, aust_amount) = redeem_stable_msg(deps, &env.block, pending)?;
+    let harvested = deduct_tax(
+        deps,
+        Coin {
+            denom: info.stable_denom.clone(),
+            amount: pending,
+        },
+    )?.amount;
+    let mut messages = vec![redeem_msg];
+
+    let split = yield_split_read(&deps.storage).load()?;
+    let share = |bps: u64| harvested.multiply_ratio(bps, 10_000u64);
+    let to_treasury = share(split.treasury_bps);
+    let to_stakers = share(split.stakers_bps);
+    let to_redeposit = share(split.redeposit_bps);
+    // rounding leftovers stay in the prize pool
+    let to_prize_pool = (((harvested - to_treasury)? - to_stakers)? - to_redeposit)?;
+
+    let mut totals = treasury_read(&deps.storage).may_load()?.unwrap_or_default();
+    for (recipient, amount) in &[(&split.treasury, to_treasury), (&split.stakers, to_stakers)] {
+        if let (Some(recipient), false) = (recipient, amount.is_zero()) {
+            let coin = deduct_tax(
+                deps,
+                Coin {
+                    denom: info.stable_denom.clone(),
+                    amount: *amount,
+                },
+            )?;
+            messages.push(BankMsg::Send {
+                from_address: env.contract.address.clone(),
+                to_address: recipient.clone(),
+                amount: vec![coin],
+            }.into());
+        }
+    }
+    if !to_redeposit.is_zero() {
+        let (anchor_res, coin_deposit) = deposit_stable_msg(deps, to_redeposit)?;
+        totals.compounded += coin_deposit.amount;
+        messages.push(anchor_res);
+    }
+    totals.prize_pool += to_prize_pool;
+    totals.total_harvested += harvested;
+    treasury(&mut deps.storage).save(&totals)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "harvest"),
+            log("yield", pending),
+            log("aust_amount", aust_amount),
+            log("prize_pool", to_prize_pool),
+            log("treasury", to_treasury),
+            log("stakers", to_stakers),
+            log("redeposit", to_redeposit),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_update_yield_split<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    split: YieldSplit,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    let total = split.prize_pool_bps + split.treasury_bps + split.stakers_bps + split.redeposit_bps;
+    if total != 10_000 {
+        return Err(StdError::generic_err("Yield split must add up to 10000 basis points"));
+    }
+    if split.treasury_bps > 0 && split.treasury.is_none() {
+        return Err(StdError::generic_err("Treasury share requires a treasury address"));
+    }
+    if split.stakers_bps > 0 && split.stakers.is_none() {
+        return Err(StdError::generic_err("Stakers share requires a stakers address"));
+    }
+    yield_split(&mut deps.storage).save(&split)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "update_yield_split"),
+            log("prize_pool_bps", split.prize_pool_bps),
+            log("treasury_bps", split.treasury_bps),
+            log("stakers_bps", split.stakers_bps),
+            log("redeposit_bps", split.redeposit_bps),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_update_contest_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    dispute_window: Duration,
+    multisig: Option<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+    contest_config(&mut deps.storage).save(&ContestConfig {
+        dispute_window,
+        multisig: multisig.clone(),
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "update_contest_config"),
+            log("dispute_window", dispute_window),
+            log("multisig", multisig.map(|m| m.to_string()).unwrap_or_default()),
+        ],
+        data: None,
+    })
+}
+
+/// Moves `new_total` of the prize pool into contest payouts, after releasing `old_total`
+fn reserve_prizes<S: Storage>(storage: &mut S, old_total: Uint128, new_total: Uint128) -> StdResult<()> {
+    let mut totals = treasury_read(storage).may_load()?.unwrap_or_default();
+    totals.prize_pool = ((totals.prize_pool + old_total) - new_total)
+        .map_err(|_| StdError::generic_err("Payouts exceed the prize pool"))?;
+    treasury(storage).save(&totals)
+}
+
+fn payouts_total(payouts: &[ContestPayout]) -> Uint128 {
+    payouts.iter().fold(Uint128::zero(), |total, payout| total + payout.amount)
+}
+
+pub fn handle_enter_contest<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contest_id: u64,
+    lineup: Vec<String>,
+) -> StdResult<HandleResponse> {
+    assert_not_paused(&deps.storage, PauseScope::Contests)?;
+    if lineup.is_empty() {
+        return Err(StdError::generic_err("Lineup is empty"));
+    }
+    if contest_results_read(&deps.storage).may_load(&contest_id.to_be_bytes())?.is_some() {
+        return Err(StdError::generic_err("Contest already settled"));
+    }
+    for (i, athlete_id) in lineup.iter().enumerate() {
+        if lineup[..i].contains(athlete_id) {
+            return Err(StdError::generic_err(format!("Athlete {} is picked twice", athlete_id)));
+        }
+        if token_addresses_read(&deps.storage).may_load(athlete_id.as_bytes())?.is_none() {
+            return Err(StdError::generic_err(format!("Unknown athlete {}", athlete_id)));
+        }
+    }
+    assert_lineup_active(&deps.storage, &lineup)?;
+
+    let sender_raw = deps.api.canonical_address(&env.message.sender)?;
+    contest_entries(&mut deps.storage, contest_id).save(sender_raw.as_slice(), &lineup)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "enter_contest"),
+            log("contest_id", contest_id),
+            log("from", env.message.sender),
+            log("lineup", lineup.join(",")),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_settle_contest<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contest_id: u64,
+    payouts: Vec<ContestPayout>,
+) -> StdResult<HandleResponse> {
+    assert_not_paused(&deps.storage, PauseScope::Contests)?;
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+    let config = contest_config_read(&deps.storage)
+        .may_load()?
+        .ok_or_else(|| StdError::generic_err("Dispute window is not configured"))?;
+    if contest_results_read(&deps.storage).may_load(&contest_id.to_be_bytes())?.is_some() {
+        return Err(StdError::generic_err("Contest already settled"));
+    }
+
+    let total = payouts_total(&payouts);
+    reserve_prizes(&mut deps.storage, Uint128::zero(), total)?;
+    let final_at = config.dispute_window.after(&env.block);
+    contest_results(&mut deps.storage).save(&contest_id.to_be_bytes(), &ContestResult {
+        payouts,
+        total,
+        final_at,
+        corrected: false,
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "settle_contest"),
+            log("contest_id", contest_id),
+            log("total", total),
+            log("final_at", final_at),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_correct_results<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contest_id: u64,
+    payouts: Vec<ContestPayout>,
+) -> StdResult<HandleResponse> {
+    let config = contest_config_read(&deps.storage).may_load()?;
+    match config.and_then(|c| c.multisig) {
+        Some(multisig) if multisig == env.message.sender => {}
+        _ => return Err(StdError::unauthorized()),
+    }
+
+    let mut result = contest_results_read(&deps.storage).load(&contest_id.to_be_bytes())?;
+    if result.final_at.is_expired(&env.block) {
+        return Err(StdError::generic_err("Dispute window has closed"));
+    }
+
+    let total = payouts_total(&payouts);
+    reserve_prizes(&mut deps.storage, result.total, total)?;
+    result.payouts = payouts;
+    result.total = total;
+    result.corrected = true;
+    contest_results(&mut deps.storage).save(&contest_id.to_be_bytes(), &result)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "correct_results"),
+            log("contest_id", contest_id),
+            log("total", total),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_claim_payout<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contest_id: u64,
+) -> StdResult<HandleResponse> {
+    // like WithdrawStable, claiming a settled payout is never paused
+    let result = contest_results_read(&deps.storage).load(&contest_id.to_be_bytes())?;
+    if !result.final_at.is_expired(&env.block) {
+        return Err(StdError::generic_err("Results are not final yet"));
+    }
+
+    let sender = env.message.sender.clone();
+    let amount = result
+        .payouts
+        .iter()
+        .filter(|payout| payout.address == sender)
+        .fold(Uint128::zero(), |total, payout| total + payout.amount);
+    if amount.is_zero() {
+        return Err(StdError::generic_err("No payout to claim"));
+    }
+    let sender_raw = deps.api.canonical_address(&sender)?;
+    contest_claims(&mut deps.storage, contest_id).update(sender_raw.as_slice(), |claimed| match claimed {
+        Some(_) => Err(StdError::generic_err("Payout already claimed")),
+        None => Ok(true),
+    })?;
+
+    let payout = deduct_tax(
+        deps,
+        Coin {
+            denom: query_state(deps)?.stable_denom,
+            amount,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![BankMsg::Send {
+            from_address: env.contract.address,
+            to_address: sender.clone(),
+            amount: vec![payout],
+        }.into()],
+        log: vec![
+            log("action", "claim_payout"),
+            log("contest_id", contest_id),
+            log("from", &sender),
+            log("amount", amount),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_pause<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    scope: PauseScope,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    let sender = env.message.sender;
+
+    // both the owner and the guardian can pull the circuit breaker
+    if sender != info.owner && Some(&sender) != info.guardian.as_ref() {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut paused = pause_state_read(&deps.storage).may_load()?.unwrap_or_default();
+    paused.set(&scope, true);
+    pause_state(&mut deps.storage).save(&paused)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "pause"),
+            log("sender", &sender),
+            log("scope", format!("{:?}", scope)),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_unpause<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    scope: PauseScope,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    let sender = env.message.sender;
+
+    // only the owner can resume operations
+    if sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut paused = pause_state_read(&deps.storage).may_load()?.unwrap_or_default();
+    paused.set(&scope, false);
+    pause_state(&mut deps.storage).save(&paused)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "unpause"),
+            log("sender", &sender),
+            log("scope", format!("{:?}", scope)),
+        ],
+        data: None,
+    })
+}
+
+pub fn handle_add_token<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    tokens: Vec<TokenData>,
+) -> StdResult<HandleResponse> {
+    let info = query_state(deps)?;
+    if env.message.sender != info.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    for token in tokens.iter() {
+        token_addresses(&mut deps.storage).update(token.athlete_id.as_bytes(), |old| match old {
+            Some(_) => Err(StdError::generic_err("athlete_id already claimed")),
+            None => Ok(token.contract_addr.clone()),
+        })?;
+
+        increment_contract_count(&mut deps.storage)?;
+    }
+
+    let response = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "add_token"),
+        ],
+        data: None,
+    };
+    
+    Ok(response)
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::State {} => to_binary(&query_state(deps)?),
+        QueryMsg::TotalDeposit {} => to_binary(&query_total_deposit(deps)?),
+        QueryMsg::TokenContract {
+            athlete_id
+        } => to_binary(&query_token_address(deps, athlete_id)?),
+        QueryMsg::IsTokenMintable {
+            athlete_id
+        } => to_binary(&query_token_mintable(deps, athlete_id, query_state(deps)?.standard_rank)?),
+        QueryMsg::ContractCount {} => to_binary(&query_contract_count(deps)?),
+        QueryMsg::PauseStatus {} => to_binary(&query_pause_status(deps)?),
+        QueryMsg::Deposit {
+            address
+        } => to_binary(&query_deposit(deps, address)?),
+        QueryMsg::UserHistory {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_user_history(deps, address, start_after, limit)?),
+        QueryMsg::ReferralStats {
+            address
+        } => to_binary(&query_referral_stats(deps, address)?),
+        QueryMsg::StarterPack {
+            address
+        } => to_binary(&query_starter_pack(deps, address)?),
+        QueryMsg::SaleStatus {
+            block_height,
+            block_time,
+        } => to_binary(&query_sale_status(deps, block_height, block_time)?),
+        QueryMsg::Retirement { athlete_id } => to_binary(&RetirementResponse {
+            retired: is_retired(&deps.storage, &athlete_id)?,
+        }),
+        QueryMsg::RetirementReserve {} => to_binary(&query_retirement_reserve(deps)?),
+        QueryMsg::YieldSplit {} => to_binary(&query_yield_split(deps)?),
+        QueryMsg::ContestConfig {} => to_binary(&contest_config_read(&deps.storage).load()?),
+        QueryMsg::ContestResult { contest_id } => to_binary(
+            &contest_results_read(&deps.storage).load(&contest_id.to_be_bytes())?,
+        ),
+        QueryMsg::ContestEntry { contest_id, address } => {
+            to_binary(&query_contest_entry(deps, contest_id, address)?)
+        }
+        QueryMsg::DepositLimits {} => to_binary(
+            &deposit_limits_read(&deps.storage).may_load()?.unwrap_or_default(),
+        ),
+        QueryMsg::WithdrawalQueue {
+            start_after,
+            limit,
+        } => to_binary(&query_withdrawal_queue(deps, start_after, limit)?),
+        QueryMsg::ShareToken {} => to_binary(&ShareTokenResponse {
+            token: share_token_read(&deps.storage).may_load()?,
+        }),
+        QueryMsg::CurrentPrice {
+            pack_type,
+            block_height,
+            block_time,
+        } => to_binary(&query_current_price(deps, pack_type, block_height, block_time)?),
+        QueryMsg::Recipe { recipe_id } => {
+            to_binary(&recipes_read(&deps.storage).load(recipe_id.as_bytes())?)
+        }
+        QueryMsg::Recipes { start_after, limit } => {
+            to_binary(&query_recipes(deps, start_after, limit)?)
+        }
+    }
+}
+
+fn query_state<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<State> {
+    state_read(&deps.storage).load()
+}
+
+fn query_total_deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<Uint128> {
+    load_total_deposit(&deps.storage)
+}
+
+fn query_pause_status<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<PauseState> {
+    Ok(pause_state_read(&deps.storage).may_load()?.unwrap_or_default())
+}
+
+fn query_deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<Uint128> {
+    let address_raw = deps.api.canonical_address(&address)?;
+    Ok(deposits_read(&deps.storage).may_load(address_raw.as_slice())?.unwrap_or_default())
+}
+
+fn query_referral_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<ReferralStatsResponse> {
+    let address_raw = deps.api.canonical_address(&address)?;
+    let stats = referrals_read(&deps.storage)
+        .may_load(address_raw.as_slice())?
+        .unwrap_or_default();
+    Ok(ReferralStatsResponse {
+        referrals: stats.referrals,
+        total_earned: stats.total_earned,
+        claimable: stats.claimable,
+    })
+}
+
+fn query_starter_pack<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<StarterPackResponse> {
+    let address_raw = deps.api.canonical_address(&address)?;
+    let claimed = starter_claims_read(&deps.storage)
+        .may_load(address_raw.as_slice())?
+        .unwrap_or_default();
+    let merkle_root = starter_merkle_root_read(&deps.storage)
+        .may_load()?
+        .map(|root| hex::encode(root.as_slice()));
+    Ok(StarterPackResponse { claimed, merkle_root })
+}
+
+fn query_yield_split<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<YieldSplitResponse> {
+    let split = yield_split_read(&deps.storage).may_load()?.unwrap_or_default();
+    let Treasury {
+        prize_pool,
+        compounded,
+        total_harvested,
+        pack_proceeds,
+    } = treasury_read(&deps.storage).may_load()?.unwrap_or_default();
+    Ok(YieldSplitResponse {
+        split,
+        prize_pool,
+        compounded,
+        total_harvested,
+        pack_proceeds,
+    })
+}
+
+fn query_retirement_reserve<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<RetirementReserveResponse> {
+    let reserve = retirement_reserve_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_else(Uint128::zero);
+    let payouts: StdResult<Vec<RankPayout>> = retirement_payouts_read(&deps.storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| {
+            let (rank, amount) = item?;
+            Ok(RankPayout {
+                rank: String::from_utf8(rank)
+                    .map_err(|_| StdError::invalid_utf8("parsing payout rank"))?,
+                amount,
+            })
+        })
+        .collect();
+    Ok(RetirementReserveResponse {
+        reserve,
+        payouts: payouts?,
+    })
+}
+
+fn query_sale_status<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    block_height: u64,
+    block_time: u64,
+) -> StdResult<SaleStatusResponse> {
+    let current = sale_read(&deps.storage).may_load()?;
+    let block = BlockInfo {
+        height: block_height,
+        time: block_time,
+        chain_id: String::new(),
+    };
+    Ok(SaleStatusResponse {
+        phase: current.as_ref().map_or(SalePhase::Unrestricted, |s| s.phase(&block)),
+        remaining: current.as_ref().map(|s| s.remaining()),
+        sale: current,
+    })
+}
+
+fn query_current_price<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pack_type: PackType,
+    block_height: u64,
+    block_time: u64,
+) -> StdResult<Uint128> {
+    match pack_type {
+        PackType::Standard => Ok(query_state(deps)?.pack_price),
+        PackType::Premium => {
+            let auction = dutch_auction_read(&deps.storage)
+                .may_load()?
+                .ok_or_else(|| StdError::generic_err("Premium packs are not for sale"))?;
+            auction.price_at(&BlockInfo {
+                height: block_height,
+                time: block_time,
+                chain_id: String::new(),
+            })
+        }
+    }
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+fn query_user_history<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<UserHistoryResponse> {
+    let address_raw = deps.api.canonical_address(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let history: StdResult<Vec<_>> = history_read(&deps.storage, &address_raw)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, entry) = item?;
+            Ok(HistoryItem {
+                id: parse_id(&key)?,
+                height: entry.height,
+                event: entry.event,
+            })
+        })
+        .collect();
+    Ok(UserHistoryResponse { history: history? })
+}
+
+fn query_withdrawal_queue<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<WithdrawalQueueResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let withdrawals: StdResult<Vec<_>> = withdrawal_queue_read(&deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, withdrawal) = item?;
+            Ok(WithdrawalItem {
+                id: parse_id(&key)?,
+                owner: withdrawal.owner,
+                amount: withdrawal.amount,
+                time: withdrawal.time,
+            })
+        })
+        .collect();
+    Ok(WithdrawalQueueResponse { withdrawals: withdrawals? })
+}
+
+fn query_recipes<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<RecipesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start_string(start_after);
+
+    let recipes: StdResult<Vec<_>> = recipes_read(&deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, recipe) = item?;
+            Ok(RecipeItem {
+                recipe_id: String::from_utf8_lossy(&key).to_string(),
+                recipe,
+            })
+        })
+        .collect();
+    Ok(RecipesResponse { recipes: recipes? })
+}
+
+fn query_contract_count<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<ContractCountResponse> {
+    let count = get_contract_count(&deps.storage)?;
+    Ok(ContractCountResponse { count })
+}
+
+fn query_contest_entry<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    contest_id: u64,
+    address: HumanAddr,
+) -> StdResult<ContestEntryResponse> {
+    let address_raw = deps.api.canonical_address(&address)?;
+    let lineup = contest_entries_read(&deps.storage, contest_id).load(address_raw.as_slice())?;
+    Ok(ContestEntryResponse { lineup })
+}
+
+fn query_token_address<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    athlete_id: String
+) -> StdResult<HumanAddr> {
+    token_addresses_read(&deps.storage).load(athlete_id.as_bytes())
+}
+
+fn query_token_mintable<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    athlete_id: String,
+    rank: String,
+) -> StdResult<bool> {
+    let token_address = query_token_address(deps, athlete_id)?;
+
+    let msg = QueryMintMsg::IsMintable { rank };
+    let wasm = WasmQuery::Smart {
+        contract_addr: token_address,
+        msg: to_binary(&msg)?,
+    };
+    let is_mintable: bool = deps.querier.query(&wasm.into())?;
+
+    Ok(is_mintable)
+}
+
+/// Picks the athletes of a new pack, skipping retired athletes and those that cannot be
+/// minted. Every registered athlete is checked at most once
 fn generate_pack<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
+    pack_type: PackType,
 ) -> StdResult<Vec<String>> {
-    let contract_info = query_state(&deps).unwrap();
-    let pack_len = contract_info.pack_len;
-    let contract_count = query_contract_count(&deps).unwrap().count;
-   
-    let mut pack: Vec<String> = Vec::new();
-    //let mut roll = rand::thread_rng();
-    //let rolls = Uniform::from(0..5);
-    //let rolls = Uniform::from(0..contract_count);
-    let mut mintable_tokens = Uint128::from(0 as u128);
-
-    while mintable_tokens < pack_len {
-        //let pull = rolls.sample(&mut roll) as u8;
-        let pull = 1;
-        
-        if query_token_mintable(&deps, pull.to_string()).unwrap(){    
-            // let token_data = TokenData {
-            //     athlete_id: pull.to_string(),
-            //     contract_addr: query_token_address(&deps, pull.clone().to_string()).unwrap()
-            // };
-            pack.push(pull.to_string());
-            mintable_tokens += Uint128::from(1 as u128);
-        }
-    }
-
-    Ok(pack)
-}
\ No newline at end of file
+    let info = query_state(deps)?;
+    let pack_len = info.pack_len.u128() as usize;
+    let mut ranks = vec![info.standard_rank];
+    if pack_type == PackType::Premium {
+        ranks.push(info.premium_rank);
+    }
+
+    let athletes: StdResult<Vec<String>> = token_addresses_read(&deps.storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| item.map(|(key, _)| String::from_utf8_lossy(&key).to_string()))
+        .collect();
+    'athletes: for athlete_id in athletes? {
+        if is_retired(&deps.storage, &athlete_id)? {
+            continue;
+        }
+        for rank in ranks.iter() {
+            if !query_token_mintable(deps, athlete_id.clone(), rank.clone())? {
+                continue 'athletes;
+            }
+        }
+        // TODO: pull random athletes using Terrand, until then the whole pack is the first one
+        return Ok(vec![athlete_id; pack_len]);
+    }
+
+    Err(StdError::generic_err("No athlete can be minted"))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::{coins, StdError};
+    use cw0::Expiration;
+
+    use super::*;
+    use crate::mock_querier::{mock_dependencies, ANCHOR_ADDR, MOCK_CONTRACT_ADDR};
+    use crate::state::{RecipeInput, RecipeOutput, TOTAL_DEPOSIT_KEY};
+    use cosmwasm_storage::{singleton, singleton_read};
+
+    const OWNER: &str = "creator";
+    const GUARDIAN: &str = "guardian";
+    const ATHLETE: &str = "athlete1";
+    const PACK_PRICE: Uint128 = Uint128(1000);
+
+    fn setup_contract<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>) {
+        let msg = InitMsg {
+            stable_denom: "uusd".to_string(),
+            anchor_addr: ANCHOR_ADDR.into(),
+            terrand_addr: "terrand".into(),
+            tokens: Some(vec![TokenData {
+                athlete_id: "1".to_string(),
+                contract_addr: ATHLETE.into(),
+            }]),
+            pack_len: Uint128(2),
+            guardian: Some(GUARDIAN.into()),
+            pack_price: PACK_PRICE,
+            referral_rate: Decimal::percent(10),
+            standard_rank: "B".to_string(),
+            premium_rank: "G".to_string(),
+        };
+        let env = mock_env(OWNER, &[]);
+        let res = init(deps, env, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let info = query_state(&deps).unwrap();
+        assert_eq!(OWNER, info.owner.as_str());
+        assert_eq!(Some(HumanAddr::from(GUARDIAN)), info.guardian);
+        assert_eq!(PauseState::default(), query_pause_status(&deps).unwrap());
+        assert_eq!(Uint128::zero(), query_total_deposit(&deps).unwrap());
+    }
+
+    #[test]
+    fn deposit_tracks_principal() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let env = mock_env("depositor", &coins(1000, "uusd"));
+        let res = handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap();
+        assert_eq!(1, res.messages.len());
+
+        let principal = query_deposit(&deps, "depositor".into()).unwrap();
+        assert_eq!(Uint128(1000), principal);
+        assert_eq!(Uint128(1000), query_total_deposit(&deps).unwrap());
+
+        // cannot withdraw more than the deposited principal
+        let env = mock_env("depositor", &[]);
+        let msg = HandleMsg::WithdrawStable { amount: Uint128(1001) };
+        handle(&mut deps, env, msg).unwrap_err();
+
+        // and others cannot withdraw it at all
+        let env = mock_env("random", &[]);
+        let msg = HandleMsg::WithdrawStable { amount: Uint128(1) };
+        handle(&mut deps, env, msg).unwrap_err();
+    }
+
+    #[test]
+    fn pausing_and_unpausing() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // random cannot pause
+        let env = mock_env("random", &[]);
+        let msg = HandleMsg::Pause { scope: PauseScope::Deposits };
+        let err = handle(&mut deps, env, msg.clone()).unwrap_err();
+        match err {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // guardian can pause deposits
+        let env = mock_env(GUARDIAN, &[]);
+        handle(&mut deps, env, msg).unwrap();
+        let paused = query_pause_status(&deps).unwrap();
+        assert_eq!(
+            paused,
+            PauseState {
+                purchases: false,
+                deposits: true,
+                redeems: false,
+                contests: false,
+            }
+        );
+
+        let env = mock_env("depositor", &coins(1000, "uusd"));
+        let err = handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap_err();
+        match err {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Deposits are paused"),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // guardian cannot unpause
+        let env = mock_env(GUARDIAN, &[]);
+        let msg = HandleMsg::Unpause { scope: PauseScope::Deposits };
+        let err = handle(&mut deps, env, msg.clone()).unwrap_err();
+        match err {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // owner can
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, msg).unwrap();
+        let env = mock_env("depositor", &coins(1000, "uusd"));
+        handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap();
+
+        // pause everything, the other scopes are rejected
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, HandleMsg::Pause { scope: PauseScope::All }).unwrap();
+        assert!(query_pause_status(&deps).unwrap().is_paused(&PauseScope::All));
+
+        let env = mock_env("buyer", &[]);
+        let err = handle(&mut deps, env, HandleMsg::PurchasePack { referrer: None, pack_type: None }).unwrap_err();
+        match err {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Purchases are paused"),
+            e => panic!("unexpected error: {}", e),
+        }
+        let assert_paused = |res: StdResult<HandleResponse>, scope: &str| match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg, format!("{} are paused", scope)),
+            e => panic!("unexpected error: {}", e),
+        };
+        let msg = HandleMsg::RedeemRetired {
+            athlete_id: "1".into(),
+            token_id: "LBJB1".into(),
+            redeem_for: RedeemOption::Payout,
+        };
+        assert_paused(handle(&mut deps, mock_env("holder", &[]), msg), "Redeems");
+        assert_paused(handle(&mut deps, mock_env("random", &[]), HandleMsg::Harvest {}), "Redeems");
+        let msg = HandleMsg::Craft { recipe_id: "captain".into(), inputs: vec![] };
+        assert_paused(handle(&mut deps, mock_env("holder", &[]), msg), "Purchases");
+        let msg = HandleMsg::ReceiveNft(Cw721ReceiveMsg {
+            sender: "holder".into(),
+            token_id: "LBJS1".into(),
+            msg: Some(to_binary(&Cw721HookMsg::Craft { recipe_id: "captain".into() }).unwrap()),
+        });
+        assert_paused(handle(&mut deps, mock_env(ATHLETE, &[]), msg), "Purchases");
+        assert_paused(handle(&mut deps, mock_env("friend", &[]), HandleMsg::ClaimReferral {}), "Purchases");
+        let msg = HandleMsg::EnterContest { contest_id: 1, lineup: vec!["1".into()] };
+        assert_paused(handle(&mut deps, mock_env("holder", &[]), msg), "Contests");
+
+        // withdrawing principal is not blocked by the pause
+        let env = mock_env("depositor", &[]);
+        let msg = HandleMsg::WithdrawStable { amount: Uint128(10) };
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(Uint128(990), query_deposit(&deps, "depositor".into()).unwrap());
+    }
+
+    #[test]
+    fn user_history() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let mut env = mock_env("depositor", &coins(1000, "uusd"));
+        handle(&mut deps, env.clone(), HandleMsg::DepositStable {}).unwrap();
+        env.block.height += 10;
+        env.message.sent_funds = coins(500, "uusd");
+        handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap();
+
+        // nothing recorded for other users
+        let res = query_user_history(&deps, "random".into(), None, None).unwrap();
+        assert_eq!(0, res.history.len());
+        assert_eq!(7, parse_id(&7u64.to_be_bytes()).unwrap());
+        parse_id(&[0, 7]).unwrap_err();
+
+        let res = query_user_history(&deps, "depositor".into(), None, Some(1)).unwrap();
+        assert_eq!(
+            res.history,
+            vec![HistoryItem {
+                id: 1,
+                height: 12345,
+                event: HistoryEvent::Deposit { amount: Uint128(1000) },
+            }]
+        );
+        let res = query_user_history(&deps, "depositor".into(), Some(1), None).unwrap();
+        assert_eq!(
+            res.history,
+            vec![HistoryItem {
+                id: 2,
+                height: 12355,
+                event: HistoryEvent::Deposit { amount: Uint128(500) },
+            }]
+        );
+    }
+
+    #[test]
+    fn purchase_with_referrer() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // must pay the pack price
+        let env = mock_env("buyer", &coins(999, "uusd"));
+        let msg = HandleMsg::PurchasePack { referrer: None, pack_type: None };
+        let err = handle(&mut deps, env, msg).unwrap_err();
+        match err {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Pack price is 1000uusd"),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // cannot refer yourself
+        let env = mock_env("buyer", &coins(1000, "uusd"));
+        let msg = HandleMsg::PurchasePack { referrer: Some("buyer".into()), pack_type: None };
+        handle(&mut deps, env, msg).unwrap_err();
+
+        // 10% goes to the referrer, the rest is deposited to anchor before minting
+        let env = mock_env("buyer", &coins(1000, "uusd"));
+        let msg = HandleMsg::PurchasePack { referrer: Some("friend".into()), pack_type: None };
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            res.messages[0],
+            encode_msg_execute(
+                to_binary(&AnchorMsg::DepositStable {}).unwrap(),
+                ANCHOR_ADDR.into(),
+                coins(900, "uusd"),
+            )
+            .unwrap()
+        );
+        // both pulls are of the same athlete, so they are minted in one message
+        let mint = TokenMint {
+            owner: "buyer".into(),
+            rank: "B".to_string(),
+        };
+        let mint_msg = TokenMsg::BatchMint {
+            mints: vec![mint.clone(), mint],
+        };
+        assert_eq!(
+            res.messages[1],
+            encode_msg_execute(to_binary(&mint_msg).unwrap(), ATHLETE.into(), vec![]).unwrap()
+        );
+        // the proceeds are not depositor principal
+        assert_eq!(Uint128::zero(), query_total_deposit(&deps).unwrap());
+        assert_eq!(Uint128(900), query_yield_split(&deps).unwrap().pack_proceeds);
+
+        let stats = query_referral_stats(&deps, "friend".into()).unwrap();
+        assert_eq!(
+            stats,
+            ReferralStatsResponse {
+                referrals: 1,
+                total_earned: Uint128(100),
+                claimable: Uint128(100),
+            }
+        );
+
+        // the pack shows up in the buyer's history
+        let history = query_user_history(&deps, "buyer".into(), None, None).unwrap();
+        assert_eq!(
+            history.history[0].event,
+            HistoryEvent::PurchasePack {
+                athletes: vec![
+                    PulledAthlete {
+                        athlete_id: "1".to_string(),
+                        contract_addr: ATHLETE.into(),
+                        rank: "B".to_string(),
+                    };
+                    2
+                ],
+            }
+        );
+
+        // overpaying is refunded
+        let env = mock_env("buyer", &coins(1500, "uusd"));
+        let msg = HandleMsg::PurchasePack { referrer: Some("friend".into()), pack_type: None };
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(
+            res.messages[1],
+            BankMsg::Send {
+                from_address: MOCK_CONTRACT_ADDR.into(),
+                to_address: "buyer".into(),
+                amount: coins(500, "uusd"),
+            }
+            .into()
+        );
+
+        // friend claims the rewards
+        let env = mock_env("friend", &[]);
+        let res = handle(&mut deps, env, HandleMsg::ClaimReferral {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![BankMsg::Send {
+                from_address: MOCK_CONTRACT_ADDR.into(),
+                to_address: "friend".into(),
+                amount: coins(200, "uusd"),
+            }
+            .into()]
+        );
+        let stats = query_referral_stats(&deps, "friend".into()).unwrap();
+        assert_eq!(Uint128(0), stats.claimable);
+        assert_eq!(Uint128(200), stats.total_earned);
+
+        // nothing left to claim
+        let env = mock_env("friend", &[]);
+        handle(&mut deps, env, HandleMsg::ClaimReferral {}).unwrap_err();
+    }
+
+    #[test]
+    fn update_config() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let msg = HandleMsg::UpdateConfig {
+            pack_price: Some(Uint128(2000)),
+            referral_rate: Some(Decimal::percent(5)),
+            standard_rank: None,
+            premium_rank: None,
+        };
+        let env = mock_env("random", &[]);
+        let err = handle(&mut deps, env, msg.clone()).unwrap_err();
+        match err {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, msg).unwrap();
+        let info = query_state(&deps).unwrap();
+        assert_eq!(Uint128(2000), info.pack_price);
+        assert_eq!(Decimal::percent(5), info.referral_rate);
+
+        // rate cannot be over 100%
+        let msg = HandleMsg::UpdateConfig {
+            pack_price: None,
+            referral_rate: Some(Decimal::percent(101)),
+            standard_rank: None,
+            premium_rank: None,
+        };
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, msg).unwrap_err();
+
+        // packs are minted at the configured ranks of the athlete contracts
+        let msg = HandleMsg::UpdateConfig {
+            pack_price: None,
+            referral_rate: None,
+            standard_rank: Some("common".to_string()),
+            premium_rank: Some("legend".to_string()),
+        };
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, msg).unwrap();
+        let env = mock_env("buyer", &coins(2000, "uusd"));
+        let msg = HandleMsg::PurchasePack { referrer: None, pack_type: None };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let mint = TokenMint {
+            owner: "buyer".into(),
+            rank: "common".to_string(),
+        };
+        let mint_msg = TokenMsg::BatchMint {
+            mints: vec![mint.clone(), mint],
+        };
+        assert_eq!(
+            res.messages[1],
+            encode_msg_execute(to_binary(&mint_msg).unwrap(), ATHLETE.into(), vec![]).unwrap()
+        );
+    }
+
+    #[test]
+    fn claim_starter_pack() {
+        use sha2::{Digest, Sha256};
+
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // anyone can claim a single pack while no allowlist is set
+        let env = mock_env("newbie", &[]);
+        let msg = HandleMsg::ClaimStarterPack { proof: vec![] };
+        let res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_eq!(1, res.messages.len());
+        let env = mock_env("newbie", &[]);
+        let err = handle(&mut deps, env, msg).unwrap_err();
+        match err {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Starter pack already claimed")
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // allowlist of two addresses
+        let leaf_a = Sha256::digest(b"alice").to_vec();
+        let leaf_b = Sha256::digest(b"bob").to_vec();
+        let mut hasher = Sha256::new();
+        if leaf_a <= leaf_b {
+            hasher.input(&leaf_a);
+            hasher.input(&leaf_b);
+        } else {
+            hasher.input(&leaf_b);
+            hasher.input(&leaf_a);
+        }
+        let root = hex::encode(hasher.result());
+
+        // only the owner can set the root, and it must be a valid hash
+        let msg = HandleMsg::UpdateMerkleRoot { merkle_root: Some(root.clone()) };
+        let env = mock_env("random", &[]);
+        handle(&mut deps, env, msg.clone()).unwrap_err();
+        let env = mock_env(OWNER, &[]);
+        let bad = HandleMsg::UpdateMerkleRoot { merkle_root: Some("1234".to_string()) };
+        match handle(&mut deps, env.clone(), bad).unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Merkle root must be 64 characters")
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+        handle(&mut deps, env, msg).unwrap();
+
+        // carol is not on the list
+        let env = mock_env("carol", &[]);
+        let msg = HandleMsg::ClaimStarterPack { proof: vec![hex::encode(&leaf_b)] };
+        let err = handle(&mut deps, env, msg).unwrap_err();
+        match err {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Invalid merkle proof"),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // alice is
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::ClaimStarterPack { proof: vec![hex::encode(&leaf_b)] };
+        handle(&mut deps, env, msg).unwrap();
+
+        let res = query_starter_pack(&deps, "alice".into()).unwrap();
+        assert_eq!(
+            res,
+            StarterPackResponse {
+                claimed: true,
+                merkle_root: Some(root),
+            }
+        );
+        let res = query_starter_pack(&deps, "bob".into()).unwrap();
+        assert!(!res.claimed);
+    }
+
+    #[test]
+    fn scheduled_sale() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // no sale, packs can be bought at any time
+        let res = query_sale_status(&deps, 12345, 0).unwrap();
+        assert_eq!(SalePhase::Unrestricted, res.phase);
+        assert_eq!(None, res.remaining);
+
+        let sale_msg = SaleMsg {
+            start: Expiration::AtHeight(12400),
+            end: Expiration::AtHeight(12600),
+            presale_end: Some(Expiration::AtHeight(12500)),
+            total_supply: 3,
+            max_per_address: 2,
+            presale_whitelist: vec!["early".into()],
+        };
+
+        // only the owner can schedule a sale
+        let msg = HandleMsg::SetSale { sale: Some(sale_msg.clone()) };
+        let env = mock_env("random", &[]);
+        handle(&mut deps, env, msg.clone()).unwrap_err();
+
+        // and it must end after it starts
+        let bad = HandleMsg::SetSale {
+            sale: Some(SaleMsg {
+                end: Expiration::AtHeight(12400),
+                ..sale_msg.clone()
+            }),
+        };
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, bad).unwrap_err();
+
+        // heights and times cannot be mixed
+        let mixed = |start: Expiration, end: Expiration, presale_end: Option<Expiration>| {
+            HandleMsg::SetSale {
+                sale: Some(SaleMsg {
+                    start,
+                    end,
+                    presale_end,
+                    ..sale_msg.clone()
+                }),
+            }
+        };
+        let assert_mixed = |res: StdResult<HandleResponse>| match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Sale must start and end both at a height or both at a time")
+            }
+            e => panic!("unexpected error: {}", e),
+        };
+        let msg_mixed = mixed(Expiration::AtHeight(12400), Expiration::AtTime(1_700_000_000), None);
+        assert_mixed(handle(&mut deps, mock_env(OWNER, &[]), msg_mixed));
+        let msg_mixed = mixed(
+            Expiration::AtHeight(12400),
+            Expiration::AtHeight(12600),
+            Some(Expiration::AtTime(1_700_000_000)),
+        );
+        assert_mixed(handle(&mut deps, mock_env(OWNER, &[]), msg_mixed));
+
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, msg).unwrap();
+
+        let buy = |deps: &mut Extern<_, _, _>, buyer: &str, height: u64| {
+            let mut env = mock_env(buyer, &coins(1000, "uusd"));
+            env.block.height = height;
+            handle(deps, env, HandleMsg::PurchasePack { referrer: None, pack_type: None })
+        };
+        let assert_err = |res: StdResult<HandleResponse>, expected: &str| match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), expected),
+            e => panic!("unexpected error: {}", e),
+        };
+
+        // too early
+        assert_err(buy(&mut deps, "early", 12399), "Sale has not started");
+
+        // presale is whitelist only
+        assert_eq!(SalePhase::Presale, query_sale_status(&deps, 12400, 0).unwrap().phase);
+        assert_err(
+            buy(&mut deps, "late", 12400),
+            "Only whitelisted addresses can buy during the presale",
+        );
+        buy(&mut deps, "early", 12400).unwrap();
+        buy(&mut deps, "early", 12401).unwrap();
+        assert_err(buy(&mut deps, "early", 12402), "Cannot buy more than 2 packs in this sale");
+
+        // public sale until sold out
+        assert_eq!(SalePhase::Public, query_sale_status(&deps, 12500, 0).unwrap().phase);
+        buy(&mut deps, "late", 12500).unwrap();
+        let res = query_sale_status(&deps, 12500, 0).unwrap();
+        assert_eq!(SalePhase::SoldOut, res.phase);
+        assert_eq!(Some(0), res.remaining);
+        assert_err(buy(&mut deps, "other", 12501), "Sale is sold out");
+
+        // a new sale resets the limits, and rejects purchases once it ended
+        let msg = HandleMsg::SetSale {
+            sale: Some(SaleMsg {
+                presale_end: None,
+                presale_whitelist: vec![],
+                ..sale_msg
+            }),
+        };
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, msg).unwrap();
+        buy(&mut deps, "early", 12450).unwrap();
+        assert_err(buy(&mut deps, "early", 12600), "Sale has ended");
+
+        // removing the sale lifts all restrictions
+        let env = mock_env(OWNER, &[]);
+        handle(&mut deps, env, HandleMsg::SetSale { sale: None }).unwrap();
+        buy(&mut deps, "early", 99999).unwrap();
+    }
+
+    #[test]
+    fn dutch_auction() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let auction_msg = DutchAuctionMsg {
+            start_price: Uint128(5000),
+            floor_price: Uint128(1000),
+            start: Expiration::AtHeight(12400),
+            end: Expiration::AtHeight(12800),
+            // one extra, as the mock storage keeps the count of the underpaid purchase
+            // that would be reverted on chain
+            supply: 3,
+        };
+
+        // the range must be consistent and the floor below the start price
+        let env = mock_env(OWNER, &[]);
+        let bad = DutchAuctionMsg {
+            end: Expiration::AtTime(12800),
+            ..auction_msg.clone()
+        };
+        handle(&mut deps, env.clone(), HandleMsg::SetDutchAuction { auction: Some(bad) }).unwrap_err();
+        let bad = DutchAuctionMsg {
+            floor_price: Uint128(6000),
+            ..auction_msg.clone()
+        };
+        handle(&mut deps, env.clone(), HandleMsg::SetDutchAuction { auction: Some(bad) }).unwrap_err();
+
+        let msg = HandleMsg::SetDutchAuction { auction: Some(auction_msg) };
+        handle(&mut deps, mock_env("random", &[]), msg.clone()).unwrap_err();
+        handle(&mut deps, env, msg).unwrap();
+
+        let price = |deps: &Extern<_, _, _>, height: u64| {
+            query_current_price(deps, PackType::Premium, height, 0).unwrap()
+        };
+        assert_eq!(Uint128(5000), price(&deps, 12000));
+        assert_eq!(Uint128(3000), price(&deps, 12600));
+        assert_eq!(Uint128(1000), price(&deps, 12800));
+        assert_eq!(Uint128(1000), price(&deps, 20000));
+        assert_eq!(PACK_PRICE, query_current_price(&deps, PackType::Standard, 12600, 0).unwrap());
+
+        let buy = |deps: &mut Extern<_, _, _>, paid: u128, height: u64| {
+            let mut env = mock_env("buyer", &coins(paid, "uusd"));
+            env.block.height = height;
+            handle(deps, env, HandleMsg::PurchasePack {
+                referrer: None,
+                pack_type: Some(PackType::Premium),
+            })
+        };
+
+        // not before the auction starts
+        buy(&mut deps, 5000, 12399).unwrap_err();
+
+        // the scheduled sale only limits standard packs, the auction has its own window and supply
+        let sale = SaleMsg {
+            start: Expiration::AtHeight(20000),
+            end: Expiration::AtHeight(30000),
+            presale_end: None,
+            total_supply: 1,
+            max_per_address: 1,
+            presale_whitelist: vec![],
+        };
+        handle(&mut deps, mock_env(OWNER, &[]), HandleMsg::SetSale { sale: Some(sale) }).unwrap();
+        let mut env = mock_env("buyer", &coins(1000, "uusd"));
+        env.block.height = 12700;
+        let standard = HandleMsg::PurchasePack { referrer: None, pack_type: None };
+        match handle(&mut deps, env, standard).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Sale has not started"),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the handler charges the queried price, refunding the excess
+        let current = price(&deps, 12700);
+        assert_eq!(Uint128(2000), current);
+        buy(&mut deps, 1999, 12700).unwrap_err();
+        let res = buy(&mut deps, 2500, 12700).unwrap();
+        assert_eq!(
+            res.messages[1],
+            CosmosMsg::Bank(BankMsg::Send {
+                from_address: MOCK_CONTRACT_ADDR.into(),
+                to_address: "buyer".into(),
+                amount: coins(500, "uusd"),
+            })
+        );
+        assert_eq!(
+            res.messages[2],
+            CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute {
+                contract_addr: ATHLETE.into(),
+                msg: to_binary(&TokenMsg::BatchMint {
+                    mints: vec![
+                        TokenMint { owner: "buyer".into(), rank: "G".to_string() },
+                        TokenMint { owner: "buyer".into(), rank: "B".to_string() },
+                    ],
+                })
+                .unwrap(),
+                send: vec![],
+            })
+        );
+
+        // limited supply
+        buy(&mut deps, 1100, 12790).unwrap();
+        match buy(&mut deps, 1100, 12790).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Premium packs are sold out"),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // nothing sells at the floor price once the auction ended, even with supply left
+        let more = DutchAuctionMsg {
+            start_price: Uint128(5000),
+            floor_price: Uint128(1000),
+            start: Expiration::AtHeight(12400),
+            end: Expiration::AtHeight(12800),
+            supply: 10,
+        };
+        handle(&mut deps, mock_env(OWNER, &[]), HandleMsg::SetDutchAuction { auction: Some(more) })
+            .unwrap();
+        match buy(&mut deps, 1000, 12800).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Auction has ended"),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn retire_and_redeem() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let retired_athlete = HumanAddr::from("athlete2");
+        let env = mock_env(OWNER, &[]);
+        let msg = HandleMsg::AddToken {
+            tokens: vec![TokenData {
+                athlete_id: "2".to_string(),
+                contract_addr: retired_athlete.clone(),
+            }],
+        };
+        handle(&mut deps, env, msg).unwrap();
+        deps.querier.nfts.insert("LBJB1".into(), ("holder".into(), "B".into()));
+        deps.querier.nfts.insert("LBJG1".into(), ("holder".into(), "G".into()));
+
+        // only retired athletes can be redeemed
+        let redeem = |token_id: &str, redeem_for: RedeemOption| HandleMsg::RedeemRetired {
+            athlete_id: "2".into(),
+            token_id: token_id.into(),
+            redeem_for,
+        };
+        let env = mock_env("holder", &[]);
+        handle(&mut deps, env, redeem("LBJB1", RedeemOption::Payout)).unwrap_err();
+
+        // retiring freezes the athlete contract
+        let msg = HandleMsg::RetireAthlete { athlete_id: "2".into() };
+        handle(&mut deps, mock_env("random", &[]), msg.clone()).unwrap_err();
+        let res = handle(&mut deps, mock_env(OWNER, &[]), msg.clone()).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![encode_msg_execute(
+                to_binary(&TokenMsg::Freeze {}).unwrap(),
+                retired_athlete.clone(),
+                vec![]
+            )
+            .unwrap()]
+        );
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap_err();
+
+        // contests reject lineups with the retired athlete
+        let enter = |lineup: &[&str]| HandleMsg::EnterContest {
+            contest_id: 1,
+            lineup: lineup.iter().map(|athlete_id| athlete_id.to_string()).collect(),
+        };
+        match handle(&mut deps, mock_env("holder", &[]), enter(&["1", "2"])).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "Athlete 2 is retired"),
+            e => panic!("unexpected error: {}", e),
+        }
+        handle(&mut deps, mock_env("holder", &[]), enter(&["1"])).unwrap();
+
+        // payouts are set per rank and paid from the reserve
+        let env = mock_env(OWNER, &[]);
+        let msg = HandleMsg::SetRetirementPayout { rank: "B".into(), amount: Uint128(300) };
+        handle(&mut deps, env, msg).unwrap();
+        let env = mock_env("holder", &[]);
+        match handle(&mut deps, env, redeem("LBJG1", RedeemOption::Payout)).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "No payout is set for rank G"),
+            e => panic!("unexpected error: {}", e),
+        }
+        let env = mock_env("holder", &[]);
+        match handle(&mut deps, env, redeem("LBJB1", RedeemOption::Payout)).unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Retirement reserve cannot cover the payout")
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+        let env = mock_env("funder", &coins(500, "uusd"));
+        handle(&mut deps, env, HandleMsg::FundReserve {}).unwrap();
+
+        // only the token owner can redeem it
+        let env = mock_env("random", &[]);
+        match handle(&mut deps, env, redeem("LBJB1", RedeemOption::Payout)).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let env = mock_env("holder", &[]);
+        let res = handle(&mut deps, env, redeem("LBJB1", RedeemOption::Payout)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                encode_msg_execute(
+                    to_binary(&Cw721HandleMsg::Burn {
+                        token_id: "LBJB1".into(),
+                    })
+                    .unwrap(),
+                    retired_athlete.clone(),
+                    vec![]
+                )
+                .unwrap(),
+                BankMsg::Send {
+                    from_address: MOCK_CONTRACT_ADDR.into(),
+                    to_address: "holder".into(),
+                    amount: coins(300, "uusd"),
+                }
+                .into(),
+            ]
+        );
+        let res = query_retirement_reserve(&deps).unwrap();
+        assert_eq!(Uint128(200), res.reserve);
+        assert_eq!(vec![RankPayout { rank: "B".into(), amount: Uint128(300) }], res.payouts);
+
+        // or burned for a replacement pack, which skips retired athletes
+        let env = mock_env("holder", &[]);
+        let res = handle(&mut deps, env, redeem("LBJG1", RedeemOption::Pack)).unwrap();
+        assert_eq!(2, res.messages.len());
+        let history = query_user_history(&deps, "holder".into(), None, None).unwrap().history;
+        match &history[1].event {
+            HistoryEvent::ReplacementPack { token_id, athletes } => {
+                assert_eq!("LBJG1", token_id);
+                assert!(athletes.iter().all(|a| a.athlete_id == "1"));
+            }
+            e => panic!("unexpected event: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn packs_skip_retired_athletes() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let msg = HandleMsg::AddToken {
+            tokens: vec![TokenData {
+                athlete_id: "2".to_string(),
+                contract_addr: "athlete2".into(),
+            }],
+        };
+        // only the owner registers athlete contracts
+        match handle(&mut deps, mock_env("random", &[]), msg.clone()).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap();
+        let purchase = HandleMsg::PurchasePack { referrer: None, pack_type: None };
+
+        // once athlete 1 retired, packs are pulled from athlete 2
+        let msg = HandleMsg::RetireAthlete { athlete_id: "1".into() };
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap();
+        let env = mock_env("buyer", &coins(1000, "uusd"));
+        let res = handle(&mut deps, env, purchase.clone()).unwrap();
+        let mint = TokenMint {
+            owner: "buyer".into(),
+            rank: "B".to_string(),
+        };
+        let mint_msg = TokenMsg::BatchMint {
+            mints: vec![mint.clone(), mint],
+        };
+        assert_eq!(
+            res.messages[1],
+            encode_msg_execute(to_binary(&mint_msg).unwrap(), "athlete2".into(), vec![]).unwrap()
+        );
+
+        // nothing left to pull once every athlete retired
+        let msg = HandleMsg::RetireAthlete { athlete_id: "2".into() };
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap();
+        let env = mock_env("buyer", &coins(1000, "uusd"));
+        match handle(&mut deps, env, purchase).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "No athlete can be minted"),
+            e => panic!("unexpected error: {}", e),
+        }
+        let env = mock_env("newcomer", &[]);
+        let msg = HandleMsg::ClaimStarterPack { proof: vec![] };
+        handle(&mut deps, env, msg).unwrap_err();
+    }
+
+    #[test]
+    fn harvest_yield() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let env = mock_env("depositor", &coins(1000, "uusd"));
+        handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap();
+        deps.querier.aust_balance = Uint128(1000);
+
+        // nothing to harvest while the exchange rate did not move
+        let env = mock_env("anyone", &[]);
+        match handle(&mut deps, env, HandleMsg::Harvest {}).unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), "No yield to harvest"),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the split must add up and name its recipients
+        let split = YieldSplit {
+            prize_pool_bps: 4000,
+            treasury_bps: 2000,
+            stakers_bps: 2000,
+            redeposit_bps: 2000,
+            treasury: Some("treasury".into()),
+            stakers: None,
+        };
+        let msg = HandleMsg::UpdateYieldSplit { split: split.clone() };
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap_err();
+        let split = YieldSplit {
+            stakers: Some("stakers".into()),
+            ..split
+        };
+        let msg = HandleMsg::UpdateYieldSplit {
+            split: YieldSplit {
+                prize_pool_bps: 5000,
+                ..split.clone()
+            },
+        };
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap_err();
+        let msg = HandleMsg::UpdateYieldSplit { split: split.clone() };
+        handle(&mut deps, mock_env("random", &[]), msg.clone()).unwrap_err();
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap();
+
+        // 1000 aUST at the live rate of 1.2 is 200uusd of yield on top of the 1000uusd principal
+        deps.querier.exchange_rate = Decimal256::percent(120);
+        let env = mock_env("anyone", &[]);
+        let res = handle(&mut deps, env.clone(), HandleMsg::Harvest {}).unwrap();
+        let (redeem_msg, aust_amount) = redeem_stable_msg(&deps, &env.block, Uint128(200)).unwrap();
+        assert_eq!(Uint128(166), aust_amount);
+        let (deposit_msg, _) = deposit_stable_msg(&deps, Uint128(40)).unwrap();
+        let send = |to: &str| -> CosmosMsg {
+            BankMsg::Send {
+                from_address: MOCK_CONTRACT_ADDR.into(),
+                to_address: to.into(),
+                amount: coins(40, "uusd"),
+            }
+            .into()
+        };
+        assert_eq!(
+            res.messages,
+            vec![redeem_msg, send("treasury"), send("stakers"), deposit_msg]
+        );
+
+        let res = query_yield_split(&deps).unwrap();
+        assert_eq!(split, res.split);
+        assert_eq!(Uint128(80), res.prize_pool);
+        assert_eq!(Uint128(40), res.compounded);
+        assert_eq!(Uint128(200), res.total_harvested);
+
+        // the compounded yield counts as principal: 1200 aUST at 1.2 is 400uusd above 1040uusd
+        deps.querier.aust_balance = Uint128(1200);
+        let env = mock_env("anyone", &[]);
+        handle(&mut deps, env, HandleMsg::Harvest {}).unwrap();
+        assert_eq!(Uint128(600), query_yield_split(&deps).unwrap().total_harvested);
+    }
+
+    #[test]
+    fn share_token_receipts() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // the share token must be minted by this contract
+        let msg = HandleMsg::RegisterShareToken { token: "shares".into() };
+        handle(&mut deps, mock_env("random", &[]), msg.clone()).unwrap_err();
+        deps.querier.cw20_minter = "random".into();
+        handle(&mut deps, mock_env(OWNER, &[]), msg.clone()).unwrap_err();
+        deps.querier.cw20_minter = MOCK_CONTRACT_ADDR.into();
+        handle(&mut deps, mock_env(OWNER, &[]), msg.clone()).unwrap();
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap_err();
+        assert_eq!(
+            Some(HumanAddr::from("shares")),
+            from_binary::<ShareTokenResponse>(&query(&deps, QueryMsg::ShareToken {}).unwrap())
+                .unwrap()
+                .token
+        );
+
+        // deposits mint shares instead of crediting the depositor
+        let env = mock_env("depositor", &coins(1000, "uusd"));
+        let res = handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap();
+        assert_eq!(
+            res.messages[1],
+            encode_msg_execute(
+                to_binary(&Cw20HandleMsg::Mint {
+                    recipient: "depositor".into(),
+                    amount: Uint128(1000),
+                })
+                .unwrap(),
+                "shares".into(),
+                vec![]
+            )
+            .unwrap()
+        );
+        assert_eq!(Uint128::zero(), query_deposit(&deps, "depositor".into()).unwrap());
+
+        // whoever holds the shares can redeem them, only through the share token
+        let receive = HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: "holder".into(),
+            amount: Uint128(400),
+            msg: Some(to_binary(&Cw20HookMsg::RedeemShares {}).unwrap()),
+        });
+        handle(&mut deps, mock_env("holder", &[]), receive.clone()).unwrap_err();
+        let env = mock_env("shares", &[]);
+        let res = handle(&mut deps, env.clone(), receive).unwrap();
+        let (redeem_msg, _) = redeem_stable_msg(&deps, &env.block, Uint128(400)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                encode_msg_execute(
+                    to_binary(&Cw20HandleMsg::Burn { amount: Uint128(400) }).unwrap(),
+                    "shares".into(),
+                    vec![]
+                )
+                .unwrap(),
+                redeem_msg,
+                BankMsg::Send {
+                    from_address: MOCK_CONTRACT_ADDR.into(),
+                    to_address: "holder".into(),
+                    amount: coins(400, "uusd"),
+                }
+                .into(),
+            ]
+        );
+        assert_eq!(Uint128(600), query_total_deposit(&deps).unwrap());
+    }
+
+    #[test]
+    fn withdrawal_queue() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        for depositor in &["alice", "bob"] {
+            let env = mock_env(*depositor, &coins(1000, "uusd"));
+            handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap();
+        }
+        deps.querier.update_balance(ANCHOR_ADDR, coins(100, "uusd"));
+
+        // withdrawals anchor cannot cover wait in line, and so does everyone after them
+        let mut env = mock_env("alice", &[]);
+        env.block.time = 111;
+        let res = handle(&mut deps, env, HandleMsg::WithdrawStable { amount: Uint128(300) }).unwrap();
+        assert_eq!(0, res.messages.len());
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::WithdrawStable { amount: Uint128(50) };
+        let res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_eq!(0, res.messages.len());
+        assert_eq!(Uint128(950), query_deposit(&deps, "bob".into()).unwrap());
+
+        let queue = query_withdrawal_queue(&deps, None, None).unwrap().withdrawals;
+        assert_eq!(2, queue.len());
+        assert_eq!(
+            WithdrawalItem {
+                id: 1,
+                owner: "alice".into(),
+                amount: Uint128(300),
+                time: 111,
+            },
+            queue[0]
+        );
+        let page = query_withdrawal_queue(&deps, Some(1), Some(1)).unwrap().withdrawals;
+        assert_eq!(vec![queue[1].clone()], page);
+
+        // only the owner can cancel, which returns the amount to their deposits
+        let cancel = HandleMsg::CancelWithdrawal { id: 2 };
+        handle(&mut deps, mock_env("alice", &[]), cancel.clone()).unwrap_err();
+        handle(&mut deps, mock_env("bob", &[]), cancel).unwrap();
+        assert_eq!(Uint128(1000), query_deposit(&deps, "bob".into()).unwrap());
+        handle(&mut deps, mock_env("bob", &[]), msg).unwrap();
+
+        // processing stops at the first withdrawal that does not fit
+        let env = mock_env("anyone", &[]);
+        let res = handle(&mut deps, env, HandleMsg::ProcessQueue { limit: None }).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        deps.querier.update_balance(ANCHOR_ADDR, coins(320, "uusd"));
+        let env = mock_env("anyone", &[]);
+        let res = handle(&mut deps, env, HandleMsg::ProcessQueue { limit: None }).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            res.messages[1],
+            BankMsg::Send {
+                from_address: MOCK_CONTRACT_ADDR.into(),
+                to_address: "alice".into(),
+                amount: coins(300, "uusd"),
+            }
+            .into()
+        );
+        assert_eq!(Uint128(1700), query_total_deposit(&deps).unwrap());
+        let queue = query_withdrawal_queue(&deps, None, None).unwrap().withdrawals;
+        assert_eq!(vec![3], queue.iter().map(|w| w.id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn deposit_limits() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let limits = DepositLimits {
+            tvl_cap: Some(Uint128(2500)),
+            address_cap: Some(Uint128(1500)),
+            min_deposit: Uint128(100),
+        };
+        let msg = HandleMsg::UpdateDepositLimits { limits: limits.clone() };
+        handle(&mut deps, mock_env("random", &[]), msg.clone()).unwrap_err();
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap();
+        assert_eq!(
+            limits,
+            from_binary::<DepositLimits>(&query(&deps, QueryMsg::DepositLimits {}).unwrap()).unwrap()
+        );
+
+        let deposit = |deps: &mut Extern<_, _, _>, sender: &str, amount: u128| {
+            let env = mock_env(sender, &coins(amount, "uusd"));
+            handle(deps, env, HandleMsg::DepositStable {})
+        };
+        let assert_err = |res: StdResult<HandleResponse>, expected: &str| match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), expected),
+            e => panic!("unexpected error: {}", e),
+        };
+
+        assert_err(deposit(&mut deps, "alice", 99), "Minimum deposit is 100uusd");
+        deposit(&mut deps, "alice", 1000).unwrap();
+        assert_err(
+            deposit(&mut deps, "alice", 600),
+            "Deposit exceeds the per-address cap, 500uusd left",
+        );
+        deposit(&mut deps, "alice", 500).unwrap();
+        deposit(&mut deps, "bob", 800).unwrap();
+        assert_err(deposit(&mut deps, "carol", 300), "Deposit exceeds the TVL cap, 200uusd left");
+
+        // share tokens count towards the per-address cap
+        handle(
+            &mut deps,
+            mock_env(OWNER, &[]),
+            HandleMsg::RegisterShareToken { token: "shares".into() },
+        )
+        .unwrap();
+        deps.querier.cw20_balances.insert("carol".into(), Uint128(1400));
+        assert_err(
+            deposit(&mut deps, "carol", 200),
+            "Deposit exceeds the per-address cap, 100uusd left",
+        );
+        deposit(&mut deps, "carol", 100).unwrap();
+        assert_eq!(Uint128(2400), query_total_deposit(&deps).unwrap());
+    }
+
+    #[test]
+    fn total_deposit_does_not_truncate() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let amount = u64::MAX as u128 + 1;
+        let env = mock_env("whale", &coins(amount, "uusd"));
+        handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap();
+        assert_eq!(Uint128(amount), query_total_deposit(&deps).unwrap());
+    }
+
+    #[test]
+    fn total_deposit_reads_legacy_u64() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        // contracts instantiated before the switch to Uint128 stored a plain number
+        singleton(&mut deps.storage, TOTAL_DEPOSIT_KEY).save(&5000u64).unwrap();
+        assert_eq!(Uint128(5000), query_total_deposit(&deps).unwrap());
+
+        // and are written in the new encoding on the next deposit
+        let env = mock_env("depositor", &coins(1000, "uusd"));
+        handle(&mut deps, env, HandleMsg::DepositStable {}).unwrap();
+        assert_eq!(Uint128(6000), query_total_deposit(&deps).unwrap());
+        let stored: Uint128 = singleton_read(&deps.storage, TOTAL_DEPOSIT_KEY).load().unwrap();
+        assert_eq!(Uint128(6000), stored);
+    }
+
+    #[test]
+    fn contest_dispute_window() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+        treasury(&mut deps.storage)
+            .save(&Treasury {
+                prize_pool: Uint128(1000),
+                ..Treasury::default()
+            })
+            .unwrap();
+
+        let payouts = vec![
+            ContestPayout { address: "alice".into(), amount: Uint128(600) },
+            ContestPayout { address: "bob".into(), amount: Uint128(300) },
+        ];
+        let settle = HandleMsg::SettleContest { contest_id: 1, payouts: payouts.clone() };
+
+        // the dispute window must be configured first
+        handle(&mut deps, mock_env(OWNER, &[]), settle.clone()).unwrap_err();
+        let msg = HandleMsg::UpdateContestConfig {
+            dispute_window: Duration::Height(100),
+            multisig: Some("multisig".into()),
+        };
+        handle(&mut deps, mock_env("random", &[]), msg.clone()).unwrap_err();
+        handle(&mut deps, mock_env(OWNER, &[]), msg).unwrap();
+
+        // lineups are entered before the contest is settled, and can be replaced
+        let enter = |lineup: &[&str]| HandleMsg::EnterContest {
+            contest_id: 1,
+            lineup: lineup.iter().map(|athlete_id| athlete_id.to_string()).collect(),
+        };
+        let assert_err = |res: StdResult<HandleResponse>, expected: &str| match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), expected),
+            e => panic!("unexpected error: {}", e),
+        };
+        assert_err(handle(&mut deps, mock_env("alice", &[]), enter(&[])), "Lineup is empty");
+        assert_err(handle(&mut deps, mock_env("alice", &[]), enter(&["1", "1"])), "Athlete 1 is picked twice");
+        assert_err(handle(&mut deps, mock_env("alice", &[]), enter(&["9"])), "Unknown athlete 9");
+        handle(&mut deps, mock_env("alice", &[]), enter(&["1"])).unwrap();
+        let entry = query_contest_entry(&deps, 1, "alice".into()).unwrap();
+        assert_eq!(vec!["1".to_string()], entry.lineup);
+        query_contest_entry(&deps, 1, "bob".into()).unwrap_err();
+
+        // only the owner settles, once, and within the prize pool
+        handle(&mut deps, mock_env("random", &[]), settle.clone()).unwrap_err();
+        let too_much = HandleMsg::SettleContest {
+            contest_id: 1,
+            payouts: vec![ContestPayout { address: "alice".into(), amount: Uint128(1001) }],
+        };
+        handle(&mut deps, mock_env(OWNER, &[]), too_much).unwrap_err();
+        let env = mock_env(OWNER, &[]);
+        let settled_at = env.block.height;
+        handle(&mut deps, env, settle.clone()).unwrap();
+        handle(&mut deps, mock_env(OWNER, &[]), settle).unwrap_err();
+        assert_eq!(Uint128(100), query_yield_split(&deps).unwrap().prize_pool);
+        assert_err(handle(&mut deps, mock_env("bob", &[]), enter(&["1"])), "Contest already settled");
+
+        let claim = |deps: &mut Extern<_, _, _>, sender: &str, height: u64| {
+            let mut env = mock_env(sender, &[]);
+            env.block.height = height;
+            handle(deps, env, HandleMsg::ClaimPayout { contest_id: 1 })
+        };
+        let assert_err = |res: StdResult<HandleResponse>, expected: &str| match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), expected),
+            e => panic!("unexpected error: {}", e),
+        };
+        assert_err(claim(&mut deps, "alice", settled_at + 99), "Results are not final yet");
+
+        // the multisig can overturn the results during the window
+        let correction = vec![
+            ContestPayout { address: "alice".into(), amount: Uint128(300) },
+            ContestPayout { address: "carol".into(), amount: Uint128(700) },
+        ];
+        let correct = |deps: &mut Extern<_, _, _>, sender: &str, height: u64| {
+            let mut env = mock_env(sender, &[]);
+            env.block.height = height;
+            let msg = HandleMsg::CorrectResults { contest_id: 1, payouts: correction.clone() };
+            handle(deps, env, msg)
+        };
+        match correct(&mut deps, OWNER, settled_at + 50).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        correct(&mut deps, "multisig", settled_at + 50).unwrap();
+        assert_eq!(Uint128::zero(), query_yield_split(&deps).unwrap().prize_pool);
+        let result: ContestResult =
+            from_binary(&query(&deps, QueryMsg::ContestResult { contest_id: 1 }).unwrap()).unwrap();
+        assert_eq!(correction, result.payouts);
+        assert!(result.corrected);
+
+        // once the window closes results are final and payouts claimable
+        assert_err(correct(&mut deps, "multisig", settled_at + 100), "Dispute window has closed");
+        assert_err(claim(&mut deps, "bob", settled_at + 100), "No payout to claim");
+
+        // pausing contests closes entries but payouts stay claimable
+        let pause = HandleMsg::Pause { scope: PauseScope::Contests };
+        handle(&mut deps, mock_env(OWNER, &[]), pause).unwrap();
+        let enter = HandleMsg::EnterContest { contest_id: 2, lineup: vec!["1".into()] };
+        assert_err(handle(&mut deps, mock_env("alice", &[]), enter), "Contests are paused");
+        let res = claim(&mut deps, "carol", settled_at + 100).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![BankMsg::Send {
+                from_address: MOCK_CONTRACT_ADDR.into(),
+                to_address: "carol".into(),
+                amount: coins(700, "uusd"),
+            }
+            .into()]
+        );
+        assert_err(claim(&mut deps, "carol", settled_at + 101), "Payout already claimed");
+    }
+
+    #[test]
+    fn crafting_recipes() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let recipe = Recipe {
+            inputs: vec![
+                RecipeInput { contract: "athlete1".into(), rank: "S".into(), count: 2 },
+                RecipeInput { contract: "athlete2".into(), rank: "S".into(), count: 1 },
+            ],
+            output: RecipeOutput { contract: "captains".into(), rank: "C".into() },
+        };
+        let set_recipe = |recipe: Option<Recipe>| HandleMsg::SetRecipe {
+            recipe_id: "captain".into(),
+            recipe,
+        };
+
+        // only the owner sets valid recipes
+        handle(&mut deps, mock_env("random", &[]), set_recipe(Some(recipe.clone()))).unwrap_err();
+        let mut invalid = recipe.clone();
+        invalid.inputs[1].count = 0;
+        handle(&mut deps, mock_env(OWNER, &[]), set_recipe(Some(invalid))).unwrap_err();
+        let mut invalid = recipe.clone();
+        invalid.inputs[1].contract = "athlete1".into();
+        handle(&mut deps, mock_env(OWNER, &[]), set_recipe(Some(invalid))).unwrap_err();
+        handle(&mut deps, mock_env(OWNER, &[]), set_recipe(Some(recipe.clone()))).unwrap();
+        assert_eq!(
+            query_recipes(&deps, None, None).unwrap().recipes,
+            vec![RecipeItem { recipe_id: "captain".into(), recipe }]
+        );
+
+        deps.querier.nfts.insert("LBJS1".into(), ("holder".into(), "S".into()));
+        deps.querier.nfts.insert("LBJS2".into(), ("holder".into(), "S".into()));
+        deps.querier.nfts.insert("LBJB1".into(), ("holder".into(), "B".into()));
+        deps.querier.nfts.insert("ADS1".into(), ("holder".into(), "S".into()));
+        deps.querier.nfts.insert("ADS2".into(), ("random".into(), "S".into()));
+        let input = |contract: &str, token_id: &str| CraftInput {
+            contract: contract.into(),
+            token_id: token_id.into(),
+        };
+        let craft = |recipe_id: &str, inputs: Vec<CraftInput>| HandleMsg::Craft {
+            recipe_id: recipe_id.into(),
+            inputs,
+        };
+        let assert_err = |res: StdResult<HandleResponse>, expected: &str| match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), expected),
+            e => panic!("unexpected error: {}", e),
+        };
+
+        let inputs = vec![
+            input("athlete1", "LBJS1"),
+            input("athlete1", "LBJS2"),
+            input("athlete2", "ADS1"),
+        ];
+        let res = handle(&mut deps, mock_env("holder", &[]), craft("trophy", inputs.clone()));
+        assert_err(res, "Unknown recipe trophy");
+        let res = handle(&mut deps, mock_env("holder", &[]), craft("captain", inputs[..2].to_vec()));
+        assert_err(res, "Recipe captain takes 1 tokens of rank S from athlete2");
+        let wrong = vec![
+            input("athlete1", "LBJS1"),
+            input("athlete1", "LBJB1"),
+            input("athlete2", "ADS1"),
+        ];
+        let res = handle(&mut deps, mock_env("holder", &[]), craft("captain", wrong));
+        assert_err(res, "Token LBJB1 is not an input of recipe captain");
+        let wrong = vec![
+            input("athlete1", "LBJS1"),
+            input("athlete1", "LBJS1"),
+            input("athlete2", "ADS1"),
+        ];
+        let res = handle(&mut deps, mock_env("holder", &[]), craft("captain", wrong));
+        assert_err(res, "Token LBJS1 is used twice");
+        let wrong = vec![
+            input("athlete1", "LBJS1"),
+            input("athlete1", "LBJS2"),
+            input("athlete2", "ADS2"),
+        ];
+        match handle(&mut deps, mock_env("holder", &[]), craft("captain", wrong)).unwrap_err() {
+            StdError::Unauthorized { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // all inputs are burned in the same transaction as the output is minted
+        let res = handle(&mut deps, mock_env("holder", &[]), craft("captain", inputs.clone())).unwrap();
+        let burn = |contract: &str, token_id: &str| {
+            encode_msg_execute(
+                to_binary(&Cw721HandleMsg::Burn { token_id: token_id.into() }).unwrap(),
+                contract.into(),
+                vec![],
+            )
+            .unwrap()
+        };
+        let mint = TokenMsg::Mint { owner: "holder".into(), rank: "C".into() };
+        assert_eq!(
+            res.messages,
+            vec![
+                burn("athlete1", "LBJS1"),
+                burn("athlete1", "LBJS2"),
+                burn("athlete2", "ADS1"),
+                encode_msg_execute(to_binary(&mint).unwrap(), "captains".into(), vec![]).unwrap(),
+            ]
+        );
+        let history = query_user_history(&deps, "holder".into(), None, None).unwrap().history;
+        assert_eq!(
+            history[0].event,
+            HistoryEvent::Craft {
+                recipe_id: "captain".into(),
+                burned: vec!["LBJS1".into(), "LBJS2".into(), "ADS1".into()],
+            }
+        );
+
+        // removed recipes cannot be crafted
+        handle(&mut deps, mock_env(OWNER, &[]), set_recipe(None)).unwrap();
+        assert!(query_recipes(&deps, None, None).unwrap().recipes.is_empty());
+        let res = handle(&mut deps, mock_env("holder", &[]), craft("captain", inputs));
+        assert_err(res, "Unknown recipe captain");
+    }
+
+    #[test]
+    fn crafting_with_send_nft() {
+        let mut deps = mock_dependencies(20, &[]);
+        setup_contract(&mut deps);
+
+        let recipe = Recipe {
+            inputs: vec![
+                RecipeInput { contract: "athlete1".into(), rank: "S".into(), count: 2 },
+                RecipeInput { contract: "athlete2".into(), rank: "S".into(), count: 1 },
+            ],
+            output: RecipeOutput { contract: "captains".into(), rank: "C".into() },
+        };
+        let set_recipe = HandleMsg::SetRecipe {
+            recipe_id: "captain".into(),
+            recipe: Some(recipe),
+        };
+        handle(&mut deps, mock_env(OWNER, &[]), set_recipe).unwrap();
+        let rookie = Recipe {
+            inputs: vec![RecipeInput { contract: "athlete3".into(), rank: "S".into(), count: 1 }],
+            output: RecipeOutput { contract: "captains".into(), rank: "R".into() },
+        };
+        let set_recipe = HandleMsg::SetRecipe {
+            recipe_id: "rookie".into(),
+            recipe: Some(rookie),
+        };
+        handle(&mut deps, mock_env(OWNER, &[]), set_recipe).unwrap();
+
+        // sent tokens are owned by this contract by the time it receives them
+        for token_id in &["LBJS1", "LBJS2", "LBJS3", "ADS1"] {
+            deps.querier.nfts.insert(token_id.to_string(), (MOCK_CONTRACT_ADDR.into(), "S".into()));
+        }
+        deps.querier.nfts.insert("LBJB1".into(), (MOCK_CONTRACT_ADDR.into(), "B".into()));
+        let hook = to_binary(&Cw721HookMsg::Craft { recipe_id: "captain".into() }).unwrap();
+        let receive = |token_id: &str, msg: Option<Binary>| {
+            HandleMsg::ReceiveNft(Cw721ReceiveMsg {
+                sender: "holder".into(),
+                token_id: token_id.into(),
+                msg,
+            })
+        };
+        let assert_err = |res: StdResult<HandleResponse>, expected: &str| match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg.as_str(), expected),
+            e => panic!("unexpected error: {}", e),
+        };
+
+        let res = handle(&mut deps, mock_env("stranger", &[]), receive("LBJS1", Some(hook.clone())));
+        assert_err(res, "Tokens of stranger are not accepted");
+        // only the inputs of the named recipe are accepted, not those of every recipe
+        let res = handle(&mut deps, mock_env("athlete3", &[]), receive("LBJS1", Some(hook.clone())));
+        assert_err(res, "Tokens of athlete3 are not accepted");
+        let unknown = to_binary(&Cw721HookMsg::Craft { recipe_id: "coach".into() }).unwrap();
+        let res = handle(&mut deps, mock_env("athlete1", &[]), receive("LBJS1", Some(unknown)));
+        assert_err(res, "Unknown recipe coach");
+        let res = handle(&mut deps, mock_env("athlete1", &[]), receive("LBJS1", None));
+        assert_err(res, "Missing the recipe to craft");
+        let res = handle(&mut deps, mock_env("athlete1", &[]), receive("LBJB1", Some(hook.clone())));
+        assert_err(res, "Token LBJB1 is not an input of recipe captain");
+
+        // the tokens are held until the recipe is complete, and can be taken back
+        let res = handle(&mut deps, mock_env("athlete1", &[]), receive("LBJS1", Some(hook.clone()))).unwrap();
+        assert!(res.messages.is_empty());
+        let res = handle(&mut deps, mock_env("holder", &[]), HandleMsg::CancelCraft {
+            recipe_id: "captain".into(),
+        })
+        .unwrap();
+        let transfer = Cw721HandleMsg::TransferNft {
+            recipient: "holder".into(),
+            token_id: "LBJS1".into(),
+        };
+        assert_eq!(
+            res.messages,
+            vec![encode_msg_execute(to_binary(&transfer).unwrap(), "athlete1".into(), vec![]).unwrap()]
+        );
+        let res = handle(&mut deps, mock_env("holder", &[]), HandleMsg::CancelCraft {
+            recipe_id: "captain".into(),
+        });
+        assert_err(res, "No tokens held for recipe captain");
+
+        handle(&mut deps, mock_env("athlete1", &[]), receive("LBJS1", Some(hook.clone()))).unwrap();
+        let res = handle(&mut deps, mock_env("athlete1", &[]), receive("LBJS1", Some(hook.clone())));
+        assert_err(res, "Token LBJS1 is used twice");
+        handle(&mut deps, mock_env("athlete1", &[]), receive("LBJS2", Some(hook.clone()))).unwrap();
+        let res = handle(&mut deps, mock_env("athlete1", &[]), receive("LBJS3", Some(hook.clone())));
+        assert_err(res, "Recipe captain takes 2 tokens of rank S from athlete1");
+
+        // the last input burns them all and mints the output to the sender
+        let res = handle(&mut deps, mock_env("athlete2", &[]), receive("ADS1", Some(hook))).unwrap();
+        let burn = |contract: &str, token_id: &str| {
+            encode_msg_execute(
+                to_binary(&Cw721HandleMsg::Burn { token_id: token_id.into() }).unwrap(),
+                contract.into(),
+                vec![],
+            )
+            .unwrap()
+        };
+        let mint = TokenMsg::Mint { owner: "holder".into(), rank: "C".into() };
+        assert_eq!(
+            res.messages,
+            vec![
+                burn("athlete1", "LBJS1"),
+                burn("athlete1", "LBJS2"),
+                burn("athlete2", "ADS1"),
+                encode_msg_execute(to_binary(&mint).unwrap(), "captains".into(), vec![]).unwrap(),
+            ]
+        );
+        let history = query_user_history(&deps, "holder".into(), None, None).unwrap().history;
+        assert_eq!(
+            history[0].event,
+            HistoryEvent::Craft {
+                recipe_id: "captain".into(),
+                burned: vec!["LBJS1".into(), "LBJS2".into(), "ADS1".into()],
+            }
+        );
+        let res = handle(&mut deps, mock_env("holder", &[]), HandleMsg::CancelCraft {
+            recipe_id: "captain".into(),
+        });
+        assert_err(res, "No tokens held for recipe captain");
+    }
+}
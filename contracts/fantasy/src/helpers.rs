@@ -1,3 +1,5 @@
+use sha2::{Digest, Sha256};
+
 use cosmwasm_std::{
     Api, 
     Binary, 
@@ -11,7 +13,8 @@ use cosmwasm_std::{
     Storage, 
     WasmMsg, 
     WasmQuery, 
-    CosmosMsg
+    CosmosMsg,
+    StdError,
 };
 use cw20::{
     BalanceResponse
@@ -55,4 +58,27 @@ pub fn wrapper_msg_anchor_balance<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<BalanceResponse> {
     let res: BalanceResponse = deps.querier.query(&query)?;
     Ok(res)
-}
\ No newline at end of file
+}
+
+/// Checks that sha256(leaf) is part of the merkle tree with the given root.
+/// Each pair of nodes is hashed in sorted order, so the proof needs no left/right flags
+pub fn verify_merkle_proof(leaf: &str, proof: &[String], root: &[u8]) -> StdResult<bool> {
+    let mut hash = Sha256::digest(leaf.as_bytes()).to_vec();
+    for sibling in proof.iter() {
+        let sibling = hex::decode(sibling)
+            .map_err(|e| StdError::generic_err(format!("Error parsing proof: {}", e)))?;
+        if sibling.len() != 32 {
+            return Err(StdError::generic_err("Proof hashes must be 64 characters"));
+        }
+        let mut hasher = Sha256::new();
+        if hash <= sibling {
+            hasher.input(&hash);
+            hasher.input(&sibling);
+        } else {
+            hasher.input(&sibling);
+            hasher.input(&hash);
+        }
+        hash = hasher.result().to_vec();
+    }
+    Ok(hash.as_slice() == root)
+}
@@ -4,5 +4,8 @@ pub mod state;
 pub mod helpers;
 pub mod querier;
 
+#[cfg(test)]
+mod mock_querier;
+
 #[cfg(all(target_arch = "wasm32", not(feature = "library")))]
 cosmwasm_std::create_entry_points!(contract);
@@ -0,0 +1,188 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    coins, from_binary, from_slice, to_binary, Api, CanonicalAddr, Coin, Decimal, Extern, HumanAddr,
+    Querier, QuerierResult, QueryRequest, SystemError, Uint128, WasmQuery,
+};
+use cosmwasm_storage::to_length_prefixed;
+use cw20::{BalanceResponse, Cw20QueryMsg, MinterResponse};
+use cw721::{AllNftInfoResponse, Cw721QueryMsg, NftInfoResponse, OwnerOfResponse};
+use std::collections::HashMap;
+use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper};
+
+use crate::msg::{AnchorQueryMsg, ConfigResponse, EpochStateResponse, QueryMintMsg};
+
+pub const MOCK_CONTRACT_ADDR: &str = "cosmos2contract";
+pub const ANCHOR_ADDR: &str = "anchor";
+pub const ATERRA_ADDR: &str = "aterra";
+/// uusd held by the anchor market unless changed with update_balance
+pub const ANCHOR_LIQUIDITY: u128 = 1_000_000;
+
+/// Like cosmwasm_std::testing::mock_dependencies, but also answers the anchor queries,
+/// the athlete contracts' IsMintable and AllNftInfo queries, the cw20 Balance and Minter
+/// queries and terra tax queries
+pub fn mock_dependencies(
+    canonical_length: usize,
+    contract_balance: &[Coin],
+) -> Extern<MockStorage, MockApi, WasmMockQuerier> {
+    let contract_addr = HumanAddr::from(MOCK_CONTRACT_ADDR);
+    let anchor_addr = HumanAddr::from(ANCHOR_ADDR);
+    let api = MockApi::new(canonical_length);
+    let custom_querier = WasmMockQuerier::new(
+        MockQuerier::new(&[
+            (&contract_addr, contract_balance),
+            (&anchor_addr, &coins(ANCHOR_LIQUIDITY, "uusd")),
+        ]),
+        api.canonical_address(&ATERRA_ADDR.into()).unwrap(),
+    );
+
+    Extern {
+        storage: MockStorage::default(),
+        api,
+        querier: custom_querier,
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<TerraQueryWrapper>,
+    aterra_contract: CanonicalAddr,
+    /// anchor's aUST exchange rate
+    pub exchange_rate: Decimal256,
+    /// answer of every athlete contract's IsMintable query
+    pub mintable: bool,
+    /// (owner, rank) of athlete tokens by token_id, shared by all athlete contracts
+    pub nfts: HashMap<String, (HumanAddr, String)>,
+    /// aUST balance of the fantasy contract
+    pub aust_balance: Uint128,
+    /// balances of any other cw20 token, by holder
+    pub cw20_balances: HashMap<HumanAddr, Uint128>,
+    /// minter of every cw20 token
+    pub cw20_minter: HumanAddr,
+    pub tax_rate: Decimal,
+    pub tax_cap: Uint128,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<TerraQueryWrapper>, aterra_contract: CanonicalAddr) -> Self {
+        WasmMockQuerier {
+            base,
+            aterra_contract,
+            exchange_rate: Decimal256::one(),
+            mintable: true,
+            nfts: HashMap::new(),
+            aust_balance: Uint128::zero(),
+            cw20_balances: HashMap::new(),
+            cw20_minter: MOCK_CONTRACT_ADDR.into(),
+            tax_rate: Decimal::zero(),
+            tax_cap: Uint128::zero(),
+        }
+    }
+
+    pub fn update_balance<U: Into<HumanAddr>>(&mut self, addr: U, balance: Vec<Coin>) {
+        self.base.update_balance(addr, balance);
+    }
+
+    pub fn handle_query(&self, request: &QueryRequest<TerraQueryWrapper>) -> QuerierResult {
+        match request {
+            QueryRequest::Custom(TerraQueryWrapper { query_data, .. }) => match query_data {
+                TerraQuery::TaxRate {} => Ok(to_binary(&TaxRateResponse {
+                    rate: self.tax_rate,
+                })),
+                TerraQuery::TaxCap { .. } => Ok(to_binary(&TaxCapResponse { cap: self.tax_cap })),
+                _ => panic!("DO NOT ENTER HERE"),
+            },
+            // raw queries answer with the stored value wrapped in a Binary
+            QueryRequest::Wasm(WasmQuery::Raw { key, .. }) => {
+                if key.as_slice() == to_length_prefixed(b"config").as_slice() {
+                    Ok(to_binary(&to_binary(&self.anchor_config()).unwrap()))
+                } else {
+                    self.base.handle_query(request)
+                }
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if let Ok(QueryMintMsg::IsMintable { .. }) = from_binary(msg) {
+                    return Ok(to_binary(&self.mintable));
+                }
+                if let Ok(AnchorQueryMsg::EpochState { .. }) = from_binary(msg) {
+                    return Ok(to_binary(&EpochStateResponse {
+                        exchange_rate: self.exchange_rate,
+                        aterra_supply: Uint256::zero(),
+                    }));
+                }
+                match from_binary(msg) {
+                    Ok(Cw20QueryMsg::Balance { address }) => {
+                        let balance = if contract_addr.as_str() == ATERRA_ADDR {
+                            self.aust_balance
+                        } else {
+                            self.cw20_balances.get(&address).cloned().unwrap_or_default()
+                        };
+                        return Ok(to_binary(&BalanceResponse { balance }));
+                    }
+                    Ok(Cw20QueryMsg::Minter {}) => {
+                        return Ok(to_binary(&MinterResponse {
+                            minter: self.cw20_minter.clone(),
+                            cap: None,
+                        }))
+                    }
+                    _ => {}
+                }
+                match from_binary(msg) {
+                    Ok(Cw721QueryMsg::AllNftInfo { token_id }) => match self.nfts.get(&token_id) {
+                        Some((owner, rank)) => Ok(to_binary(&AllNftInfoResponse {
+                            access: OwnerOfResponse {
+                                owner: owner.clone(),
+                                approvals: vec![],
+                            },
+                            info: NftInfoResponse {
+                                name: token_id.clone(),
+                                description: String::new(),
+                                image: None,
+                                token_uri: None,
+                                attributes: vec![],
+                                rank: rank.clone(),
+                                stats: Default::default(),
+                            },
+                        })),
+                        None => Err(SystemError::InvalidRequest {
+                            error: format!("Unknown token {}", token_id),
+                            request: msg.clone(),
+                        }),
+                    },
+                    _ => self.base.handle_query(request),
+                }
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+
+    fn anchor_config(&self) -> ConfigResponse {
+        let empty = CanonicalAddr::default();
+        ConfigResponse {
+            contract_addr: empty.clone(),
+            owner_addr: empty.clone(),
+            aterra_contract: self.aterra_contract.clone(),
+            interest_model: empty.clone(),
+            distribution_model: empty.clone(),
+            overseer_contract: empty.clone(),
+            collector_contract: empty.clone(),
+            distributor_contract: empty,
+            stable_denom: "uusd".to_string(),
+            max_borrow_factor: Decimal256::one(),
+        }
+    }
+}
@@ -1,7 +1,12 @@
-use cosmwasm_std::{HumanAddr, CanonicalAddr, Uint128};
+use cosmwasm_std::{HumanAddr, CanonicalAddr, Decimal, Uint128};
 use cosmwasm_bignumber::{Uint256, Decimal256};
-use crate::state::{TokenData};
+use crate::state::{
+    TokenData, PauseScope, HistoryEvent, Sale, SalePhase, PackType, YieldSplit, DepositLimits,
+    ContestPayout, Recipe, CraftInput,
+};
+use cw0::{Duration, Expiration};
 use cw20::{Cw20ReceiveMsg};
+use cw721::Cw721ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +22,16 @@ pub struct InitMsg {
     pub tokens: Option<Vec<TokenData>>,
     // Number of Player NFTs to be pulled per pack
     pub pack_len: Uint128,
+    // guardian address that can pause the contract in an emergency (optional)
+    pub guardian: Option<HumanAddr>,
+    // Price of a pack in stable_denom
+    pub pack_price: Uint128,
+    // Share of the pack price credited to the referrer, at most 1
+    pub referral_rate: Decimal,
+    // Rank id of the athlete contracts minted for standard pulls, e.g. "B"
+    pub standard_rank: String,
+    // Rank id guaranteed as the first pull of premium packs, e.g. "G"
+    pub premium_rank: String,
 }
 
 /// This is like Cw721HandleMsg but we add a Mint command for an owner
@@ -25,19 +40,211 @@ pub struct InitMsg {
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
-    /// Purchase an athlete token pack
-    PurchasePack {},
+    /// Purchase an athlete token pack, paying its price in stable_denom.
+    /// The optional referrer is credited with referral_rate of the price
+    PurchasePack {
+        referrer: Option<HumanAddr>,
+        /// defaults to a standard pack
+        pack_type: Option<PackType>,
+    },
     /// Deposit Stablecoins into the contract to receive an athlete token
     DepositStable {},
-    /// Redeem Stablecoins (UST) from Anchor
-    RedeemStable {
-        //amount in uusd to be redeemed from Anchor
-        amount: Uint128,
-    },
-    /// Add athlete token contract address
+    /// Add athlete token contract address, can only be called by the owner
     AddToken {
         tokens: Vec<TokenData>,
     },
+    /// Withdraw the sender's own deposited principal from Anchor.
+    /// If Anchor lacks the liquidity, the withdrawal is queued instead.
+    /// This stays available while the contract is paused
+    WithdrawStable {
+        //amount in uusd to be withdrawn, cannot exceed the sender's deposits
+        amount: Uint128,
+    },
+    /// Pause part of the contract, can be called by the owner or the guardian
+    Pause {
+        scope: PauseScope,
+    },
+    /// Unpause part of the contract, can only be called by the owner
+    Unpause {
+        scope: PauseScope,
+    },
+    /// Send the sender's accrued referral rewards to them
+    ClaimReferral {},
+    /// Update the pack price, referral rate and the rank ids packs are minted at,
+    /// can only be called by the owner
+    UpdateConfig {
+        pack_price: Option<Uint128>,
+        referral_rate: Option<Decimal>,
+        standard_rank: Option<String>,
+        premium_rank: Option<String>,
+    },
+    /// Claim one free pack per address. If an allowlist is set, proof must contain
+    /// the hex-encoded sha-256 sibling hashes from the sender's leaf up to the root
+    ClaimStarterPack {
+        proof: Vec<String>,
+    },
+    /// Set or clear the starter pack allowlist, can only be called by the owner
+    UpdateMerkleRoot {
+        /// hex-encoded sha-256 merkle root (must be 32*2 = 64 chars)
+        merkle_root: Option<String>,
+    },
+    /// Schedule a new sale of standard packs, replacing the current one. None removes the
+    /// sale so packs can be bought at any time. Premium packs are only limited by the
+    /// dutch auction. Can only be called by the owner
+    SetSale {
+        sale: Option<SaleMsg>,
+    },
+    /// Start a dutch auction for premium packs, replacing the current one.
+    /// None stops premium sales. Can only be called by the owner
+    SetDutchAuction {
+        auction: Option<DutchAuctionMsg>,
+    },
+    /// Marks an athlete as retired and freezes their token contract.
+    /// Can only be called by the owner
+    RetireAthlete {
+        athlete_id: String,
+    },
+    /// Sets the reserve payout for burning a retired token of the given rank.
+    /// Can only be called by the owner
+    SetRetirementPayout {
+        rank: String,
+        amount: Uint128,
+    },
+    /// Adds the sent stable coins to the retirement reserve
+    FundReserve {},
+    /// Burns a retired athlete's token owned by the sender in exchange for a payout
+    /// or a replacement pack. This contract must be approved to transfer the token
+    RedeemRetired {
+        athlete_id: String,
+        token_id: String,
+        redeem_for: RedeemOption,
+    },
+    /// Redeems the yield earned on Anchor and distributes it by the yield split.
+    /// Can be called by anyone
+    Harvest {},
+    /// Update how harvested yield is split, can only be called by the owner
+    UpdateYieldSplit {
+        split: YieldSplit,
+    },
+    /// Register the cw20 share token minted for deposits. This contract must be
+    /// its minter. Can only be called once, by the owner
+    RegisterShareToken {
+        token: HumanAddr,
+    },
+    /// Share tokens sent back to this contract with Cw20HookMsg::RedeemShares
+    /// are burned and the same amount of principal is redeemed to the sender
+    Receive(Cw20ReceiveMsg),
+    /// Pay out queued withdrawals first-in first-out while Anchor has the liquidity.
+    /// Can be called by anyone
+    ProcessQueue {
+        limit: Option<u32>,
+    },
+    /// Cancel the sender's queued withdrawal, returning the amount to their deposits
+    CancelWithdrawal {
+        id: u64,
+    },
+    /// Set the TVL cap, per-address cap and minimum deposit, can only be called by the owner
+    UpdateDepositLimits {
+        limits: DepositLimits,
+    },
+    /// Set the dispute window and the multisig that can correct results,
+    /// can only be called by the owner
+    UpdateContestConfig {
+        dispute_window: Duration,
+        multisig: Option<HumanAddr>,
+    },
+    /// Enter a contest with a lineup of athlete ids, replacing the sender's lineup if they
+    /// already entered. Lineups with retired athletes are rejected, and entries close
+    /// once the contest is settled
+    EnterContest {
+        contest_id: u64,
+        lineup: Vec<String>,
+    },
+    /// Record a contest's payouts, reserving them from the prize pool.
+    /// They become claimable once the dispute window closes. Can only be called by the owner
+    SettleContest {
+        contest_id: u64,
+        payouts: Vec<ContestPayout>,
+    },
+    /// Overturn a contest's payouts during its dispute window.
+    /// Can only be called by the multisig, through an executed proposal
+    CorrectResults {
+        contest_id: u64,
+        payouts: Vec<ContestPayout>,
+    },
+    /// Claim the sender's payout of a contest whose results are final
+    ClaimPayout {
+        contest_id: u64,
+    },
+    /// Add, replace or remove (if None) a crafting recipe, can only be called by the owner
+    SetRecipe {
+        recipe_id: String,
+        recipe: Option<Recipe>,
+    },
+    /// Burn the sender's tokens listed in inputs for the recipe's output. This contract
+    /// must be an operator (ApproveAll) of the sender on every input contract, so all the
+    /// inputs are burned in the same transaction as the output is minted.
+    /// Without ApproveAll, send the inputs with SendNft and Cw721HookMsg::Craft instead
+    Craft {
+        recipe_id: String,
+        inputs: Vec<CraftInput>,
+    },
+    /// Tokens sent with Cw721HookMsg::Craft are held for the sender until all the inputs of
+    /// the recipe are received, then they are burned for its output
+    ReceiveNft(Cw721ReceiveMsg),
+    /// Return the tokens the sender sent for a recipe they have not completed
+    CancelCraft {
+        recipe_id: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw721HookMsg {
+    Craft { recipe_id: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    RedeemShares {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RedeemOption {
+    /// the rank's payout from the retirement reserve
+    Payout,
+    /// a new standard pack
+    Pack,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DutchAuctionMsg {
+    pub start_price: Uint128,
+    pub floor_price: Uint128,
+    /// start and end must both be heights or both be times
+    pub start: Expiration,
+    /// no premium packs are sold once this is expired
+    pub end: Expiration,
+    /// number of premium packs for sale
+    pub supply: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SaleMsg {
+    /// the sale opens once this is expired
+    pub start: Expiration,
+    /// the sale closes once this is expired, at a height if start is one, else at a time
+    pub end: Expiration,
+    /// if set, only presale_whitelist can buy until this is expired, in the same unit as start
+    pub presale_end: Option<Expiration>,
+    /// number of packs for sale
+    pub total_supply: u64,
+    /// number of packs a single address can buy in this sale
+    pub max_per_address: u64,
+    /// addresses allowed to buy during the presale
+    pub presale_whitelist: Vec<HumanAddr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -49,14 +256,85 @@ pub enum QueryMsg {
     TokenContract {
         athlete_id: String,
     },
-    /// Returns the total deposited stable coin amount to Anchor
+    /// Returns the principal of all depositors held in Anchor, without pack proceeds
     TotalDeposit {},
-    /// Returns a boolean if the token is mintable using the Athlete Contract's IsMintable{} Query
+    /// Returns a boolean if the standard rank of the athlete is mintable using the
+    /// Athlete Contract's IsMintable{} Query
     IsTokenMintable {
         athlete_id: String,
     },
     /// Returns the total number of Athlete Contracts saved 
     ContractCount {},
+    /// Returns which parts of the contract are paused
+    PauseStatus {},
+    /// Returns the principal deposited by the given address
+    Deposit {
+        address: HumanAddr,
+    },
+    /// Lists the packs, deposits and withdrawals of the given address, oldest first.
+    /// Return type: UserHistoryResponse
+    UserHistory {
+        address: HumanAddr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the referral rewards of the given address
+    ReferralStats {
+        address: HumanAddr,
+    },
+    /// Returns whether the given address claimed its starter pack
+    StarterPack {
+        address: HumanAddr,
+    },
+    /// Returns the sale phase at the given block and the remaining packs.
+    /// Queries cannot read the current block, so the caller passes it in
+    SaleStatus {
+        block_height: u64,
+        block_time: u64,
+    },
+    /// Returns whether the athlete retired
+    Retirement {
+        athlete_id: String,
+    },
+    /// Returns the retirement reserve and the payout per rank
+    RetirementReserve {},
+    /// Returns the deposit limits
+    DepositLimits {},
+    /// Returns the dispute window and the multisig
+    ContestConfig {},
+    /// Returns the settled results of a contest
+    ContestResult {
+        contest_id: u64,
+    },
+    /// Returns the lineup the given address entered a contest with
+    ContestEntry {
+        contest_id: u64,
+        address: HumanAddr,
+    },
+    /// Returns the queued withdrawals, oldest first
+    WithdrawalQueue {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the cw20 share token, if registered
+    ShareToken {},
+    /// Returns the yield split and the harvested totals
+    YieldSplit {},
+    /// Returns the price PurchasePack charges for the pack type at the given block
+    CurrentPrice {
+        pack_type: PackType,
+        block_height: u64,
+        block_time: u64,
+    },
+    /// Returns a crafting recipe
+    Recipe {
+        recipe_id: String,
+    },
+    /// Lists the crafting recipes, by recipe_id
+    Recipes {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -64,6 +342,100 @@ pub struct ContractCountResponse {
     pub count: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct UserHistoryResponse {
+    pub history: Vec<HistoryItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct HistoryItem {
+    /// id of the entry, use as `start_after` to paginate
+    pub id: u64,
+    /// block height the event happened at
+    pub height: u64,
+    pub event: HistoryEvent,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ReferralStatsResponse {
+    /// number of packs bought with this referrer
+    pub referrals: u64,
+    /// all referral rewards credited so far
+    pub total_earned: Uint128,
+    /// rewards that can be claimed with ClaimReferral
+    pub claimable: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct StarterPackResponse {
+    pub claimed: bool,
+    /// hex-encoded merkle root of the allowlist, None if anyone can claim
+    pub merkle_root: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct SaleStatusResponse {
+    pub phase: SalePhase,
+    /// packs left in the current sale, None if no sale is scheduled
+    pub remaining: Option<u64>,
+    pub sale: Option<Sale>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContestEntryResponse {
+    pub lineup: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RetirementResponse {
+    pub retired: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RetirementReserveResponse {
+    /// stable coins left for payouts
+    pub reserve: Uint128,
+    pub payouts: Vec<RankPayout>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RankPayout {
+    pub rank: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalQueueResponse {
+    pub withdrawals: Vec<WithdrawalItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalItem {
+    pub id: u64,
+    pub owner: HumanAddr,
+    pub amount: Uint128,
+    /// block time the withdrawal was requested at
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShareTokenResponse {
+    pub token: Option<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct YieldSplitResponse {
+    pub split: YieldSplit,
+    /// uusd held for contest prizes
+    pub prize_pool: Uint128,
+    /// uusd of yield deposited back into Anchor
+    pub compounded: Uint128,
+    /// uusd of yield harvested so far
+    pub total_harvested: Uint128,
+    /// uusd of pack sales deposited into Anchor
+    pub pack_proceeds: Uint128,
+}
+
 /// Athlete Token Message
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
@@ -74,6 +446,17 @@ pub enum TokenMsg {
         /// Describes the rank of the NFT 
         rank: String,
     },
+    /// Mints several tokens in one message
+    BatchMint { mints: Vec<TokenMint> },
+    /// Stops all minting once the athlete retired
+    Freeze {},
+}
+
+/// One token of a TokenMsg::BatchMint
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct TokenMint {
+    pub owner: HumanAddr,
+    pub rank: String,
 }
 
 /// IsMintable Message
@@ -96,18 +479,19 @@ pub enum AnchorMsg {
     RedeemStable {},
 }
 
+/// Anchor Queries
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorQueryMsg {
+    /// Returns the aUST exchange rate with the interest accrued up to block_height
+    EpochState { block_height: Option<u64> },
+}
+
 /// Anchor Responses
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct StateResponse {
-    pub total_liabilities: Decimal256,
-    pub total_reserves: Decimal256,
-    pub last_interest_updated: u64,
-    pub last_reward_updated: u64,
-    pub global_interest_index: Decimal256,
-    pub global_reward_index: Decimal256,
-    pub anc_emission_rate: Decimal256,
-    pub prev_aterra_supply: Uint256,
-    pub prev_exchange_rate: Decimal256,
+pub struct EpochStateResponse {
+    pub exchange_rate: Decimal256,
+    pub aterra_supply: Uint256,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -122,4 +506,15 @@ pub struct ConfigResponse {
     pub distributor_contract: CanonicalAddr,
     pub stable_denom: String,
     pub max_borrow_factor: Decimal256,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RecipesResponse {
+    pub recipes: Vec<RecipeItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RecipeItem {
+    pub recipe_id: String,
+    pub recipe: Recipe,
+}
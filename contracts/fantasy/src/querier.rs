@@ -10,8 +10,8 @@ pub fn compute_tax<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<Uint128> {
     let terra_querier = TerraQuerier::new(&deps.querier);
     let tax_rate = Decimal256::from((terra_querier.query_tax_rate()?).rate);
-    let tax_cap = Uint128::from((terra_querier.query_tax_cap(coin.denom.to_string())?).cap);
-    let amount = Uint128::from(coin.amount);
+    let tax_cap = (terra_querier.query_tax_cap(coin.denom.to_string())?).cap;
+    let amount = coin.amount;
     Ok(std::cmp::min(
         amount * (Decimal256::one() - Decimal256::one() / (Decimal256::one() + tax_rate)).into(),
         tax_cap,
@@ -24,8 +24,8 @@ pub fn compute_price_with_tax<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<Uint128> {
     let terra_querier = TerraQuerier::new(&deps.querier);
     let tax_rate = Decimal256::from((terra_querier.query_tax_rate()?).rate);
-    let tax_cap = Uint128::from((terra_querier.query_tax_cap(coin.denom.to_string())?).cap);
-    let amount = Uint128::from(coin.amount);
+    let tax_cap = (terra_querier.query_tax_cap(coin.denom.to_string())?).cap;
+    let amount = coin.amount;
     Ok(std::cmp::min(
         amount * (Decimal256::one() + tax_rate).into(),
         amount + tax_cap,
@@ -39,7 +39,7 @@ pub fn deduct_tax<S: Storage, A: Api, Q: Querier>(
     let tax_amount = compute_tax(deps, &coin).unwrap_or(Uint128::zero());
     Ok(Coin {
         denom: coin.denom,
-        amount: (Uint128::from(coin.amount) - tax_amount)?,
+        amount: (coin.amount - tax_amount)?,
     })
 }
 
@@ -1,7 +1,12 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{HumanAddr, ReadonlyStorage, Storage, StdResult, Uint128};
+use std::convert::TryInto;
+
+use cosmwasm_std::{
+    Binary, BlockInfo, CanonicalAddr, Decimal, HumanAddr, ReadonlyStorage, Storage, StdError, StdResult, Uint128,
+};
+use cw0::{Duration, Expiration};
 use cosmwasm_storage::{
     bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
     Singleton,
@@ -14,6 +19,33 @@ pub const TERRAND_ADDR_KEY: &[u8] = b"terrand_addr";
 pub const PACK_LEN_KEY: &[u8] = b"pack_len";
 pub const TOKEN_ADDRESSES_PREFIX: &[u8] = b"token_addresses";
 pub const CONTRACT_COUNT_KEY: &[u8] = b"contract_count";
+pub const PAUSE_KEY: &[u8] = b"pause";
+pub const DEPOSITS_PREFIX: &[u8] = b"deposits";
+pub const DEPOSIT_LIMITS_KEY: &[u8] = b"deposit_limits";
+pub const HISTORY_PREFIX: &[u8] = b"history";
+pub const HISTORY_COUNT_PREFIX: &[u8] = b"history_count";
+pub const REFERRALS_PREFIX: &[u8] = b"referrals";
+pub const STARTER_CLAIMS_PREFIX: &[u8] = b"starter_claims";
+pub const MERKLE_ROOT_KEY: &[u8] = b"merkle_root";
+pub const SALE_KEY: &[u8] = b"sale";
+pub const SALE_COUNT_KEY: &[u8] = b"sale_count";
+pub const SALE_PURCHASES_PREFIX: &[u8] = b"sale_purchases";
+pub const PRESALE_WHITELIST_PREFIX: &[u8] = b"presale_whitelist";
+pub const DUTCH_AUCTION_KEY: &[u8] = b"dutch_auction";
+pub const YIELD_SPLIT_KEY: &[u8] = b"yield_split";
+pub const TREASURY_KEY: &[u8] = b"treasury";
+pub const SHARE_TOKEN_KEY: &[u8] = b"share_token";
+pub const WITHDRAWAL_QUEUE_PREFIX: &[u8] = b"withdrawal_queue";
+pub const WITHDRAWAL_COUNT_KEY: &[u8] = b"withdrawal_count";
+pub const CONTEST_CONFIG_KEY: &[u8] = b"contest_config";
+pub const CONTEST_RESULTS_PREFIX: &[u8] = b"contest_results";
+pub const CONTEST_CLAIMS_PREFIX: &[u8] = b"contest_claims";
+pub const CONTEST_ENTRIES_PREFIX: &[u8] = b"contest_entries";
+pub const RETIRED_PREFIX: &[u8] = b"retired";
+pub const RETIREMENT_PAYOUTS_PREFIX: &[u8] = b"retirement_payouts";
+pub const RETIREMENT_RESERVE_KEY: &[u8] = b"retirement_reserve";
+pub const RECIPES_PREFIX: &[u8] = b"recipes";
+pub const CRAFT_ESCROW_PREFIX: &[u8] = b"craft_escrow";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
@@ -25,6 +57,354 @@ pub struct State {
     pub terrand_addr: HumanAddr,
     /// number of NFT players to be pulled per pack
     pub pack_len: Uint128,
+    /// owner can pause and unpause the contract
+    pub owner: HumanAddr,
+    /// guardian can only pause the contract (optional)
+    pub guardian: Option<HumanAddr>,
+    /// price of a pack in stable_denom
+    pub pack_price: Uint128,
+    /// share of the pack price credited to the referrer
+    pub referral_rate: Decimal,
+    /// rank id minted for standard pulls, from the athlete contracts' rank table
+    pub standard_rank: String,
+    /// rank id guaranteed as the first pull of premium packs
+    pub premium_rank: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct ReferralInfo {
+    /// number of packs bought with this referrer
+    pub referrals: u64,
+    /// all referral rewards credited so far
+    pub total_earned: Uint128,
+    /// rewards that have not been claimed yet
+    pub claimable: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContestConfig {
+    /// how long settled results can still be corrected before payouts open
+    pub dispute_window: Duration,
+    /// cw3-fixed-multisig contract allowed to correct results during the window
+    pub multisig: Option<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContestPayout {
+    pub address: HumanAddr,
+    /// uusd paid out of the prize pool
+    pub amount: Uint128,
+}
+
+/// Settled results of a contest
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContestResult {
+    pub payouts: Vec<ContestPayout>,
+    /// sum of all payouts, reserved from the prize pool
+    pub total: Uint128,
+    /// results are final and payouts can be claimed once this is expired
+    pub final_at: Expiration,
+    /// set once the multisig overturned the results
+    pub corrected: bool,
+}
+
+/// Limits on DepositStable for a guarded launch, all in uusd after tax
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct DepositLimits {
+    /// maximum total principal held by the contract
+    pub tvl_cap: Option<Uint128>,
+    /// maximum principal a single address can hold
+    pub address_cap: Option<Uint128>,
+    /// smallest accepted deposit
+    pub min_deposit: Uint128,
+}
+
+/// A withdrawal waiting for Anchor to have enough liquidity
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueuedWithdrawal {
+    pub owner: HumanAddr,
+    /// uusd of principal to withdraw, already taken off the owner's deposits
+    pub amount: Uint128,
+    /// block time the withdrawal was requested at
+    pub time: u64,
+}
+
+/// Basis points of harvested yield per destination, summing up to 10000
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct YieldSplit {
+    /// kept in the contract for contest prizes
+    pub prize_pool_bps: u64,
+    /// sent to the protocol treasury address
+    pub treasury_bps: u64,
+    /// sent to the NFT staking rewards address
+    pub stakers_bps: u64,
+    /// deposited back into Anchor
+    pub redeposit_bps: u64,
+    pub treasury: Option<HumanAddr>,
+    pub stakers: Option<HumanAddr>,
+}
+
+/// The default split keeps all yield in the prize pool
+impl Default for YieldSplit {
+    fn default() -> Self {
+        YieldSplit {
+            prize_pool_bps: 10_000,
+            treasury_bps: 0,
+            stakers_bps: 0,
+            redeposit_bps: 0,
+            treasury: None,
+            stakers: None,
+        }
+    }
+}
+
+/// Totals of the harvested yield kept by the contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Treasury {
+    /// uusd held for contest prizes
+    pub prize_pool: Uint128,
+    /// uusd of yield deposited back into Anchor, not owed to any depositor
+    pub compounded: Uint128,
+    /// uusd of yield harvested so far
+    pub total_harvested: Uint128,
+    /// uusd of pack sales deposited into Anchor, also not owed to any depositor
+    #[serde(default)]
+    pub pack_proceeds: Uint128,
+}
+
+/// An athlete token minted to the user when opening a pack
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PulledAthlete {
+    // ID of the athlete token
+    pub athlete_id: String,
+    // Contract address of the athlete token
+    pub contract_addr: HumanAddr,
+    /// Describes the rank of the minted NFT
+    pub rank: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryEvent {
+    PurchasePack {
+        athletes: Vec<PulledAthlete>,
+    },
+    StarterPack {
+        athletes: Vec<PulledAthlete>,
+    },
+    Deposit {
+        amount: Uint128,
+    },
+    /// principal redeemed from Anchor and sent to the user
+    Withdraw {
+        amount: Uint128,
+    },
+    /// a retired athlete's token was burned for a payout from the reserve
+    RetirementPayout {
+        token_id: String,
+        amount: Uint128,
+    },
+    /// a retired athlete's token was burned for a replacement pack
+    ReplacementPack {
+        token_id: String,
+        athletes: Vec<PulledAthlete>,
+    },
+    /// tokens were burned for the output of a crafting recipe
+    Craft {
+        recipe_id: String,
+        burned: Vec<String>,
+    },
+}
+
+/// Burning the inputs, possibly from several athlete contracts, mints one output token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Recipe {
+    pub inputs: Vec<RecipeInput>,
+    pub output: RecipeOutput,
+}
+
+/// `count` tokens of `rank` from the `contract` athlete contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecipeInput {
+    pub contract: HumanAddr,
+    pub rank: String,
+    pub count: u32,
+}
+
+/// The crafted token, minted by this contract so it must be the minter of `contract`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecipeOutput {
+    pub contract: HumanAddr,
+    pub rank: String,
+}
+
+/// One token burned by crafting
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CraftInput {
+    pub contract: HumanAddr,
+    pub token_id: String,
+}
+
+/// A single entry of a user's history, stored under (user, id)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HistoryEntry {
+    /// block height the event happened at
+    pub height: u64,
+    pub event: HistoryEvent,
+}
+
+/// A scheduled pack sale. Without one, packs can be bought at any time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Sale {
+    /// increases with every new sale, so per-address limits start over
+    pub id: u64,
+    /// the sale opens once this is expired
+    pub start: Expiration,
+    /// the sale closes once this is expired
+    pub end: Expiration,
+    /// if set, only whitelisted addresses can buy until this is expired
+    pub presale_end: Option<Expiration>,
+    /// number of packs for sale
+    pub total_supply: u64,
+    /// number of packs a single address can buy in this sale
+    pub max_per_address: u64,
+    /// number of packs sold so far
+    pub sold: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SalePhase {
+    /// no sale is scheduled, packs can be bought at any time
+    Unrestricted,
+    NotStarted,
+    Presale,
+    Public,
+    SoldOut,
+    Ended,
+}
+
+impl Sale {
+    pub fn phase(&self, block: &BlockInfo) -> SalePhase {
+        if !self.start.is_expired(block) {
+            SalePhase::NotStarted
+        } else if self.end.is_expired(block) {
+            SalePhase::Ended
+        } else if self.remaining() == 0 {
+            SalePhase::SoldOut
+        } else if self.presale_end.is_some_and(|end| !end.is_expired(block)) {
+            SalePhase::Presale
+        } else {
+            SalePhase::Public
+        }
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.total_supply.saturating_sub(self.sold)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PackType {
+    /// sold at the fixed pack_price
+    Standard,
+    /// limited gold-guaranteed packs sold through a dutch auction, outside of any
+    /// scheduled sale
+    Premium,
+}
+
+/// Price of premium packs decays linearly from start_price at `start`
+/// to floor_price at `end`, when the auction closes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DutchAuction {
+    pub start_price: Uint128,
+    pub floor_price: Uint128,
+    /// start and end must both be heights or both be times
+    pub start: Expiration,
+    pub end: Expiration,
+    /// number of premium packs for sale
+    pub supply: u64,
+    /// number of premium packs sold so far
+    pub sold: u64,
+}
+
+impl DutchAuction {
+    pub fn price_at(&self, block: &BlockInfo) -> StdResult<Uint128> {
+        let (now, start, end) = match (self.start, self.end) {
+            (Expiration::AtHeight(start), Expiration::AtHeight(end)) => (block.height, start, end),
+            (Expiration::AtTime(start), Expiration::AtTime(end)) => (block.time, start, end),
+            _ => {
+                return Err(StdError::generic_err(
+                    "Auction must start and end both at a height or both at a time",
+                ))
+            }
+        };
+        if end <= start {
+            return Err(StdError::generic_err("Auction must end after it starts"));
+        }
+
+        if now <= start {
+            Ok(self.start_price)
+        } else if now >= end {
+            Ok(self.floor_price)
+        } else {
+            let range = (self.start_price - self.floor_price)?.u128();
+            let decay = range * u128::from(now - start) / u128::from(end - start);
+            self.start_price - Uint128(decay)
+        }
+    }
+}
+
+/// The parts of the contract that can be paused independently
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseScope {
+    All,
+    /// Packs, starter packs, crafting and referral reward claims
+    Purchases,
+    Deposits,
+    /// Harvest and redeeming retired tokens
+    Redeems,
+    /// Entering and settling contests. Payouts of settled contests can still be claimed
+    Contests,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct PauseState {
+    pub purchases: bool,
+    pub deposits: bool,
+    pub redeems: bool,
+    pub contests: bool,
+}
+
+impl PauseState {
+    /// returns true if the given scope is currently paused
+    pub fn is_paused(&self, scope: &PauseScope) -> bool {
+        match scope {
+            PauseScope::All => self.purchases && self.deposits && self.redeems && self.contests,
+            PauseScope::Purchases => self.purchases,
+            PauseScope::Deposits => self.deposits,
+            PauseScope::Redeems => self.redeems,
+            PauseScope::Contests => self.contests,
+        }
+    }
+
+    /// sets the paused flag for the given scope, All touches every flag
+    pub fn set(&mut self, scope: &PauseScope, paused: bool) {
+        match scope {
+            PauseScope::All => {
+                self.purchases = paused;
+                self.deposits = paused;
+                self.redeems = paused;
+                self.contests = paused;
+            }
+            PauseScope::Purchases => self.purchases = paused,
+            PauseScope::Deposits => self.deposits = paused,
+            PauseScope::Redeems => self.redeems = paused,
+            PauseScope::Contests => self.contests = paused,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -45,26 +425,321 @@ pub fn state_read<S: ReadonlyStorage>(
     singleton_read(storage, STATE_KEY)
 }
 
-pub fn total_deposit<S: Storage>(storage: &mut S) -> Singleton<S, u64> {
+pub fn total_deposit<S: Storage>(storage: &mut S) -> Singleton<S, Uint128> {
     singleton(storage, TOTAL_DEPOSIT_KEY)
 }
 
-pub fn total_deposit_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, u64> {
-    singleton_read(storage, TOTAL_DEPOSIT_KEY)
+/// total_deposit used to be stored as a u64 number. Contracts instantiated before it became
+/// a Uint128 string still hold the old encoding until the next deposit or withdrawal
+pub fn load_total_deposit<S: ReadonlyStorage>(storage: &S) -> StdResult<Uint128> {
+    match singleton_read(storage, TOTAL_DEPOSIT_KEY).load() {
+        Err(StdError::ParseErr { .. }) => {
+            let legacy: u64 = singleton_read(storage, TOTAL_DEPOSIT_KEY).load()?;
+            Ok(Uint128::from(u128::from(legacy)))
+        }
+        res => res,
+    }
 }
 
-pub fn increase_deposit<S: Storage>(storage: &mut S, amount: u64) -> StdResult<u64> {
-    let val = total_deposit_read(storage).load()? + amount;
+pub fn increase_deposit<S: Storage>(storage: &mut S, amount: Uint128) -> StdResult<Uint128> {
+    let val = load_total_deposit(storage)? + amount;
     total_deposit(storage).save(&val)?;
     Ok(val)
 }
 
-pub fn reduce_deposit<S: Storage>(storage: &mut S, amount: u64) -> StdResult<u64> {
-    let val = total_deposit_read(storage).load()? - amount;
+pub fn reduce_deposit<S: Storage>(storage: &mut S, amount: Uint128) -> StdResult<Uint128> {
+    let val = (load_total_deposit(storage)? - amount)?;
     total_deposit(storage).save(&val)?;
     Ok(val)
 }
 
+pub fn deposit_limits<S: Storage>(storage: &mut S) -> Singleton<S, DepositLimits> {
+    singleton(storage, DEPOSIT_LIMITS_KEY)
+}
+
+pub fn deposit_limits_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, DepositLimits> {
+    singleton_read(storage, DEPOSIT_LIMITS_KEY)
+}
+
+pub fn pause_state<S: Storage>(storage: &mut S) -> Singleton<S, PauseState> {
+    singleton(storage, PAUSE_KEY)
+}
+
+pub fn pause_state_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, PauseState> {
+    singleton_read(storage, PAUSE_KEY)
+}
+
+/// Returns an error if the given scope is paused
+pub fn assert_not_paused<S: ReadonlyStorage>(storage: &S, scope: PauseScope) -> StdResult<()> {
+    let paused = pause_state_read(storage).may_load()?.unwrap_or_default();
+    if paused.is_paused(&scope) {
+        return Err(StdError::generic_err(format!("{:?} are paused", scope)));
+    }
+    Ok(())
+}
+
+pub fn contest_config<S: Storage>(storage: &mut S) -> Singleton<S, ContestConfig> {
+    singleton(storage, CONTEST_CONFIG_KEY)
+}
+
+pub fn contest_config_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, ContestConfig> {
+    singleton_read(storage, CONTEST_CONFIG_KEY)
+}
+
+pub fn contest_results<S: Storage>(storage: &mut S) -> Bucket<S, ContestResult> {
+    bucket(CONTEST_RESULTS_PREFIX, storage)
+}
+
+pub fn contest_results_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, ContestResult> {
+    bucket_read(CONTEST_RESULTS_PREFIX, storage)
+}
+
+/// Addresses that claimed their payout of the given contest
+pub fn contest_claims<S: Storage>(storage: &mut S, contest_id: u64) -> Bucket<S, bool> {
+    Bucket::multilevel(&[CONTEST_CLAIMS_PREFIX, &contest_id.to_be_bytes()], storage)
+}
+
+pub fn contest_claims_read<S: ReadonlyStorage>(storage: &S, contest_id: u64) -> ReadonlyBucket<S, bool> {
+    ReadonlyBucket::multilevel(&[CONTEST_CLAIMS_PREFIX, &contest_id.to_be_bytes()], storage)
+}
+
+/// Lineup of athlete ids each address entered the given contest with
+pub fn contest_entries<S: Storage>(storage: &mut S, contest_id: u64) -> Bucket<S, Vec<String>> {
+    Bucket::multilevel(&[CONTEST_ENTRIES_PREFIX, &contest_id.to_be_bytes()], storage)
+}
+
+pub fn contest_entries_read<S: ReadonlyStorage>(
+    storage: &S,
+    contest_id: u64,
+) -> ReadonlyBucket<S, Vec<String>> {
+    ReadonlyBucket::multilevel(&[CONTEST_ENTRIES_PREFIX, &contest_id.to_be_bytes()], storage)
+}
+
+/// Queued withdrawals keyed by an increasing id, so ascending order is first-in first-out
+pub fn withdrawal_queue<S: Storage>(storage: &mut S) -> Bucket<S, QueuedWithdrawal> {
+    bucket(WITHDRAWAL_QUEUE_PREFIX, storage)
+}
+
+pub fn withdrawal_queue_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, QueuedWithdrawal> {
+    bucket_read(WITHDRAWAL_QUEUE_PREFIX, storage)
+}
+
+pub fn next_withdrawal_id<S: Storage>(storage: &mut S) -> StdResult<u64> {
+    let mut s = singleton(storage, WITHDRAWAL_COUNT_KEY);
+    let id: u64 = s.may_load()?.unwrap_or_default() + 1;
+    s.save(&id)?;
+    Ok(id)
+}
+
+/// cw20 token minted 1:1 for deposited principal, with this contract as minter
+pub fn share_token<S: Storage>(storage: &mut S) -> Singleton<S, HumanAddr> {
+    singleton(storage, SHARE_TOKEN_KEY)
+}
+
+pub fn share_token_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, HumanAddr> {
+    singleton_read(storage, SHARE_TOKEN_KEY)
+}
+
+pub fn yield_split<S: Storage>(storage: &mut S) -> Singleton<S, YieldSplit> {
+    singleton(storage, YIELD_SPLIT_KEY)
+}
+
+pub fn yield_split_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, YieldSplit> {
+    singleton_read(storage, YIELD_SPLIT_KEY)
+}
+
+pub fn treasury<S: Storage>(storage: &mut S) -> Singleton<S, Treasury> {
+    singleton(storage, TREASURY_KEY)
+}
+
+pub fn treasury_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, Treasury> {
+    singleton_read(storage, TREASURY_KEY)
+}
+
+/// Athletes that retired or left the league, keyed by athlete_id
+pub fn retired<S: Storage>(storage: &mut S) -> Bucket<S, bool> {
+    bucket(RETIRED_PREFIX, storage)
+}
+
+pub fn retired_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, bool> {
+    bucket_read(RETIRED_PREFIX, storage)
+}
+
+pub fn is_retired<S: ReadonlyStorage>(storage: &S, athlete_id: &str) -> StdResult<bool> {
+    Ok(retired_read(storage).may_load(athlete_id.as_bytes())?.unwrap_or_default())
+}
+
+/// Returns an error if a lineup includes a retired athlete
+pub fn assert_lineup_active<S: ReadonlyStorage>(storage: &S, athlete_ids: &[String]) -> StdResult<()> {
+    for athlete_id in athlete_ids {
+        if is_retired(storage, athlete_id)? {
+            return Err(StdError::generic_err(format!("Athlete {} is retired", athlete_id)));
+        }
+    }
+    Ok(())
+}
+
+/// Amount paid out of the reserve for a burned retired token, keyed by rank
+pub fn retirement_payouts<S: Storage>(storage: &mut S) -> Bucket<S, Uint128> {
+    bucket(RETIREMENT_PAYOUTS_PREFIX, storage)
+}
+
+pub fn retirement_payouts_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, Uint128> {
+    bucket_read(RETIREMENT_PAYOUTS_PREFIX, storage)
+}
+
+/// Stable coins set aside for retirement payouts
+pub fn retirement_reserve<S: Storage>(storage: &mut S) -> Singleton<S, Uint128> {
+    singleton(storage, RETIREMENT_RESERVE_KEY)
+}
+
+pub fn retirement_reserve_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, Uint128> {
+    singleton_read(storage, RETIREMENT_RESERVE_KEY)
+}
+
+/// Crafting recipes, keyed by recipe_id
+pub fn recipes<S: Storage>(storage: &mut S) -> Bucket<S, Recipe> {
+    bucket(RECIPES_PREFIX, storage)
+}
+
+pub fn recipes_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, Recipe> {
+    bucket_read(RECIPES_PREFIX, storage)
+}
+
+/// Principal deposited by each user (in uusd, after tax)
+pub fn deposits<S: Storage>(storage: &mut S) -> Bucket<S, Uint128> {
+    bucket(DEPOSITS_PREFIX, storage)
+}
+
+pub fn deposits_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, Uint128> {
+    bucket_read(DEPOSITS_PREFIX, storage)
+}
+
+pub fn referrals<S: Storage>(storage: &mut S) -> Bucket<S, ReferralInfo> {
+    bucket(REFERRALS_PREFIX, storage)
+}
+
+pub fn referrals_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, ReferralInfo> {
+    bucket_read(REFERRALS_PREFIX, storage)
+}
+
+/// Addresses that already claimed their free starter pack
+pub fn starter_claims<S: Storage>(storage: &mut S) -> Bucket<S, bool> {
+    bucket(STARTER_CLAIMS_PREFIX, storage)
+}
+
+pub fn starter_claims_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, bool> {
+    bucket_read(STARTER_CLAIMS_PREFIX, storage)
+}
+
+/// sha-256 merkle root of the starter pack allowlist, unset if anyone can claim
+pub fn starter_merkle_root<S: Storage>(storage: &mut S) -> Singleton<S, Binary> {
+    singleton(storage, MERKLE_ROOT_KEY)
+}
+
+pub fn starter_merkle_root_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, Binary> {
+    singleton_read(storage, MERKLE_ROOT_KEY)
+}
+
+pub fn dutch_auction<S: Storage>(storage: &mut S) -> Singleton<S, DutchAuction> {
+    singleton(storage, DUTCH_AUCTION_KEY)
+}
+
+pub fn dutch_auction_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, DutchAuction> {
+    singleton_read(storage, DUTCH_AUCTION_KEY)
+}
+
+pub fn sale<S: Storage>(storage: &mut S) -> Singleton<S, Sale> {
+    singleton(storage, SALE_KEY)
+}
+
+pub fn sale_read<S: ReadonlyStorage>(storage: &S) -> ReadonlySingleton<S, Sale> {
+    singleton_read(storage, SALE_KEY)
+}
+
+pub fn next_sale_id<S: Storage>(storage: &mut S) -> StdResult<u64> {
+    let mut s = singleton(storage, SALE_COUNT_KEY);
+    let id: u64 = s.may_load()?.unwrap_or_default() + 1;
+    s.save(&id)?;
+    Ok(id)
+}
+
+/// Number of packs each address bought in the given sale
+pub fn sale_purchases<S: Storage>(storage: &mut S, sale_id: u64) -> Bucket<S, u64> {
+    Bucket::multilevel(&[SALE_PURCHASES_PREFIX, &sale_id.to_be_bytes()], storage)
+}
+
+pub fn sale_purchases_read<S: ReadonlyStorage>(
+    storage: &S,
+    sale_id: u64,
+) -> ReadonlyBucket<S, u64> {
+    ReadonlyBucket::multilevel(&[SALE_PURCHASES_PREFIX, &sale_id.to_be_bytes()], storage)
+}
+
+/// Addresses allowed to buy during the presale of the given sale
+pub fn presale_whitelist<S: Storage>(storage: &mut S, sale_id: u64) -> Bucket<S, bool> {
+    Bucket::multilevel(&[PRESALE_WHITELIST_PREFIX, &sale_id.to_be_bytes()], storage)
+}
+
+pub fn presale_whitelist_read<S: ReadonlyStorage>(
+    storage: &S,
+    sale_id: u64,
+) -> ReadonlyBucket<S, bool> {
+    ReadonlyBucket::multilevel(&[PRESALE_WHITELIST_PREFIX, &sale_id.to_be_bytes()], storage)
+}
+
+/// Tokens sent to this contract for a recipe that is not complete yet, by recipe_id
+pub fn craft_escrow<'a, S: Storage>(
+    storage: &'a mut S,
+    owner: &CanonicalAddr,
+) -> Bucket<'a, S, Vec<CraftInput>> {
+    Bucket::multilevel(&[CRAFT_ESCROW_PREFIX, owner.as_slice()], storage)
+}
+
+pub fn craft_escrow_read<'a, S: ReadonlyStorage>(
+    storage: &'a S,
+    owner: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, Vec<CraftInput>> {
+    ReadonlyBucket::multilevel(&[CRAFT_ESCROW_PREFIX, owner.as_slice()], storage)
+}
+
+pub fn history<'a, S: Storage>(
+    storage: &'a mut S,
+    owner: &CanonicalAddr,
+) -> Bucket<'a, S, HistoryEntry> {
+    Bucket::multilevel(&[HISTORY_PREFIX, owner.as_slice()], storage)
+}
+
+pub fn history_read<'a, S: ReadonlyStorage>(
+    storage: &'a S,
+    owner: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, HistoryEntry> {
+    ReadonlyBucket::multilevel(&[HISTORY_PREFIX, owner.as_slice()], storage)
+}
+
+/// Appends an entry to the owner's history and returns its id
+pub fn append_history<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    entry: &HistoryEntry,
+) -> StdResult<u64> {
+    let mut counter = bucket(HISTORY_COUNT_PREFIX, storage);
+    let id: u64 = counter.may_load(owner.as_slice())?.unwrap_or_default() + 1;
+    counter.save(owner.as_slice(), &id)?;
+    history(storage, owner).save(&id.to_be_bytes(), entry)?;
+    Ok(id)
+}
+
+/// Reads an id stored as 8 big-endian bytes, erroring on keys of any other length
+pub fn parse_id(data: &[u8]) -> StdResult<u64> {
+    match data.try_into() {
+        Ok(bytes) => Ok(u64::from_be_bytes(bytes)),
+        Err(_) => Err(StdError::generic_err(
+            "Corrupted data found. 8 byte expected.",
+        )),
+    }
+}
+
 pub fn token_addresses<S: Storage>(storage: &mut S) -> Bucket<S, HumanAddr> {
     bucket(TOKEN_ADDRESSES_PREFIX, storage)
 }
@@ -8,7 +8,7 @@ use cosmwasm_std::{
 use crate::{
     AllNftInfoResponse, Approval, ApprovedForAllResponse, ContractInfoResponse, Cw721HandleMsg,
     Cw721QueryMsg, NftInfoResponse, OwnerOfResponse,
-    NumTokensResponse, TokensResponse,
+    NumTokensResponse, RankSupplyResponse, TokensResponse,
 };
 
 /// Cw721Contract is a wrapper around HumanAddr that provides a lot of helpers
@@ -81,20 +81,21 @@ impl Cw721Contract {
         Ok(res.operators)
     }
 
-    pub fn base_tokens<Q: Querier>(&self, querier: &Q) -> StdResult<u64> {
-        let req = Cw721QueryMsg::BaseTokens {};
-        let res: NumTokensResponse = self.query(querier, req)?;
-        Ok(res.count)
-    }
-
-    pub fn silver_tokens<Q: Querier>(&self, querier: &Q) -> StdResult<u64> {
-        let req = Cw721QueryMsg::SilverTokens {};
-        let res: NumTokensResponse = self.query(querier, req)?;
-        Ok(res.count)
+    pub fn rank_supply<Q: Querier, T: Into<String>>(
+        &self,
+        querier: &Q,
+        rank: T,
+    ) -> StdResult<RankSupplyResponse> {
+        let req = Cw721QueryMsg::RankSupply { rank: rank.into() };
+        self.query(querier, req)
     }
 
-    pub fn gold_tokens<Q: Querier>(&self, querier: &Q) -> StdResult<u64> {
-        let req = Cw721QueryMsg::GoldTokens {};
+    pub fn burned_tokens<Q: Querier, T: Into<String>>(
+        &self,
+        querier: &Q,
+        rank: T,
+    ) -> StdResult<u64> {
+        let req = Cw721QueryMsg::BurnedTokens { rank: rank.into() };
         let res: NumTokensResponse = self.query(querier, req)?;
         Ok(res.count)
     }
@@ -130,15 +131,17 @@ impl Cw721Contract {
     }
 
     /// With enumerable extension
-    pub fn list_base_tokens<Q: Querier, T: Into<HumanAddr>>(
+    pub fn tokens<Q: Querier, T: Into<HumanAddr>>(
         &self,
         querier: &Q,
         owner: T,
+        rank: Option<String>,
         start_after: Option<String>,
         limit: Option<u32>,
     ) -> StdResult<TokensResponse> {
-        let req = Cw721QueryMsg::ListBaseTokens {
+        let req = Cw721QueryMsg::Tokens {
             owner: owner.into(),
+            rank,
             start_after,
             limit,
         };
@@ -146,86 +149,40 @@ impl Cw721Contract {
     }
 
     /// With enumerable extension
-    pub fn all_base_tokens<Q: Querier>(
+    pub fn all_tokens<Q: Querier>(
         &self,
         querier: &Q,
         start_after: Option<String>,
         limit: Option<u32>,
     ) -> StdResult<TokensResponse> {
-        let req = Cw721QueryMsg::AllBaseTokens { start_after, limit };
+        let req = Cw721QueryMsg::AllTokens { start_after, limit };
         self.query(querier, req)
     }
 
     /// With enumerable extension
-    pub fn list_silver_tokens<Q: Querier, T: Into<HumanAddr>>(
+    pub fn tokens_by_rank<Q: Querier, T: Into<String>>(
         &self,
         querier: &Q,
-        owner: T,
+        rank: T,
         start_after: Option<String>,
         limit: Option<u32>,
     ) -> StdResult<TokensResponse> {
-        let req = Cw721QueryMsg::ListSilverTokens {
-            owner: owner.into(),
+        let req = Cw721QueryMsg::TokensByRank {
+            rank: rank.into(),
             start_after,
             limit,
         };
         self.query(querier, req)
     }
 
-    /// With enumerable extension
-    pub fn all_silver_tokens<Q: Querier>(
-        &self,
-        querier: &Q,
-        start_after: Option<String>,
-        limit: Option<u32>,
-    ) -> StdResult<TokensResponse> {
-        let req = Cw721QueryMsg::AllSilverTokens { start_after, limit };
-        self.query(querier, req)
-    }
-
-    /// With enumerable extension
-    pub fn list_gold_tokens<Q: Querier, T: Into<HumanAddr>>(
-        &self,
-        querier: &Q,
-        owner: T,
-        start_after: Option<String>,
-        limit: Option<u32>,
-    ) -> StdResult<TokensResponse> {
-        let req = Cw721QueryMsg::ListGoldTokens {
-            owner: owner.into(),
-            start_after,
-            limit,
-        };
-        self.query(querier, req)
-    }
-
-    /// With enumerable extension
-    pub fn all_gold_tokens<Q: Querier>(
-        &self,
-        querier: &Q,
-        start_after: Option<String>,
-        limit: Option<u32>,
-    ) -> StdResult<TokensResponse> {
-        let req = Cw721QueryMsg::AllGoldTokens { start_after, limit };
-        self.query(querier, req)
-    }
-
     /// returns true if the contract supports the metadata extension
     pub fn has_metadata<Q: Querier>(&self, querier: &Q) -> bool {
         self.contract_info(querier).is_ok()
     }
 
     /// returns true if the contract supports the enumerable extension
-    pub fn has_base_enumerable<Q: Querier>(&self, querier: &Q) -> bool {
-        self.list_base_tokens(querier, self.addr(), None, Some(1)).is_ok()
-    }
-
-    pub fn has_silver_enumerable<Q: Querier>(&self, querier: &Q) -> bool {
-        self.list_silver_tokens(querier, self.addr(), None, Some(1)).is_ok()
-    }
-
-    pub fn has_gold_enumerable<Q: Querier>(&self, querier: &Q) -> bool {
-        self.list_gold_tokens(querier, self.addr(), None, Some(1)).is_ok()
+    pub fn has_enumerable<Q: Querier>(&self, querier: &Q) -> bool {
+        self.tokens(querier, self.addr(), None, None, Some(1)).is_ok()
     }
 }
 
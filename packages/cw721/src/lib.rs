@@ -10,9 +10,9 @@ pub use crate::msg::Cw721HandleMsg;
 pub use crate::query::{
     AllNftInfoResponse, Approval, ApprovedForAllResponse, ContractInfoResponse, Cw721QueryMsg,
     NftInfoResponse,OwnerOfResponse,
-    NumTokensResponse, TokensResponse,
+    NumTokensResponse, RankSupplyResponse, TokenStats, TokensResponse, Trait,
 };
-pub use crate::receiver::Cw721ReceiveMsg;
+pub use crate::receiver::{parse_receive_nft, Cw721ReceiveMsg};
 
 #[cfg(test)]
 mod tests {
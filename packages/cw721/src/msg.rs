@@ -19,6 +19,17 @@ pub enum Cw721HandleMsg {
         token_id: String,
         msg: Option<Binary>,
     },
+    /// Transfers several tokens to the same recipient at once
+    BatchTransferNft {
+        recipient: HumanAddr,
+        token_ids: Vec<String>,
+    },
+    /// Sends several tokens to the same contract at once, triggering the action for each
+    BatchSendNft {
+        contract: HumanAddr,
+        token_ids: Vec<String>,
+        msg: Option<Binary>,
+    },
     /// Allows operator to transfer / send the token from the owner's account.
     /// If expiration is set, then this allowance has a time/height limit
     Approve {
@@ -39,4 +50,6 @@ pub enum Cw721HandleMsg {
     },
     /// Remove previously granted ApproveAll permission
     RevokeAll { operator: HumanAddr },
+    /// Destroys a token. Can be called by anyone allowed to transfer it
+    Burn { token_id: String },
 }
@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::HumanAddr;
+use cosmwasm_std::{HumanAddr, Uint128};
 use cw0::Expiration;
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -18,14 +18,12 @@ pub enum Cw721QueryMsg {
         limit: Option<u32>,
     },
 
-    /// Total number of base tokens issued
-    BaseTokens {},
+    /// Number of tokens of the given rank in supply, burned and its cap
+    /// Return type: RankSupplyResponse
+    RankSupply { rank: String },
 
-    /// Total number of silver tokens issued
-    SilverTokens {},
-
-    /// Total number of gold tokens issued
-    GoldTokens {},
+    /// Total number of tokens of the given rank that were burned
+    BurnedTokens { rank: String },
 
     /// With MetaData Extension.
     /// Returns top-level metadata about the contract: `ContractInfoResponse`
@@ -40,39 +38,26 @@ pub enum Cw721QueryMsg {
     AllNftInfo { token_id: String },
 
     /// With Enumerable extension.
-    /// Returns all tokens owned by the given address, [] if unset.
+    /// Returns all tokens owned by the given address, optionally filtered by rank.
     /// Return type: TokensResponse.
-    ListBaseTokens {
+    Tokens {
         owner: HumanAddr,
+        rank: Option<String>,
         start_after: Option<String>,
         limit: Option<u32>,
     },
     /// With Enumerable extension.
     /// Requires pagination. Lists all token_ids controlled by the contract.
     /// Return type: TokensResponse.
-    AllBaseTokens {
-        start_after: Option<String>,
-        limit: Option<u32>,
-    },
-
-    ListSilverTokens {
-        owner: HumanAddr,
-        start_after: Option<String>,
-        limit: Option<u32>,
-    },
-
-    AllSilverTokens {
+    AllTokens {
         start_after: Option<String>,
         limit: Option<u32>,
     },
-
-    ListGoldTokens {
-        owner: HumanAddr,
-        start_after: Option<String>,
-        limit: Option<u32>,
-    },
-
-    AllGoldTokens {
+    /// With Enumerable extension.
+    /// Requires pagination. Lists the token_ids of the given rank.
+    /// Return type: TokensResponse.
+    TokensByRank {
+        rank: String,
         start_after: Option<String>,
         limit: Option<u32>,
     },
@@ -108,15 +93,38 @@ pub struct ContractInfoResponse {
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct NftInfoResponse {
     /// Identifies the asset to which this NFT represents
-    // pub name: String,
+    pub name: String,
     /// Describes the asset to which this NFT represents
-    // pub description: String,
+    pub description: String,
     /// "A URI pointing to a resource with mime type image/* representing the asset to which this
     /// NFT represents. Consider making any images at a width between 320 and 1080 pixels and aspect
     /// ratio between 1.91:1 and 4:5 inclusive.
     /// TODO: Use https://docs.rs/url_serde for type-safety
-    // pub image: Option<String>,
+    pub image: Option<String>,
+    /// The collection's base URI followed by the token_id, if the collection has one
+    pub token_uri: Option<String>,
+    /// Traits of the asset, e.g. season or jersey number
+    pub attributes: Vec<Trait>,
     pub rank: String,
+    /// Progression of the athlete from the games it played
+    #[serde(default)]
+    pub stats: TokenStats,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema, Debug)]
+pub struct TokenStats {
+    pub xp: u64,
+    pub games_played: u64,
+    pub best_score: u64,
+    /// Starts at 1 and goes up as the xp passes the contract's level thresholds
+    pub level: u32,
+}
+
+/// One entry of the metadata `attributes` list
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Trait {
+    pub trait_type: String,
+    pub value: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -132,6 +140,16 @@ pub struct NumTokensResponse {
     pub count: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RankSupplyResponse {
+    /// Tokens of the rank that exist now
+    pub supply: u64,
+    /// Tokens of the rank that were burned
+    pub burned: u64,
+    /// Maximum number of tokens of the rank in supply
+    pub cap: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct TokensResponse {
     /// Contains all token_ids in lexicographical ordering
@@ -1,14 +1,19 @@
 use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{to_binary, Binary, CosmosMsg, HumanAddr, StdResult, Uint128, WasmMsg};
+use cosmwasm_std::{
+    from_binary, to_binary, Api, Binary, CanonicalAddr, CosmosMsg, Env, HumanAddr, StdError,
+    StdResult, WasmMsg,
+};
 
-/// Cw721ReceiveMsg should be de/serialized under `Receive()` variant in a HandleMsg
+/// Cw721ReceiveMsg should be de/serialized under `ReceiveNft()` variant in a HandleMsg
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub struct Cw721ReceiveMsg {
+    /// The account that sent the token
     pub sender: HumanAddr,
-    pub amount: Uint128,
+    pub token_id: String,
     pub msg: Option<Binary>,
 }
 
@@ -31,6 +36,27 @@ impl Cw721ReceiveMsg {
     }
 }
 
+/// Checks that a ReceiveNft comes from one of the `allowed` cw721 contracts, whose
+/// address is `env.message.sender`, and decodes its `msg`. Returns None if no msg was sent
+pub fn parse_receive_nft<A: Api, T: DeserializeOwned>(
+    api: &A,
+    env: &Env,
+    allowed: &[CanonicalAddr],
+    wrapper: &Cw721ReceiveMsg,
+) -> StdResult<Option<T>> {
+    let contract_raw = api.canonical_address(&env.message.sender)?;
+    if !allowed.contains(&contract_raw) {
+        return Err(StdError::generic_err(format!(
+            "Tokens of {} are not accepted",
+            env.message.sender
+        )));
+    }
+    match &wrapper.msg {
+        Some(bin) => Ok(Some(from_binary(bin)?)),
+        None => Ok(None),
+    }
+}
+
 /// This is just a helper to properly serialize the above message.
 /// The actual receiver should include this variant in the larger HandleMsg enum
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -38,3 +64,54 @@ impl Cw721ReceiveMsg {
 enum ReceiverHandleMsg {
     ReceiveNft(Cw721ReceiveMsg),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+
+    #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+    #[serde(rename_all = "snake_case")]
+    enum HookMsg {
+        Stake { days: u32 },
+    }
+
+    #[test]
+    fn parsing_receive_nft() {
+        let deps = mock_dependencies(20, &[]);
+        let allowed = vec![deps.api.canonical_address(&"nft".into()).unwrap()];
+        let wrapper = Cw721ReceiveMsg {
+            sender: "owner".into(),
+            token_id: "LBJB1".into(),
+            msg: Some(to_binary(&HookMsg::Stake { days: 7 }).unwrap()),
+        };
+
+        let msg: Option<HookMsg> =
+            parse_receive_nft(&deps.api, &mock_env("nft", &[]), &allowed, &wrapper).unwrap();
+        assert_eq!(Some(HookMsg::Stake { days: 7 }), msg);
+
+        // only the allowed contracts can call it
+        let err = parse_receive_nft::<_, HookMsg>(
+            &deps.api,
+            &mock_env("owner", &[]),
+            &allowed,
+            &wrapper,
+        )
+        .unwrap_err();
+        match err {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg.as_str(), "Tokens of owner are not accepted")
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the msg is optional, but must be valid when set
+        let empty = Cw721ReceiveMsg { msg: None, ..wrapper.clone() };
+        let msg: Option<HookMsg> =
+            parse_receive_nft(&deps.api, &mock_env("nft", &[]), &allowed, &empty).unwrap();
+        assert_eq!(None, msg);
+        let invalid = Cw721ReceiveMsg { msg: Some(to_binary("stake").unwrap()), ..wrapper };
+        parse_receive_nft::<_, HookMsg>(&deps.api, &mock_env("nft", &[]), &allowed, &invalid)
+            .unwrap_err();
+    }
+}